@@ -0,0 +1,101 @@
+use std::{
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  thread::{self, JoinHandle},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use safav::{AudioListener, Host, MockClock, MockDevice, MockHost};
+
+/// Other readers polling alongside the measured one
+const READERS: [usize; 4] = [0, 1, 3, 7];
+
+/// Listens to a mock device that's only advanced by the benchmark
+fn host() -> (Host, MockClock, AudioListener<Vec<f32>>) {
+  let mock = MockHost::new().with_device(MockDevice::new("Speakers").with_block_size(512));
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+  let listener = host.create_listener::<Vec<f32>>();
+
+  host.listen().unwrap();
+
+  (host, clock, listener)
+}
+
+/// Runs `f` on `count` threads until the returned flag is set
+fn spawn(
+  count: usize,
+  f: impl Fn() + Send + Clone + 'static,
+) -> (Arc<AtomicBool>, Vec<JoinHandle<()>>) {
+  let stop = Arc::new(AtomicBool::new(false));
+  let threads = (0..count)
+    .map(|_| {
+      let stop = stop.clone();
+      let f = f.clone();
+
+      thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+          f();
+        }
+      })
+    })
+    .collect();
+
+  (stop, threads)
+}
+
+fn join((stop, threads): (Arc<AtomicBool>, Vec<JoinHandle<()>>)) {
+  stop.store(true, Ordering::Relaxed);
+
+  for thread in threads {
+    thread.join().unwrap();
+  }
+}
+
+/// Polls the listener while a writer publishes blocks and other readers poll it too,
+/// readers share a lock so they slow each other down but never the writer
+fn poll(c: &mut Criterion) {
+  let mut group = c.benchmark_group("poll");
+
+  for readers in READERS {
+    let (_host, clock, listener) = host();
+    let writer = spawn(1, move || clock.step());
+    let others = {
+      let listener = listener.clone();
+
+      spawn(readers, move || drop(listener.poll()))
+    };
+
+    group.bench_function(BenchmarkId::from_parameter(readers), |b| {
+      b.iter(|| listener.poll().len())
+    });
+
+    join(others);
+    join(writer);
+  }
+
+  group.finish();
+}
+
+/// Publishes blocks while readers poll, the writer never waits for them
+fn write(c: &mut Criterion) {
+  let mut group = c.benchmark_group("write");
+
+  for readers in READERS {
+    let (_host, clock, listener) = host();
+    let others = spawn(readers, move || drop(listener.poll()));
+
+    group.bench_function(BenchmarkId::from_parameter(readers), |b| {
+      b.iter(|| clock.step())
+    });
+
+    join(others);
+  }
+
+  group.finish();
+}
+
+criterion_group!(benches, poll, write);
+criterion_main!(benches);
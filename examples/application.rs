@@ -0,0 +1,44 @@
+use std::time::{Duration, Instant};
+
+use safav::Host;
+
+/// Pass part of an application name to capture it, otherwise the first one playing is captured
+fn main() -> safav::Result<()> {
+  let filter = std::env::args().nth(1).unwrap_or_default().to_lowercase();
+  let mut host = Host::new()?;
+  let listener = host.create_listener::<Vec<f32>>();
+  let applications = host.applications()?;
+
+  for application in &applications {
+    println!("{application} ({:?})", application.binary());
+  }
+
+  let Some(application) = applications
+    .iter()
+    .find(|application| application.name().to_lowercase().contains(&filter))
+  else {
+    println!("No application is playing");
+    return Ok(());
+  };
+
+  host.listen()?;
+  host.capture_application(application)?;
+
+  let timer = Instant::now();
+  let mut peak = 0f32;
+
+  while timer.elapsed() < Duration::from_secs(5) {
+    let Some(data) = listener.wait() else {
+      break;
+    };
+
+    peak = data.iter().fold(peak, |peak, value| peak.max(value.abs()));
+  }
+
+  println!("{application}: peak {peak:.3}");
+
+  // Moves the application back to the device it played on
+  host.stop_capturing_application()?;
+
+  Ok(())
+}
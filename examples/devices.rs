@@ -0,0 +1,66 @@
+use safav::{Device, DeviceKind, Host, MockDevice, MockHost};
+
+fn describe(device: &Device) -> String {
+  let mut details = vec![format!("id {}", device.id())];
+
+  if let Some(channels) = device.channels() {
+    details.push(format!("{channels} channels"));
+  }
+
+  if !device.sample_rates().is_empty() {
+    details.push(format!("{:?} hz", device.sample_rates()));
+  }
+
+  if !device.formats().is_empty() {
+    details.push(format!("{:?}", device.formats()));
+  }
+
+  if device.is_default() {
+    details.push(String::from("default"));
+  }
+
+  format!("{device} ({})", details.join(", "))
+}
+
+/// Lists devices grouped by their kind, like a device picker would
+fn main() -> safav::Result<()> {
+  let host = match std::env::args().nth(1).as_deref() {
+    Some("mock") => Host::mock(
+      MockHost::new()
+        .with_device(MockDevice::new("Speakers"))
+        .with_device(MockDevice::new("Microphone").with_channels(1)),
+    ),
+    _ => Host::new()?,
+  };
+
+  if let Some(device) = host.devices().first() {
+    println!("Listed by {}", device.backend());
+  }
+
+  let kinds = [
+    (DeviceKind::OutputMonitor, "Output monitors"),
+    (DeviceKind::Input, "Inputs"),
+    (DeviceKind::Virtual, "Virtual devices"),
+    (DeviceKind::Application, "Applications"),
+  ];
+
+  for (kind, title) in kinds {
+    let devices = host
+      .devices()
+      .iter()
+      .filter(|device| device.kind() == kind)
+      .collect::<Vec<_>>();
+
+    if devices.is_empty() {
+      continue;
+    }
+
+    println!("{title}:");
+
+    for device in devices {
+      println!("  {}", describe(device));
+    }
+  }
+
+  Ok(())
+}
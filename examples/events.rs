@@ -0,0 +1,34 @@
+use safav::{DeviceEvent, Host};
+
+/// Prints devices being plugged in and out, capture follows the default device meanwhile
+fn main() -> safav::Result<()> {
+  let mut host = Host::new()?;
+  let listener = host.create_listener::<Vec<f32>>();
+  let events = host.device_events()?;
+
+  host.listen()?;
+  host.follow_default_device(true)?;
+
+  while let Some(event) = events.recv() {
+    match event {
+      DeviceEvent::Added(device) => println!("Added {device}"),
+      DeviceEvent::Removed(device) => println!("Removed {device}"),
+      DeviceEvent::DefaultChanged(device) => println!("Default is now {device}"),
+    }
+
+    // Picks up the new devices, so the followed device shows up as the current one
+    host.refresh()?;
+
+    let peak = listener
+      .poll()
+      .iter()
+      .fold(0f32, |peak, value| peak.max(value.abs()));
+
+    match host.current_device() {
+      Some(device) => println!("Listening to {device}, peak {peak:.3}"),
+      None => println!("Not listening to any listed device"),
+    }
+  }
+
+  Ok(())
+}
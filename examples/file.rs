@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use safav::{Error, Host, Pace};
+
+fn main() -> safav::Result<()> {
+  let path = std::env::args()
+    .nth(1)
+    .ok_or_else(|| Error::NoDeviceFound(String::from("<path>")))?;
+
+  let mut host = Host::new()?;
+  let player = host.add_file(&path)?;
+  let listener = host.create_listener::<Vec<f32>>();
+
+  player.set_looping(true);
+  player.set_pace(Pace::RealTime);
+
+  host.listen()?;
+  host.change_device(&player.device().clone())?;
+
+  println!(
+    "Playing {} ({} hz, {} channels, {:?})",
+    player.device(),
+    player.sample_rate(),
+    player.channels(),
+    player.duration(),
+  );
+
+  while let Some(data) = listener.wait() {
+    let peak = data.iter().fold(0f32, |peak, value| peak.max(value.abs()));
+
+    println!("{:>8.2?} peak {peak:.3}", player.position());
+
+    // Skips ahead every few seconds to show seeking
+    if player.position() > Duration::from_secs(10) {
+      player.seek(Duration::ZERO);
+    }
+  }
+
+  Ok(())
+}
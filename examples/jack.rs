@@ -0,0 +1,42 @@
+use std::time::{Duration, Instant};
+
+use safav::{Device, Host};
+
+/// Run with `jackd -d dummy -r 48000` for a server without any audio hardware
+fn main() -> safav::Result<()> {
+  let mut host = Host::jack()?;
+  let listener = host.create_listener::<Vec<f32>>();
+
+  for device in host.devices() {
+    println!("{device}");
+  }
+
+  host.listen()?;
+
+  // The dummy driver has capture ports too, these are captured like any other port
+  let ports = Device::jack_ports(&["system:capture_1", "system:capture_2"]);
+
+  for device in host.devices().clone().into_iter().chain([ports]) {
+    host.change_device(&device)?;
+
+    let timer = Instant::now();
+    let mut peak = 0f32;
+
+    while timer.elapsed() < Duration::from_secs(1) {
+      let Some(data) = listener.wait() else {
+        break;
+      };
+
+      peak = data.iter().fold(peak, |peak, value| peak.max(value.abs()));
+    }
+
+    println!("{device}: peak {peak:.3}");
+  }
+
+  // Clients started since the host was created show up after a refresh
+  host.refresh()?;
+
+  println!("{} devices after refresh", host.devices().len());
+
+  Ok(())
+}
@@ -0,0 +1,54 @@
+use std::{thread, time::Duration};
+
+use safav::{Clock, Host, MockDevice, MockHost, Signal, State};
+
+/// Pauses, resumes and stops listening to a mock device, printing the state of the host after every step
+fn main() -> safav::Result<()> {
+  let sine = Signal::Sine {
+    frequency: 440.,
+    amplitude: 0.5,
+  };
+  let mock = MockHost::new()
+    .with_clock(Clock::RealTime)
+    .with_device(MockDevice::new("Speakers").with_signal(sine));
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+  let listener = host.create_listener::<Vec<f32>>();
+
+  println!("Created: {:?}", host.state());
+
+  host.listen()?;
+  assert!(listener.wait_timeout(Duration::from_secs(1)).is_some());
+  println!("Listening: {:?}", host.state());
+
+  host.pause()?;
+  let sequence = listener.sequence();
+  let samples = listener.poll().len();
+
+  thread::sleep(Duration::from_millis(200));
+  assert!(listener.is_paused());
+  assert_eq!(listener.sequence(), sequence);
+  assert_eq!(listener.poll().len(), samples);
+  println!("Paused: {:?}, kept {samples} samples", host.state());
+
+  host.resume()?;
+  assert!(!listener.is_paused());
+  assert!(listener.wait_timeout(Duration::from_secs(1)).is_some());
+  println!("Resumed: {:?}", host.state());
+
+  clock.fail("injected error");
+  assert_eq!(host.state(), State::Error(String::from("injected error")));
+  println!("Failed: {:?}", host.state());
+
+  host.stop()?;
+  assert_eq!(host.state(), State::Idle);
+  println!("Stopped: {:?}", host.state());
+
+  assert!(host.pause().is_err());
+
+  host.listen()?;
+  assert!(listener.wait_timeout(Duration::from_secs(1)).is_some());
+  println!("Listening again: {:?}", host.state());
+
+  Ok(())
+}
@@ -241,12 +241,13 @@ fn ui(ui: &Ui, settings: &mut Settings, host: &Host) {
   }
 }
 
-fn update(listener: &AudioListener<CustomData>, settings: &Settings) {
-  let audio = listener.poll();
+/// Sends the fft size once it changes, `sent` is the last size sent to the audio callback
+fn update(listener: &AudioListener<CustomData>, settings: &Settings, sent: &mut usize) {
+  if *sent != settings.fft_size {
+    let size = settings.fft_size;
 
-  if audio.fft_size != settings.fft_size {
-    let mut audio = listener.poll_mut();
-    audio.fft_size = settings.fft_size;
+    *sent = size;
+    listener.modify(move |audio| audio.fft_size = size);
   }
 }
 
@@ -254,7 +255,8 @@ async fn _main() -> safav::Result<!> {
   let mut settings = Settings::default();
   let mut host = Host::new()?;
   let imgui = imgui_macroquad::get_imgui_context();
-  let listener = host.create_listener();
+  let listener = host.create_listener::<CustomData>();
+  let mut fft_size = listener.poll().fft_size;
 
   host.listen()?;
 
@@ -270,7 +272,7 @@ async fn _main() -> safav::Result<!> {
         .build(|| self::ui(ui, &mut settings, &host));
     });
 
-    update(&listener, &settings);
+    update(&listener, &settings, &mut fft_size);
     visualize(&listener, &settings);
 
     imgui.draw();
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use safav::{Clock, Host, MockDevice, MockHost, Signal};
+
+fn main() -> safav::Result<()> {
+  let mock = MockHost::new()
+    .with_device(MockDevice::new("Sine").with_signal(Signal::Sine {
+      frequency: 440.,
+      amplitude: 0.5,
+    }))
+    .with_device(
+      MockDevice::new("Mono sweep")
+        .with_sample_rate(44100)
+        .with_channels(1)
+        .with_signal(Signal::Sweep {
+          start: 20.,
+          end: 20000.,
+          duration: Duration::from_secs(1),
+          amplitude: 1.,
+        }),
+    );
+
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+  let listener = host.create_listener::<Vec<f32>>();
+  let receiver = host.subscribe(64);
+
+  host.listen()?;
+
+  for device in host.devices().clone() {
+    host.change_device(&device)?;
+
+    // Nothing happens until the clock is moved forward
+    clock.advance(Duration::from_millis(100));
+
+    let mut blocks = 0;
+
+    while let Some(block) = receiver.try_recv() {
+      blocks += 1;
+
+      assert_eq!(block.overruns, 0);
+    }
+
+    let peak = listener
+      .poll()
+      .iter()
+      .fold(0f32, |peak, value| peak.max(value.abs()));
+
+    println!(
+      "{device}: {blocks} blocks, {} frames, last block peak {peak:.3}",
+      clock.position()
+    );
+  }
+
+  // Generates audio on its own like a real device
+  let mock = MockHost::new()
+    .with_clock(Clock::RealTime)
+    .with_device(MockDevice::new("Noise").with_signal(Signal::Noise { amplitude: 0.25 }));
+
+  let mut host = Host::mock(mock);
+  let listener = host.create_listener::<Vec<f32>>();
+
+  host.listen()?;
+
+  for _ in 0..10 {
+    let Some(data) = listener.wait() else {
+      break;
+    };
+    let rms = (data.iter().map(|value| value * value).sum::<f32>() / data.len() as f32).sqrt();
+
+    println!("[{}] real time noise rms {rms:.3}", data.sequence());
+  }
+
+  Ok(())
+}
@@ -0,0 +1,38 @@
+use std::{fs::File, io::BufWriter, time::Instant};
+
+use safav::{Error, Offline, OutputFormat};
+
+fn main() -> safav::Result<()> {
+  let mut args = std::env::args().skip(1);
+  let input = args
+    .next()
+    .ok_or_else(|| Error::NoDeviceFound(String::from("<input>")))?;
+  let output = args
+    .next()
+    .ok_or_else(|| Error::NoDeviceFound(String::from("<output>")))?;
+  let frame_rate = args
+    .next()
+    .and_then(|rate| rate.parse().ok())
+    .unwrap_or(60.);
+
+  // Picks the format from the extension of the output
+  let format = if output.ends_with(".csv") {
+    OutputFormat::Csv
+  } else {
+    OutputFormat::JsonLines
+  };
+
+  let started = Instant::now();
+  let mut offline = Offline::new(BufWriter::new(File::create(&output)?), format, frame_rate)?;
+
+  offline.analyze_file(&input)?;
+
+  let frames = offline.finish()?;
+
+  println!(
+    "Wrote {frames} frames at {frame_rate} fps to {output} in {:?}",
+    started.elapsed()
+  );
+
+  Ok(())
+}
@@ -0,0 +1,38 @@
+use std::time::{Duration, Instant};
+
+use safav::Host;
+
+fn main() -> safav::Result<()> {
+  let mut host = Host::pipewire()?;
+  let listener = host.create_listener::<Vec<f32>>();
+
+  for device in host.devices() {
+    println!("{device}");
+  }
+
+  host.listen()?;
+
+  for device in host.devices().clone() {
+    host.change_device(&device)?;
+
+    let timer = Instant::now();
+    let mut peak = 0f32;
+
+    while timer.elapsed() < Duration::from_secs(1) {
+      let Some(data) = listener.wait() else {
+        break;
+      };
+
+      peak = data.iter().fold(peak, |peak, value| peak.max(value.abs()));
+    }
+
+    println!("{device}: peak {peak:.3}");
+  }
+
+  // Nodes added or removed since the host was created show up after a refresh
+  host.refresh()?;
+
+  println!("{} devices after refresh", host.devices().len());
+
+  Ok(())
+}
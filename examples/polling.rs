@@ -1,5 +1,25 @@
+use safav::Host;
 
 fn main() -> safav::Result<()> {
+  let mut host = Host::new()?;
+  let listener = host.create_listener::<Vec<f32>>();
+
+  host.listen()?;
+
+  // Sleeps until the audio callback produces a new frame instead of spinning
+  while let Some(data) = listener.wait() {
+    let peak = data
+      .iter()
+      .copied()
+      .fold(0f32, |peak, value| peak.max(value.abs()));
+
+    println!(
+      "[{}] {} samples, peak {peak:.3}, missed {}",
+      data.sequence(),
+      data.len(),
+      listener.missed_frames(),
+    );
+  }
 
   Ok(())
 }
@@ -0,0 +1,38 @@
+use std::{thread, time::Duration};
+
+use safav::Host;
+
+fn main() -> safav::Result<()> {
+  let mut host = Host::new()?;
+  // Keeps the last 10 seconds around
+  let recorder = host.create_recorder(Some(Duration::from_secs(10)))?;
+
+  host.listen()?;
+
+  recorder.start("recording.wav")?;
+  println!("Recording 5 seconds to recording.wav");
+
+  thread::sleep(Duration::from_secs(5));
+
+  if let Some(recording) = recorder.stop()? {
+    println!(
+      "Wrote {:?} ({} hz, {} channels, {:?})",
+      recording.path,
+      recording.sample_rate,
+      recording.channels,
+      recording.duration()
+    );
+  }
+
+  thread::sleep(Duration::from_secs(5));
+
+  let history = recorder.save_history("history.wav")?;
+
+  println!(
+    "Saved the last {:?} to {:?}",
+    history.duration(),
+    history.path
+  );
+
+  Ok(())
+}
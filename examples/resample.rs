@@ -0,0 +1,44 @@
+use safav::{Host, MockDevice, MockHost, Signal};
+
+fn main() -> safav::Result<()> {
+  let signal = Signal::Sine {
+    frequency: 440.,
+    amplitude: 0.5,
+  };
+  let mock = [22050, 44100, 48000, 96000]
+    .into_iter()
+    .fold(MockHost::new(), |mock, rate| {
+      mock.with_device(
+        MockDevice::new(format!("{rate}hz"))
+          .with_sample_rate(rate)
+          .with_block_size(rate as usize / 100)
+          .with_signal(signal.clone()),
+      )
+    });
+
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+  let raw = host.create_listener::<Vec<f32>>();
+  // About 10ms at 44100 per block, whatever rate the device runs at
+  let resampled = host.create_resampled_listener::<Vec<f32>>(44100);
+
+  host.listen()?;
+
+  for device in host.devices().clone() {
+    host.change_device(&device)?;
+
+    // A few blocks so the resampler is past its latency
+    for _ in 0..4 {
+      clock.step();
+    }
+
+    println!(
+      "{:>8}: {:>4} samples per block, {:>4} after resampling",
+      device.name(),
+      raw.poll().len(),
+      resampled.poll().len()
+    );
+  }
+
+  Ok(())
+}
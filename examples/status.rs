@@ -0,0 +1,48 @@
+use std::{thread, time::Duration};
+
+use safav::{AudioData, Clock, Host, MockDevice, MockHost, Status, SupervisorSettings};
+
+/// Takes longer than a block lasts, so the worker falls behind
+#[derive(Debug, Clone, Default)]
+struct Slow;
+
+impl AudioData for Slow {
+  fn update(&mut self, _: &[f32]) {
+    thread::sleep(Duration::from_millis(30));
+  }
+}
+
+/// Prints what happens to a mock stream through a handler and a receiver of statuses
+fn main() -> safav::Result<()> {
+  let mock = MockHost::new()
+    .with_clock(Clock::RealTime)
+    .with_device(MockDevice::new("Speakers"));
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+
+  host.on_status(|status| println!("handler: {status}"));
+
+  let statuses = host.statuses();
+  let _slow = host.create_listener::<Slow>();
+
+  host.enable_worker(2)?;
+  host.listen()?;
+  host.supervise(SupervisorSettings::default())?;
+
+  thread::sleep(Duration::from_millis(200));
+  clock.fail("injected error");
+  thread::sleep(Duration::from_millis(500));
+
+  let mut dropped = 0;
+
+  while let Some(status) = statuses.try_recv() {
+    match status {
+      Status::Dropped { blocks } => dropped += blocks,
+      status => println!("receiver: {status:?}"),
+    }
+  }
+
+  println!("receiver: {dropped} blocks dropped in total");
+
+  Ok(())
+}
@@ -0,0 +1,21 @@
+use futures::{executor::block_on, StreamExt};
+
+use safav::Host;
+
+fn main() -> safav::Result<()> {
+  let mut host = Host::new()?;
+  let mut stream = host.create_stream::<Vec<f32>>(8);
+
+  host.listen()?;
+
+  block_on(async {
+    while let Some(frame) = stream.next().await {
+      match frame {
+        Ok(data) => println!("{} samples", data.len()),
+        Err(lagged) => eprintln!("{lagged}"),
+      }
+    }
+  });
+
+  Ok(())
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use safav::{
+  Clock, Host, MockDevice, MockHost, Signal, StreamState, StreamStates, SupervisorSettings,
+};
+
+/// Prints states until one matches `until`, panics if it takes longer than a few seconds
+fn wait_for(states: &StreamStates, until: impl Fn(&StreamState) -> bool) -> StreamState {
+  loop {
+    let state = states
+      .recv_timeout(Duration::from_secs(5))
+      .expect("supervisor stopped sending states");
+
+    println!("  {state:?}");
+
+    if until(&state) {
+      return state;
+    }
+  }
+}
+
+fn running_on(name: &str) -> impl Fn(&StreamState) -> bool + '_ {
+  move |state| matches!(state, StreamState::Running(device) if device.name() == name)
+}
+
+/// Injects failures into a mock device and checks that the stream recovers from them
+fn main() -> safav::Result<()> {
+  let sine = Signal::Sine {
+    frequency: 440.,
+    amplitude: 0.5,
+  };
+  let mock = MockHost::new()
+    .with_clock(Clock::RealTime)
+    .with_device(MockDevice::new("Speakers").with_signal(sine.clone()))
+    .with_device(MockDevice::new("Headphones").with_signal(sine));
+
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+  let listener = host.create_listener::<Vec<f32>>();
+  let states = host.stream_states();
+
+  host.listen()?;
+  host.change_device_by_index(1)?;
+  host.supervise(SupervisorSettings {
+    stall_timeout: Duration::from_millis(200),
+    backoff: Duration::from_millis(50),
+    attempts_per_device: 2,
+    max_attempts: Some(5),
+    ..Default::default()
+  })?;
+
+  wait_for(&states, running_on("Headphones"));
+
+  println!("Stream error:");
+  clock.fail("injected error");
+  wait_for(&states, running_on("Headphones"));
+
+  println!("Stall:");
+  clock.stall();
+  wait_for(&states, running_on("Headphones"));
+
+  println!("Headphones unplugged:");
+  clock.set_unavailable("Headphones", true);
+  wait_for(&states, running_on("Speakers"));
+
+  assert_eq!(
+    host.current_device().map(|device| device.name()),
+    Some("Speakers")
+  );
+  assert!(listener.wait_timeout(Duration::from_secs(1)).is_some());
+
+  println!("Every device unplugged:");
+  clock.set_unavailable("Speakers", true);
+  wait_for(&states, |state| *state == StreamState::GaveUp);
+
+  Ok(())
+}
@@ -0,0 +1,300 @@
+use std::collections::VecDeque;
+
+use crate::{
+  AudioListener, Bands, BlockInfo, BlockSize, Error, Listener, MixChannels, Processor, Result,
+  Spectrum, FFT,
+};
+
+/// Settings of [Analyze], the same settings give the same features live and offline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalysisSettings {
+  /// Rate every source is resampled to before it's analyzed
+  pub sample_rate: u32,
+  /// Frames in every analyzed window, also the size of the [FFT],
+  /// has to be between 1 and [FFT::MAX_SIZE]
+  pub window: usize,
+  /// Frames between the start of two windows
+  pub hop: usize,
+  /// Amount of logarithmically spaced bands
+  pub bands: usize,
+  /// How far the spectral flux has to rise above its recent average to count as an onset
+  pub onset_threshold: f32,
+  /// Seconds of spectral flux the tempo is estimated from
+  pub tempo_window: f32,
+  pub min_bpm: f32,
+  pub max_bpm: f32,
+}
+
+impl AnalysisSettings {
+  /// Size of the blocks [Analyze] expects
+  pub fn block_size(&self) -> BlockSize {
+    BlockSize::new(self.window).with_hop(self.hop)
+  }
+
+  /// Amount of windows analyzed every second
+  pub fn windows_per_second(&self) -> f32 {
+    self.sample_rate as f32 / self.hop.max(1) as f32
+  }
+
+  /// Amount of windows the tempo is estimated from
+  fn tempo_windows(&self) -> usize {
+    ((self.tempo_window * self.windows_per_second()).ceil() as usize).max(1)
+  }
+
+  /// Shortest and longest flux period in windows the tempo is looked for in
+  fn lags(&self) -> (usize, usize) {
+    let per_second = self.windows_per_second();
+    let min_lag = ((60. / self.max_bpm.max(1.)) * per_second).floor().max(1.) as usize;
+    let max_lag = ((60. / self.min_bpm.max(1.)) * per_second).ceil() as usize;
+
+    (min_lag, max_lag)
+  }
+}
+
+impl Default for AnalysisSettings {
+  fn default() -> Self {
+    Self {
+      sample_rate: 44100,
+      window: 2048,
+      hop: 512,
+      bands: 16,
+      onset_threshold: 1.5,
+      tempo_window: 8.,
+      min_bpm: 60.,
+      max_bpm: 200.,
+    }
+  }
+}
+
+/// Features of one analyzed window
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Features {
+  /// Position of the first frame of the window at the analysis rate
+  pub position: u64,
+  /// Frames in the window
+  pub frames: usize,
+  pub rms: f32,
+  pub bands: Vec<f32>,
+  /// Sum of how much every band rose since the last window
+  pub flux: f32,
+  pub onset: bool,
+  /// Estimated tempo, `None` until enough windows were analyzed
+  pub bpm: Option<f32>,
+}
+
+/// Extracts [Features] from windows of interleaved samples,
+/// expects blocks of [AnalysisSettings::block_size] at [AnalysisSettings::sample_rate]
+///
+/// Use [Listener::create_analysis] to run it live and [Offline](crate::Offline) to run it over files
+#[derive(Debug)]
+pub struct Analyze {
+  settings: AnalysisSettings,
+  mix: MixChannels,
+  spectrum: Spectrum,
+  bands: Bands,
+  mono: Vec<f32>,
+  magnitudes: Vec<f32>,
+  previous: Vec<f32>,
+  /// Spectral flux of the last windows, newest last
+  history: VecDeque<f32>,
+  /// Sum of `history`
+  sum: f64,
+  /// Sum of the products of every two fluxes in `history` that are `lag` windows apart,
+  /// kept up to date as fluxes come and go so [Self::bpm] doesn't have to go over every pair
+  products: Vec<f64>,
+  /// Autocorrelation of the centered history for every lag, only reused by [Self::bpm]
+  correlations: Vec<f32>,
+  /// Windows since the last onset
+  since_onset: usize,
+}
+
+impl Analyze {
+  /// Fails if [AnalysisSettings::window] isn't between 1 and [FFT::MAX_SIZE],
+  /// everything the tempo estimation needs is allocated here instead of in the audio callback
+  pub fn new(settings: AnalysisSettings) -> Result<Self> {
+    if !(1..=<FFT>::MAX_SIZE).contains(&settings.window) {
+      return Err(Error::InvalidAnalysisWindow(settings.window));
+    }
+
+    // Refining the best lag looks at the lag after it
+    let lags = settings.lags().1 + 2;
+
+    Ok(Self {
+      settings,
+      mix: MixChannels,
+      spectrum: Spectrum::new(settings.window),
+      bands: Bands::new(settings.bands),
+      mono: Vec::new(),
+      magnitudes: Vec::new(),
+      previous: Vec::new(),
+      history: VecDeque::with_capacity(settings.tempo_windows() + 1),
+      sum: 0.,
+      products: vec![0.; lags],
+      correlations: vec![0.; lags],
+      since_onset: usize::MAX,
+    })
+  }
+
+  pub fn settings(&self) -> &AnalysisSettings {
+    &self.settings
+  }
+
+  /// Whether the newest flux peaks above the average of the last ~250ms,
+  /// ignoring changes that are tiny next to the loudest flux in the tempo window
+  /// and anything within ~100ms of the last onset
+  fn onset(&self, flux: f32) -> bool {
+    let recent = (self.settings.windows_per_second() / 4.).ceil().max(1.) as usize;
+
+    if self.since_onset < recent / 2 {
+      return false;
+    }
+    let previous = self.history.iter().rev().skip(1);
+    let count = previous.clone().take(recent).count();
+
+    if count == 0 {
+      return false;
+    }
+
+    let average = previous.clone().take(recent).sum::<f32>() / count as f32;
+    let last = previous.clone().next().copied().unwrap_or_default();
+    let max = self.history.iter().fold(0f32, |max, flux| max.max(*flux));
+
+    flux > average * self.settings.onset_threshold && flux > last && flux > max * 0.1
+  }
+
+  /// Adds the newest flux and drops the oldest ones past the tempo window,
+  /// updating the sums [Self::bpm] works from
+  fn push_flux(&mut self, flux: f32) {
+    let len = self.history.len();
+
+    for (lag, product) in self.products.iter_mut().enumerate().skip(1) {
+      if lag <= len {
+        *product += flux as f64 * self.history[len - lag] as f64;
+      }
+    }
+
+    self.history.push_back(flux);
+    self.sum += flux as f64;
+
+    while self.history.len() > self.settings.tempo_windows() {
+      let Some(oldest) = self.history.pop_front() else {
+        break;
+      };
+
+      for (lag, product) in self.products.iter_mut().enumerate().skip(1) {
+        if let Some(later) = self.history.get(lag - 1) {
+          *product -= oldest as f64 * *later as f64;
+        }
+      }
+
+      self.sum -= oldest as f64;
+    }
+  }
+
+  /// Estimates the tempo from the autocorrelation of the flux history,
+  /// only goes over the lags and the ends of the history instead of every pair of fluxes
+  fn bpm(&mut self) -> Option<f32> {
+    let per_second = self.settings.windows_per_second();
+    let (min_lag, max_lag) = self.settings.lags();
+    let len = self.history.len();
+
+    if len < max_lag * 2 || min_lag >= max_lag {
+      return None;
+    }
+
+    let mean = self.sum / len as f64;
+    let (mut first, mut last) = (0., 0.);
+
+    // Correlation of the history minus its mean, from the sums of the fluxes
+    // in the `lag` windows at either end that are left out of the pairs
+    for lag in 1..self.correlations.len() {
+      first += self.history[lag - 1] as f64;
+      last += self.history[len - lag] as f64;
+
+      let pairs = (len - lag) as f64;
+      let sums = (self.sum - last) + (self.sum - first);
+
+      self.correlations[lag] = (self.products[lag] - mean * sums + pairs * mean * mean) as f32;
+    }
+
+    let correlation = |lag: usize| self.correlations[lag];
+    let (lag, best) = (min_lag..=max_lag)
+      .map(|lag| (lag, correlation(lag)))
+      .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    if best <= 0. {
+      return None;
+    }
+
+    // Refines the lag between the neighbouring lags
+    let before = correlation(lag - 1);
+    let after = correlation(lag + 1);
+    let curve = before - 2. * best + after;
+    let offset = if curve.abs() > f32::EPSILON {
+      (0.5 * (before - after) / curve).clamp(-0.5, 0.5)
+    } else {
+      0.
+    };
+
+    Some(60. * per_second / (lag as f32 + offset))
+  }
+}
+
+impl Processor for Analyze {
+  type Input = Vec<f32>;
+  type Output = Features;
+
+  fn process(&mut self, input: &Vec<f32>, output: &mut Features, info: &mut BlockInfo) {
+    let mut mono_info = *info;
+
+    self.mix.process(input, &mut self.mono, &mut mono_info);
+    self
+      .spectrum
+      .process(&self.mono, &mut self.magnitudes, &mut mono_info);
+    self
+      .bands
+      .process(&self.magnitudes, &mut output.bands, &mut mono_info);
+
+    let flux = if self.previous.len() == output.bands.len() {
+      output
+        .bands
+        .iter()
+        .zip(&self.previous)
+        .map(|(band, previous)| (band - previous).max(0.))
+        .sum()
+    } else {
+      0.
+    };
+
+    self.previous.clone_from(&output.bands);
+    self.push_flux(flux);
+
+    output.position = info.position;
+    output.frames = self.mono.len();
+    output.rms = (self.mono.iter().map(|sample| sample * sample).sum::<f32>()
+      / self.mono.len().max(1) as f32)
+      .sqrt();
+    output.flux = flux;
+    output.onset = self.onset(flux);
+    self.since_onset = match output.onset {
+      true => 0,
+      false => self.since_onset.saturating_add(1),
+    };
+    output.bpm = self.bpm();
+  }
+}
+
+impl Listener {
+  /// Creates a listener that's updated with the [Features] of every window,
+  /// it resamples and chunks the same way [Offline](crate::Offline) does
+  pub fn create_analysis(&self, settings: AnalysisSettings) -> Result<AudioListener<Features>> {
+    Ok(
+      self
+        .pipeline()
+        .sample_rate(settings.sample_rate)
+        .block_size(settings.block_size())
+        .then(Analyze::new(settings)?)
+        .build(),
+    )
+  }
+}
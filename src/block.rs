@@ -0,0 +1,269 @@
+use std::{
+  fmt::{Debug, Formatter},
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+use crate::{listener::AudioListenerTrait, notify::Notify, ring::Ring, Listener, ListenerId};
+
+/// Information about a block of samples given to the audio callback
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockInfo {
+  /// Amount of interleaved channels
+  pub channels: u16,
+  pub sample_rate: u32,
+  /// Amount of frames given to listeners before this block since the stream was built,
+  /// doesn't count blocks while the host is paused or another source is selected
+  /// and starts over when the stream is rebuilt, like after changing devices
+  pub position: u64,
+  /// When the audio callback received this block
+  pub timestamp: Instant,
+}
+
+impl Default for BlockInfo {
+  fn default() -> Self {
+    Self {
+      channels: 2,
+      sample_rate: 44100,
+      position: 0,
+      timestamp: Instant::now(),
+    }
+  }
+}
+
+/// Block of interleaved samples received by a [BlockReceiver]
+#[derive(Debug, Clone, Default)]
+pub struct Block {
+  pub samples: Vec<f32>,
+  pub info: BlockInfo,
+  /// Blocks dropped right before this one because the receiver or the processing worker fell behind
+  pub overruns: u64,
+}
+
+impl Block {
+  /// Gets the amount of frames (samples per channel) in this block
+  pub fn frames(&self) -> usize {
+    self.samples.len() / self.info.channels.max(1) as usize
+  }
+
+  /// Gets how long this block is
+  pub fn duration(&self) -> Duration {
+    Duration::from_secs_f64(self.frames() as f64 / self.info.sample_rate.max(1) as f64)
+  }
+}
+
+struct BlockShared {
+  id: ListenerId,
+  /// Blocks dropped since the last pushed block, only touched by the audio callback
+  dropped: Mutex<u64>,
+  queue: Ring<Block>,
+  notify: Notify,
+  overruns: AtomicU64,
+  /// Blocks pushed to the queue so far
+  pushed: AtomicU64,
+  closed: AtomicBool,
+}
+
+impl Debug for BlockShared {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("BlockShared")
+      .field("id", &self.id)
+      .field("queued", &self.queue.len())
+      .field("overruns", &self.overruns)
+      .field("closed", &self.closed)
+      .finish()
+  }
+}
+
+impl AudioListenerTrait for BlockShared {
+  fn update(&self, data: &[f32], info: &BlockInfo) {
+    let Ok(mut dropped) = self.dropped.try_lock() else {
+      return;
+    };
+
+    let pushed = self.queue.push_with(|block| {
+      // Only allocates if the block is bigger than any block before it
+      block.samples.clear();
+      block.samples.extend_from_slice(data);
+      block.info = *info;
+      block.overruns = *dropped;
+    });
+
+    if pushed {
+      *dropped = 0;
+      self.pushed.fetch_add(1, Ordering::Release);
+      self.notify.notify();
+    } else {
+      *dropped += 1;
+      self.overruns.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  fn is_active(&self) -> bool {
+    !self.is_detached()
+  }
+
+  fn is_detached(&self) -> bool {
+    self.closed.load(Ordering::SeqCst)
+  }
+
+  fn detach(&self) {
+    self.closed.store(true, Ordering::SeqCst);
+    self.notify.notify();
+  }
+
+  fn skipped(&self, blocks: u64) {
+    *self.dropped.lock().unwrap() += blocks;
+    self.overruns.fetch_add(blocks, Ordering::Relaxed);
+  }
+}
+
+/// Receives every block of samples from the audio callback in order,
+/// up to `capacity` blocks are kept, anything past that is dropped and counted as an overrun
+/// so the audio callback never has to wait
+pub struct BlockReceiver {
+  shared: Arc<BlockShared>,
+}
+
+impl BlockReceiver {
+  pub(crate) fn new(listener: &Listener, capacity: usize) -> Self {
+    let id = listener.next_id();
+    let shared = Arc::new(BlockShared {
+      id,
+      dropped: Mutex::new(0),
+      queue: Ring::new(capacity, Block::default),
+      notify: Default::default(),
+      overruns: AtomicU64::new(0),
+      pushed: AtomicU64::new(0),
+      closed: AtomicBool::new(false),
+    });
+
+    listener.register(id, shared.clone());
+
+    Self { shared }
+  }
+
+  /// Gets the id of this receiver, can be used with [Listener::remove]
+  pub fn id(&self) -> ListenerId {
+    self.shared.id
+  }
+
+  /// Gets the next block without waiting
+  pub fn try_recv(&self) -> Option<Block> {
+    self.shared.queue.pop_with(|block| block.clone())
+  }
+
+  /// Same as [Self::try_recv] but swaps the samples into `block`,
+  /// so neither side has to allocate once both buffers are big enough
+  pub fn try_recv_into(&self, block: &mut Block) -> bool {
+    self
+      .shared
+      .queue
+      .pop_with(|next| std::mem::swap(next, block))
+      .is_some()
+  }
+
+  /// Waits for the next block, `None` once the receiver was removed and is empty
+  pub fn recv(&self) -> Option<Block> {
+    self.recv_with(None, || self.try_recv())
+  }
+
+  /// Same as [Self::recv] but gives up after `timeout`
+  pub fn recv_timeout(&self, timeout: Duration) -> Option<Block> {
+    self.recv_with(Some(timeout), || self.try_recv())
+  }
+
+  /// Same as [Self::recv] but swaps the samples into `block`, see [Self::try_recv_into]
+  pub fn recv_into(&self, block: &mut Block) -> bool {
+    self
+      .recv_with(None, || self.try_recv_into(block).then_some(()))
+      .is_some()
+  }
+
+  /// Same as [Self::recv_into] but gives up after `timeout`
+  pub fn recv_timeout_into(&self, block: &mut Block, timeout: Duration) -> bool {
+    self
+      .recv_with(Some(timeout), || self.try_recv_into(block).then_some(()))
+      .is_some()
+  }
+
+  /// Waits and tries again until `recv` gets a block, the receiver is closed or `timeout` passes,
+  /// since another thread can take the block between waking up and receiving it
+  fn recv_with<R>(
+    &self,
+    timeout: Option<Duration>,
+    mut recv: impl FnMut() -> Option<R>,
+  ) -> Option<R> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+      if let Some(value) = recv() {
+        return Some(value);
+      }
+
+      if self.shared.is_detached() {
+        return None;
+      }
+
+      let timeout = match deadline {
+        Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+          Some(timeout) if !timeout.is_zero() => Some(timeout),
+          _ => return None,
+        },
+        None => None,
+      };
+
+      self.shared.notify.wait_until(timeout, || {
+        self.shared.queue.len() > 0 || self.shared.is_detached()
+      });
+    }
+  }
+
+  /// Gets how many blocks are waiting to be received
+  pub fn len(&self) -> usize {
+    self.shared.queue.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Gets how many blocks were dropped in total because the receiver
+  /// or the processing worker fell behind
+  pub fn overruns(&self) -> u64 {
+    self.shared.overruns.load(Ordering::Relaxed)
+  }
+
+  /// Gets how many blocks were queued for this receiver so far, not counting overruns
+  #[cfg(feature = "record")]
+  pub(crate) fn pushed(&self) -> u64 {
+    self.shared.pushed.load(Ordering::Acquire)
+  }
+
+  /// Whether this receiver was removed and won't receive any more blocks
+  pub fn is_closed(&self) -> bool {
+    self.shared.is_detached()
+  }
+}
+
+impl Drop for BlockReceiver {
+  fn drop(&mut self) {
+    self.shared.closed.store(true, Ordering::SeqCst);
+  }
+}
+
+impl Debug for BlockReceiver {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    Debug::fmt(&self.shared, f)
+  }
+}
+
+impl Listener {
+  /// Creates a [BlockReceiver] that keeps up to `capacity` blocks
+  pub fn subscribe(&self, capacity: usize) -> BlockReceiver {
+    BlockReceiver::new(self, capacity)
+  }
+}
@@ -0,0 +1,146 @@
+use std::{
+  cell::UnsafeCell,
+  ops::Deref,
+  sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+};
+
+struct Slot<T> {
+  cell: UnsafeCell<Frame<T>>,
+  /// Readers holding a [ReadGuard] of this slot, the writer skips it while there are any
+  readers: AtomicUsize,
+}
+
+struct Frame<T> {
+  sequence: u64,
+  value: T,
+}
+
+/// Wait-free triple buffer, readers pin the published slot while they read it
+/// and the writer fills whichever other slot nobody reads, so neither can block the other
+pub(crate) struct TripleBuffer<T> {
+  slots: [Slot<T>; 3],
+  sequence: AtomicU64,
+  published: AtomicU8,
+  writing: AtomicBool,
+}
+
+// SAFETY: the writer only touches a slot that isn't published and has no readers,
+// readers only touch the published slot while they're counted in it
+unsafe impl<T: Send> Send for TripleBuffer<T> {}
+unsafe impl<T: Send + Sync> Sync for TripleBuffer<T> {}
+
+impl<T: Default> Default for TripleBuffer<T> {
+  fn default() -> Self {
+    Self::new(T::default(), T::default(), T::default())
+  }
+}
+
+impl<T> TripleBuffer<T> {
+  pub fn new(a: T, b: T, c: T) -> Self {
+    let slot = |value| Slot {
+      cell: UnsafeCell::new(Frame { sequence: 0, value }),
+      readers: AtomicUsize::new(0),
+    };
+
+    Self {
+      slots: [slot(a), slot(b), slot(c)],
+      sequence: AtomicU64::new(0),
+      published: AtomicU8::new(0),
+      writing: AtomicBool::new(false),
+    }
+  }
+
+  /// Writes into a free slot and publishes it, returns `false` without waiting
+  /// if another write is in progress or readers still hold both other slots
+  pub fn write(&self, f: impl FnOnce(&mut T)) -> bool {
+    if self.writing.swap(true, Ordering::Acquire) {
+      return false;
+    }
+
+    let published = self.published.load(Ordering::Relaxed);
+    // Pairs with the increment in `read`, a reader that shows up after this
+    // sees the slot isn't published anymore and doesn't touch it.
+    // The oldest one is picked so writes go around every slot
+    let free = (0..3u8)
+      .filter(|&index| {
+        index != published && self.slots[index as usize].readers.load(Ordering::SeqCst) == 0
+      })
+      // SAFETY: only the writer changes frames, reading one it doesn't write is fine
+      .min_by_key(|&index| unsafe { (*self.slots[index as usize].cell.get()).sequence });
+
+    let Some(index) = free else {
+      self.writing.store(false, Ordering::Release);
+      return false;
+    };
+
+    // SAFETY: the slot isn't published and has no readers, new readers back off from it
+    let frame = unsafe { &mut *self.slots[index as usize].cell.get() };
+    let sequence = self.sequence.load(Ordering::Relaxed) + 1;
+
+    f(&mut frame.value);
+    frame.sequence = sequence;
+
+    self.published.store(index, Ordering::SeqCst);
+    self.sequence.store(sequence, Ordering::SeqCst);
+    self.writing.store(false, Ordering::Release);
+
+    true
+  }
+
+  /// Gets the sequence number of the latest published write, `0` before the first one
+  pub fn sequence(&self) -> u64 {
+    self.sequence.load(Ordering::SeqCst)
+  }
+
+  /// Gets the latest published slot without ever waiting on the writer or other readers,
+  /// only retries if the writer published another slot right as it was pinned
+  pub fn read(&self) -> ReadGuard<'_, T> {
+    loop {
+      let index = self.published.load(Ordering::SeqCst);
+      let slot = &self.slots[index as usize];
+
+      slot.readers.fetch_add(1, Ordering::SeqCst);
+
+      if self.published.load(Ordering::SeqCst) == index {
+        return ReadGuard { slot };
+      }
+
+      slot.readers.fetch_sub(1, Ordering::Release);
+    }
+  }
+}
+
+/// Read access to the latest value of a [TripleBuffer]
+///
+/// Never blocks the audio callback or other readers, any amount of guards can be held at once.
+/// While guards pin both slots the writer doesn't use, new frames are skipped until one is dropped
+pub struct ReadGuard<'a, T> {
+  slot: &'a Slot<T>,
+}
+
+impl<T> ReadGuard<'_, T> {
+  fn frame(&self) -> &Frame<T> {
+    // SAFETY: the writer never touches a slot that has readers
+    unsafe { &*self.slot.cell.get() }
+  }
+
+  /// Gets the sequence number of this frame, counting up by one for every update
+  pub fn sequence(&self) -> u64 {
+    self.frame().sequence
+  }
+}
+
+impl<T> Deref for ReadGuard<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.frame().value
+  }
+}
+
+impl<T> Drop for ReadGuard<'_, T> {
+  fn drop(&mut self) {
+    // Lets the writer see everything this guard read
+    self.slot.readers.fetch_sub(1, Ordering::Release);
+  }
+}
@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+use crate::{BlockInfo, Resample};
+
+/// Fixed amount of frames given to a listener per update,
+/// see [Listener::create_chunked](crate::Listener::create_chunked)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct BlockSize {
+  /// Frames (samples per channel) in every block
+  pub frames: usize,
+  /// Frames between the start of one block and the next,
+  /// less than `frames` makes blocks overlap, more skips frames
+  pub hop: usize,
+}
+
+impl BlockSize {
+  /// Blocks of `frames` frames that don't overlap
+  pub fn new(frames: usize) -> Self {
+    Self {
+      frames,
+      hop: frames,
+    }
+  }
+
+  pub fn with_hop(self, hop: usize) -> Self {
+    Self { hop, ..self }
+  }
+}
+
+/// Re-chunks blocks of any size into blocks of [BlockSize]
+#[derive(Debug)]
+pub(crate) struct Chunker {
+  size: BlockSize,
+  queue: VecDeque<f32>,
+  chunk: Vec<f32>,
+  /// Samples still to be skipped when hop is bigger than the block
+  skip: usize,
+  /// Position of the first frame in `queue`
+  position: u64,
+  /// Position right after the last block that was pushed
+  end: u64,
+  channels: u16,
+}
+
+impl Chunker {
+  pub fn new(size: BlockSize) -> Self {
+    let size = BlockSize {
+      frames: size.frames.max(1),
+      hop: size.hop.max(1),
+    };
+
+    Self {
+      size,
+      queue: VecDeque::new(),
+      chunk: Vec::new(),
+      skip: 0,
+      position: 0,
+      end: 0,
+      channels: 0,
+    }
+  }
+
+  /// Queues `data` and calls `f` for every full block
+  pub fn push(&mut self, data: &[f32], info: &BlockInfo, mut f: impl FnMut(&[f32], &BlockInfo)) {
+    let channels = info.channels.max(1);
+
+    // Starts over if the stream changed or restarted
+    if channels != self.channels || info.position != self.end {
+      self.channels = channels;
+      self.queue.clear();
+      self.skip = 0;
+      self.position = info.position;
+    }
+
+    let channels = channels as usize;
+
+    self.end = info.position + (data.len() / channels) as u64;
+    let len = self.size.frames * channels;
+    let hop = self.size.hop * channels;
+
+    let skipped = self.skip.min(data.len());
+    self.skip -= skipped;
+    self.position += (skipped / channels) as u64;
+
+    // Only allocates until the queue fits the largest block
+    self.queue.extend(&data[skipped..]);
+
+    while self.queue.len() >= len {
+      self.chunk.clear();
+      self.chunk.extend(self.queue.range(..len));
+
+      let info = BlockInfo {
+        position: self.position,
+        ..*info
+      };
+
+      f(&self.chunk, &info);
+
+      let drained = hop.min(self.queue.len());
+
+      self.queue.drain(..drained);
+      self.skip = hop - drained;
+      self.position += (drained / channels) as u64;
+    }
+  }
+}
+
+/// Optional steps that run on blocks before a listener gets them,
+/// resampling first so chunks are sized at the resampled rate
+#[derive(Debug, Default)]
+pub(crate) struct Prepare {
+  resample: Option<Resample>,
+  resampled: Vec<f32>,
+  chunker: Option<Chunker>,
+}
+
+impl Prepare {
+  pub fn new(rate: Option<u32>, size: Option<BlockSize>) -> Self {
+    Self {
+      resample: rate.map(Resample::new),
+      resampled: Vec::new(),
+      chunker: size.map(Chunker::new),
+    }
+  }
+
+  /// Calls `f` with every block that's ready after all steps
+  pub fn run(&mut self, data: &[f32], info: &BlockInfo, mut f: impl FnMut(&[f32], &BlockInfo)) {
+    let mut info = *info;
+    let data = match &mut self.resample {
+      Some(resample) => {
+        resample.resample(data, &mut self.resampled, &mut info);
+        &self.resampled
+      }
+      None => data,
+    };
+
+    match &mut self.chunker {
+      Some(chunker) => chunker.push(data, &info, f),
+      None => f(data, &info),
+    }
+  }
+}
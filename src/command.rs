@@ -0,0 +1,57 @@
+use std::sync::Mutex;
+
+type Command<T> = Box<dyn FnMut(&mut T) + Send>;
+
+struct Queue<T> {
+  commands: Vec<Command<T>>,
+  /// Commands at the start of `commands` that were already applied
+  applied: usize,
+}
+
+/// Changes sent from other threads, applied in order by the audio thread between blocks
+pub(crate) struct Commands<T> {
+  queue: Mutex<Queue<T>>,
+}
+
+impl<T> Default for Commands<T> {
+  fn default() -> Self {
+    Self {
+      queue: Mutex::new(Queue {
+        commands: Vec::new(),
+        applied: 0,
+      }),
+    }
+  }
+}
+
+impl<T> Commands<T> {
+  /// Queues a change, commands applied since the last push are dropped here
+  /// so the audio thread never frees them
+  pub fn push(&self, command: impl FnOnce(&mut T) + Send + 'static) {
+    let mut command = Some(command);
+    let mut queue = self.queue.lock().unwrap();
+    let applied = queue.applied;
+
+    queue.commands.drain(..applied);
+    queue.applied = 0;
+    queue.commands.push(Box::new(move |value| {
+      if let Some(command) = command.take() {
+        command(value);
+      }
+    }));
+  }
+
+  /// Applies every queued change, if the queue is being pushed to
+  /// they're applied before the next block instead of waiting
+  pub fn apply(&self, value: &mut T) {
+    if let Ok(mut queue) = self.queue.try_lock() {
+      let Queue { commands, applied } = &mut *queue;
+
+      for command in &mut commands[*applied..] {
+        command(value);
+      }
+
+      *applied = commands.len();
+    }
+  }
+}
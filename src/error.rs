@@ -31,9 +31,72 @@ pub enum Error {
   #[error("Couldn't find a device named '{0}'")]
   NoDeviceFound(String),
 
+  #[error("Sample format '{0}' isn't supported")]
+  UnsupportedSampleFormat(cpal::SampleFormat),
+
   #[cfg(target_os = "linux")]
   NoApplicationFound(String),
 
+  #[error("Capturing a single application isn't supported by this backend")]
+  ApplicationCaptureUnsupported,
+
+  #[error("Device events aren't supported by this backend")]
+  DeviceEventsUnsupported,
+
+  #[error("Supervising the stream isn't supported by this backend")]
+  SupervisionUnsupported,
+
+  #[error("Host isn't listening yet")]
+  NotListening,
+
+  #[error("Stream thread stopped before the stream was playing")]
+  StreamThreadStopped,
+
+  #[error("Analysis window of {0} frames isn't between 1 and 16384")]
+  InvalidAnalysisWindow(usize),
+
+  #[cfg(all(target_os = "linux", feature = "events"))]
+  #[error("Couldn't connect to the PulseAudio server")]
+  PulseConnectionFailed,
+
+  #[cfg(target_os = "linux")]
+  #[error("Couldn't load PulseAudio module '{0}'")]
+  ModuleLoadFailed(String),
+
+  #[cfg(target_os = "linux")]
+  #[error("Couldn't unload PulseAudio module {0}")]
+  ModuleUnloadFailed(u32),
+
+  #[cfg(feature = "file")]
+  #[error("Couldn't find an audio track in '{0}'")]
+  NoTrackFound(String),
+
+  #[cfg(feature = "file")]
+  DecodeError(#[from] symphonia::core::errors::Error),
+
+  #[cfg(feature = "record")]
+  WavError(#[from] hound::Error),
+
+  #[cfg(feature = "record")]
+  #[error("Device changed to {0} hz and {1} channels while recording")]
+  FormatChanged(u32, u16),
+
+  #[cfg(all(target_os = "linux", feature = "pipewire"))]
+  PipeWireError(#[from] pipewire::Error),
+
+  #[cfg(all(target_os = "linux", feature = "events"))]
+  PulseError(#[from] libpulse_binding::error::PAErr),
+
+  #[cfg(all(target_os = "linux", feature = "jack"))]
+  JackError(#[from] jack::Error),
+
+  #[cfg(windows)]
+  WindowsError(#[from] windows::core::Error),
+
+  #[cfg(windows)]
+  #[error("Devices kept changing while they were listed")]
+  DevicesChanged,
+
   #[cfg(target_os = "linux")]
   ControllerError(#[from] pulsectl::controllers::errors::ControllerError),
 }
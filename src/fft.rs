@@ -6,6 +6,11 @@ pub struct FFT<const BUF_SIZE: usize = 16384>  {
   #[debug(skip)]
   planner: FftPlanner<f32>,
   data: Vec<f32>,
+  /// Reused between calls so processing doesn't allocate once it ran with the biggest size
+  #[debug(skip)]
+  buffer: Vec<Complex32>,
+  #[debug(skip)]
+  scratch: Vec<Complex32>,
 }
 
 impl Default for FFT<16384> {
@@ -13,15 +18,22 @@ impl Default for FFT<16384> {
     Self {
       planner: FftPlanner::new(),
       data: vec![0.; 16384],
+      buffer: Vec::new(),
+      scratch: Vec::new(),
     }
   }
 }
 
 impl<const BUF_SIZE: usize> FFT<BUF_SIZE> {
+  /// Highest size [Self::process] accepts
+  pub const MAX_SIZE: usize = BUF_SIZE;
+
   pub fn new() -> Self {
     Self {
       planner: FftPlanner::new(),
       data: vec![0.; BUF_SIZE],
+      buffer: Vec::new(),
+      scratch: Vec::new(),
     }
   }
 
@@ -30,10 +42,13 @@ impl<const BUF_SIZE: usize> FFT<BUF_SIZE> {
       panic!("{size} is higher then max buf size of {BUF_SIZE}")
     }
 
-    let mut buffer = vec![Complex32::zero(); size * 2];
-    let mut scratch = vec![Complex32::zero(); size * 2];
+    if self.buffer.len() < size {
+      self.buffer.resize(size, Complex32::zero());
+    }
+
+    let buffer = &mut self.buffer[..size];
 
-    buffer[..size].copy_from_slice(&vec![Complex32::zero(); size][..size]);
+    buffer.fill(Complex32::zero());
 
     if buf.len() > size {
       let chunk_size = (buf.len() as f64 / size as f64).floor() as usize;
@@ -45,8 +60,8 @@ impl<const BUF_SIZE: usize> FFT<BUF_SIZE> {
         })
         .map(Complex32::from);
 
-      for i in 0..size {
-        buffer[i] = bins.next().unwrap_or_default();
+      for value in buffer.iter_mut() {
+        *value = bins.next().unwrap_or_default();
       }
     } else {
       for i in 0..buf.len().min(size) {
@@ -60,14 +75,14 @@ impl<const BUF_SIZE: usize> FFT<BUF_SIZE> {
 
     let scratch_len = fft.get_inplace_scratch_len();
 
-    if scratch_len >= scratch.len() {
-      scratch.resize(scratch_len, Complex32::zero());
+    if scratch_len > self.scratch.len() {
+      self.scratch.resize(scratch_len, Complex32::zero());
     }
 
-    fft.process_with_scratch(&mut buffer[..size], &mut scratch[..scratch_len]);
+    fft.process_with_scratch(buffer, &mut self.scratch[..scratch_len]);
 
-    for i in 0..size {
-      self.data[i] = buffer[i].re / max;
+    for (data, value) in self.data.iter_mut().zip(buffer.iter()) {
+      *data = value.re / max;
     }
 
     &self.data[..size]
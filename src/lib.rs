@@ -1,11 +1,40 @@
+pub use analysis::*;
+pub use block::*;
+pub use buffer::ReadGuard;
+pub use chunk::BlockSize;
 pub use error::*;
 pub use fft::*;
 pub use listener::*;
+pub use offline::*;
+pub use pipeline::*;
 pub use platform::*;
+pub use processors::*;
+#[cfg(feature = "record")]
+pub use record::*;
+pub use resample::*;
+#[cfg(feature = "stream")]
+pub use stream::*;
+pub use worker::WorkerStats;
 
+mod analysis;
+mod block;
+mod buffer;
+mod chunk;
+mod command;
 mod error;
 mod fft;
 mod listener;
+mod notify;
+mod offline;
+mod pipeline;
 mod platform;
+mod processors;
+#[cfg(feature = "record")]
+mod record;
+mod resample;
+mod ring;
+#[cfg(feature = "stream")]
+mod stream;
+mod worker;
 
 pub type Result<T> = std::result::Result<T, Error>;
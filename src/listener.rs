@@ -1,17 +1,31 @@
 #![allow(clippy::type_complexity)]
 
 use std::{
-  any::TypeId,
-  collections::{hash_map::Entry, HashMap},
-  fmt::Debug,
+  collections::HashMap,
+  fmt::{Debug, Display, Formatter},
+  ops::{Deref, DerefMut},
   sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering}, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex, RwLock, Weak,
   },
+  thread,
+  time::{Duration, Instant},
 };
 
 pub use cpal::InputCallbackInfo;
-use downcast_rs::{Downcast, impl_downcast};
+use cpal::StreamConfig;
+use downcast_rs::{impl_downcast, DowncastSync};
+
+use crate::{
+  buffer::TripleBuffer,
+  chunk::Prepare,
+  command::Commands,
+  notify::Notify,
+  pipeline::PipelineShared,
+  platform::{Health, Reporter},
+  worker::Worker,
+  BlockInfo, BlockSize, ReadGuard, Result, WorkerStats,
+};
 
 pub struct DataCallback {
   callback: Box<dyn FnMut(&[f32], &InputCallbackInfo) + Send + Sync + 'static>,
@@ -40,101 +54,714 @@ impl AudioData for Vec<f32> {
   }
 }
 
-pub(crate) trait AudioListenerTrait: Downcast + Send + Sync + Debug + 'static {
-  fn update(&self, data: &[f32]);
+pub(crate) trait AudioListenerTrait: DowncastSync + Debug + 'static {
+  fn update(&self, data: &[f32], info: &BlockInfo);
+
+  /// Whether the callback should call [Self::update]
+  fn is_active(&self) -> bool;
+
+  /// Whether every handle was dropped or it was removed, so it can be removed from [Listener]
+  fn is_detached(&self) -> bool;
+
+  fn detach(&self);
+
+  /// Counts blocks the processing worker dropped before they reached [Self::update]
+  fn skipped(&self, _blocks: u64) {}
+
+  /// Whether the audio callback updates it, `false` if something else does like a [Publisher]
+  fn is_dispatched(&self) -> bool {
+    true
+  }
+
+  /// Wakes threads waiting for it so they see a change that didn't come with a block
+  fn wake(&self) {}
 }
 
-impl_downcast!(AudioListenerTrait);
+impl_downcast!(sync AudioListenerTrait);
 
-#[derive(Debug)]
+/// Identifies a single listener created by [Listener]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ListenerId(u64);
+
+impl Display for ListenerId {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(&self.0, f)
+  }
+}
+
+struct Shared<T> {
+  id: ListenerId,
+  /// Only touched by the audio callback
+  state: Mutex<T>,
+  /// Changes sent by [AudioListener::modify]
+  commands: Commands<T>,
+  /// Only touched by the audio callback
+  prepare: Mutex<Prepare>,
+  buffer: TripleBuffer<T>,
+  notify: Notify,
+  /// Amount of [AudioListener] handles alive
+  handles: AtomicUsize,
+  paused: AtomicBool,
+  /// Set while the [Host](crate::Host) is paused, shared by every listener of it
+  host_paused: Arc<AtomicBool>,
+  removed: AtomicBool,
+  /// Handles of the [Listener] it belongs to, so the last handle can take it out when it's dropped
+  registry: Weak<Handles>,
+}
+
+impl<T> Shared<T> {
+  /// Adds a handle unless all of them were already dropped
+  fn acquire(&self) -> bool {
+    self
+      .handles
+      .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+        (n > 0).then_some(n + 1)
+      })
+      .is_ok()
+  }
+
+  fn is_detached(&self) -> bool {
+    self.removed.load(Ordering::Acquire) || self.handles.load(Ordering::Acquire) == 0
+  }
+
+  fn is_active(&self) -> bool {
+    !self.paused.load(Ordering::Acquire) && !self.is_detached()
+  }
+}
+
+impl<T> Debug for Shared<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Shared")
+      .field("id", &self.id)
+      .field("handles", &self.handles)
+      .field("paused", &self.paused)
+      .field("host_paused", &self.host_paused)
+      .field("removed", &self.removed)
+      .finish()
+  }
+}
+
+/// Handle to the data of a listener, the listener is detached from the
+/// audio callback once every clone of it is dropped
 pub struct AudioListener<T = Vec<f32>> {
-  handle: Arc<RwLock<T>>,
-  modify: Arc<RwLock<T>>,
-  marked: Arc<AtomicBool>,
+  shared: Arc<Shared<T>>,
+  /// Sequence number of the last frame this handle polled
+  seen: AtomicU64,
+  /// Frames skipped between the last two polls of this handle
+  missed: AtomicU64,
 }
 
-impl<T: AudioData> AudioListener<T> {
-  fn new() -> Self {
+impl<T: Clone + Debug + Send + Sync + 'static> AudioListener<T> {
+  pub(crate) fn new(listener: &Listener, value: T) -> Self {
+    Self::with_prepare(listener, value, Prepare::default())
+  }
+
+  fn with_prepare(listener: &Listener, value: T, prepare: Prepare) -> Self {
     Self {
-      handle: Default::default(),
-      modify: Default::default(),
-      marked: Default::default(),
+      shared: Arc::new(Shared {
+        id: listener.next_id(),
+        state: Mutex::new(value.clone()),
+        commands: Default::default(),
+        prepare: Mutex::new(prepare),
+        buffer: TripleBuffer::new(value.clone(), value.clone(), value),
+        notify: Default::default(),
+        handles: AtomicUsize::new(1),
+        paused: Default::default(),
+        host_paused: listener.paused.clone(),
+        removed: Default::default(),
+        registry: Arc::downgrade(&listener.handles),
+      }),
+      seen: Default::default(),
+      missed: Default::default(),
     }
   }
 
-  pub fn poll(&self) -> RwLockReadGuard<T> {
-    if self.marked.load(Ordering::SeqCst) {
-      *self.handle.write().unwrap() = self.modify.read().unwrap().clone();
+  /// Gets the id of this listener, shared by all of its clones
+  pub fn id(&self) -> ListenerId {
+    self.shared.id
+  }
+
+  /// Stops updating this listener until [Self::resume], keeps the last value
+  pub fn pause(&self) {
+    self.shared.paused.store(true, Ordering::Release);
+    // Lets threads in [Self::wait] return
+    self.shared.notify.notify();
+  }
 
-      self.marked.store(false, Ordering::SeqCst)
+  /// Resumes updating this listener after [Self::pause]
+  pub fn resume(&self) {
+    self.shared.paused.store(false, Ordering::Release);
+  }
+
+  /// Whether this listener or the [Host](crate::Host) it belongs to is paused,
+  /// it keeps its last value either way
+  pub fn is_paused(&self) -> bool {
+    self.shared.paused.load(Ordering::Acquire) || self.shared.host_paused.load(Ordering::Acquire)
+  }
+
+  /// Whether this listener still gets updated by the audio callback,
+  /// `false` after [Listener::remove]
+  pub fn is_attached(&self) -> bool {
+    !self.shared.removed.load(Ordering::Acquire)
+  }
+
+  /// Gets the latest complete frame, never blocks the audio callback or other readers,
+  /// see [ReadGuard] for what holding on to it does
+  pub fn poll(&self) -> ReadGuard<'_, T> {
+    let frame = self.shared.buffer.read();
+    let seen = self.seen.swap(frame.sequence(), Ordering::AcqRel);
+
+    if frame.sequence() > seen {
+      self
+        .missed
+        .store(frame.sequence() - seen - 1, Ordering::Release);
     }
 
-    self.handle.read().unwrap()
+    frame
+  }
+
+  /// Blocks until there's a frame this handle hasn't polled yet, then polls it,
+  /// `None` once this listener is paused or removed since no new frame would come
+  pub fn wait(&self) -> Option<ReadGuard<'_, T>> {
+    self.wait_until(None)
   }
 
-  pub fn poll_mut(&self) -> RwLockWriteGuard<T> {
-    let mut data = self.modify.write().unwrap();
+  /// Same as [Self::wait] but also gives up after `timeout`
+  pub fn wait_timeout(&self, timeout: Duration) -> Option<ReadGuard<'_, T>> {
+    self.wait_until(Some(timeout))
+  }
+
+  fn wait_until(&self, timeout: Option<Duration>) -> Option<ReadGuard<'_, T>> {
+    self.shared.notify.wait_until(timeout, || {
+      self.has_new() || self.is_paused() || !self.is_attached()
+    });
+
+    self.has_new().then(|| self.poll())
+  }
 
-    *data = self.handle.read().unwrap().clone();
-    self.marked.store(true, Ordering::SeqCst);
+  /// Whether a frame was published since this handle last polled
+  pub fn has_new(&self) -> bool {
+    self.sequence() > self.seen.load(Ordering::Acquire)
+  }
+
+  /// Gets the sequence number of the latest frame, counting up by one for every update
+  pub fn sequence(&self) -> u64 {
+    self.shared.buffer.sequence()
+  }
 
-    data
+  /// Gets how many frames were skipped between the last two polls of this handle
+  pub fn missed_frames(&self) -> u64 {
+    self.missed.load(Ordering::Acquire)
+  }
+
+  /// Gets a [Publisher] to update this listener directly
+  pub(crate) fn publisher(&self) -> Publisher<T> {
+    Publisher(self.shared.clone())
   }
 }
 
-impl<T: AudioData> Clone for AudioListener<T> {
+impl<T: AudioData> AudioListener<T> {
+  /// Changes the audio callback's state before its next update,
+  /// changes are applied in the order they're sent so none of them get lost
+  ///
+  /// The closure's allocation is freed off the audio thread by the next call,
+  /// anything it captures and doesn't move into the state is still dropped on the audio thread
+  pub fn modify(&self, f: impl FnOnce(&mut T) + Send + 'static) {
+    self.shared.commands.push(f);
+  }
+
+  /// Gets a copy of the latest frame that replaces the audio callback's state once dropped
+  #[deprecated(note = "replaces changes made in the meantime, use `modify` instead")]
+  #[allow(deprecated)]
+  pub fn poll_mut(&self) -> ModifyGuard<'_, T> {
+    ModifyGuard {
+      value: Some(self.poll().clone()),
+      listener: self,
+    }
+  }
+}
+
+/// Modifiable copy of the latest frame returned by [AudioListener::poll_mut]
+#[deprecated(note = "replaces changes made in the meantime, use `AudioListener::modify` instead")]
+pub struct ModifyGuard<'a, T: AudioData> {
+  value: Option<T>,
+  listener: &'a AudioListener<T>,
+}
+
+#[allow(deprecated)]
+impl<T: AudioData> Deref for ModifyGuard<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    self.value.as_ref().unwrap()
+  }
+}
+
+#[allow(deprecated)]
+impl<T: AudioData> DerefMut for ModifyGuard<'_, T> {
+  fn deref_mut(&mut self) -> &mut T {
+    self.value.as_mut().unwrap()
+  }
+}
+
+#[allow(deprecated)]
+impl<T: AudioData> Drop for ModifyGuard<'_, T> {
+  fn drop(&mut self) {
+    if let Some(value) = self.value.take() {
+      self.listener.modify(move |state| *state = value);
+    }
+  }
+}
+
+impl<T> Clone for AudioListener<T> {
   fn clone(&self) -> Self {
+    self.shared.handles.fetch_add(1, Ordering::AcqRel);
+
     Self {
-      handle: self.handle.clone(),
-      modify: self.modify.clone(),
-      marked: self.marked.clone(),
+      shared: self.shared.clone(),
+      seen: AtomicU64::new(self.seen.load(Ordering::Acquire)),
+      missed: AtomicU64::new(self.missed.load(Ordering::Acquire)),
     }
   }
 }
 
-impl<T: AudioData> AudioListenerTrait for AudioListener<T> {
-  fn update(&self, data: &[f32]) {
-    let mut handle = self.handle.read().unwrap().clone();
+impl<T> Drop for AudioListener<T> {
+  fn drop(&mut self) {
+    if self.shared.handles.fetch_sub(1, Ordering::AcqRel) > 1 {
+      return;
+    }
+
+    // The last handle takes it out so it doesn't stay registered until the next listener is created
+    if let Some(registry) = self.shared.registry.upgrade() {
+      registry.change(|_| ());
+    }
+  }
+}
+
+impl<T: Clone + Debug + Send + Sync + 'static> Debug for AudioListener<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("AudioListener")
+      .field("id", &self.id())
+      .field("data", &*self.poll())
+      .finish()
+  }
+}
+
+impl<T: AudioData> AudioListenerTrait for Shared<T> {
+  fn update(&self, data: &[f32], info: &BlockInfo) {
+    // Only fails if another callback is running, which cpal doesn't do
+    let Ok(mut state) = self.state.try_lock() else {
+      return;
+    };
 
-    handle.update(data);
+    self.commands.apply(&mut state);
 
-    *self.handle.write().unwrap() = handle;
+    let Ok(mut prepare) = self.prepare.try_lock() else {
+      return;
+    };
+
+    prepare.run(data, info, |data, _| {
+      state.update(data);
+
+      // clone_from reuses the slot's allocations
+      if self.buffer.write(|slot| slot.clone_from(&state)) {
+        self.notify.notify();
+      }
+    });
+  }
+
+  fn is_active(&self) -> bool {
+    Shared::is_active(self)
+  }
+
+  fn is_detached(&self) -> bool {
+    Shared::is_detached(self)
+  }
+
+  fn detach(&self) {
+    self.removed.store(true, Ordering::Release);
+    self.notify.notify();
+  }
+
+  fn wake(&self) {
+    self.notify.notify();
+  }
+}
+
+/// Updates an [AudioListener] directly instead of through [AudioData::update],
+/// doesn't keep the listener attached like a handle would
+///
+/// Registered in place of the listener so [Listener::get] finds it, the audio callback skips it
+pub(crate) struct Publisher<T>(Arc<Shared<T>>);
+
+impl<T: Clone> Publisher<T> {
+  /// Publishes `value` with the changes from [AudioListener::modify] applied to it
+  pub fn publish(&self, value: &T) {
+    let written = self.0.buffer.write(|slot| {
+      slot.clone_from(value);
+      self.0.commands.apply(slot);
+    });
+
+    if written {
+      self.0.notify.notify();
+    }
+  }
+
+  pub fn is_active(&self) -> bool {
+    self.0.is_active()
+  }
+
+  pub fn is_detached(&self) -> bool {
+    self.0.is_detached()
+  }
+
+  pub fn detach(&self) {
+    self.0.removed.store(true, Ordering::Release);
+    self.0.notify.notify();
   }
 }
 
-#[derive(Debug, Clone)]
+impl<T> Clone for Publisher<T> {
+  fn clone(&self) -> Self {
+    Self(self.0.clone())
+  }
+}
+
+impl<T> Debug for Publisher<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("Publisher").field(&self.0).finish()
+  }
+}
+
+impl<T: Clone + Send + Sync + 'static> AudioListenerTrait for Publisher<T> {
+  fn update(&self, _: &[f32], _: &BlockInfo) {}
+
+  fn is_active(&self) -> bool {
+    Publisher::is_active(self)
+  }
+
+  fn is_detached(&self) -> bool {
+    Publisher::is_detached(self)
+  }
+
+  fn detach(&self) {
+    Publisher::detach(self)
+  }
+
+  fn is_dispatched(&self) -> bool {
+    false
+  }
+
+  fn wake(&self) {
+    self.0.notify.notify();
+  }
+}
+
+pub(crate) type Handle = Arc<dyn AudioListenerTrait>;
+
+/// Every handle of a [Listener], the audio callback only reads a snapshot of them
+/// so adding or removing one never makes it wait
+#[derive(Default)]
+pub(crate) struct Handles {
+  /// Only locked by threads that add or remove handles
+  map: Mutex<HashMap<ListenerId, Handle>>,
+  snapshot: TripleBuffer<Arc<Vec<Handle>>>,
+}
+
+impl Handles {
+  /// Changes the handles, drops detached ones and publishes the rest to the audio callback,
+  /// old snapshots are dropped here so the callback never frees a handle
+  fn change<R>(&self, f: impl FnOnce(&mut HashMap<ListenerId, Handle>) -> R) -> R {
+    let mut map = self.map.lock().unwrap();
+    let result = f(&mut map);
+
+    map.retain(|_, handle| !handle.is_detached());
+
+    let snapshot = map
+      .values()
+      .filter(|handle| handle.is_dispatched())
+      .cloned()
+      .collect::<Vec<_>>();
+    let snapshot = Arc::new(snapshot);
+
+    // Written into every slot so older snapshots don't keep removed handles alive,
+    // a write only fails while the callback reads the other slots
+    for _ in 0..3 {
+      while !self.snapshot.write(|slot| slot.clone_from(&snapshot)) {
+        thread::yield_now();
+      }
+    }
+
+    result
+  }
+
+  fn get(&self, id: ListenerId) -> Option<Handle> {
+    self.map.lock().unwrap().get(&id).cloned()
+  }
+
+  fn wake(&self) {
+    for handle in self.map.lock().unwrap().values() {
+      handle.wake();
+    }
+  }
+}
+
+impl Debug for Handles {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_map()
+      .entries(self.map.lock().unwrap().iter())
+      .finish()
+  }
+}
+
+/// Updates every active handle with a block of samples
+pub(crate) fn dispatch(handles: &Handles, data: &[f32], info: &BlockInfo) {
+  for handle in handles.snapshot.read().iter() {
+    if handle.is_active() {
+      handle.update(data, info);
+    }
+  }
+}
+
+/// Tells every handle that the processing worker dropped `blocks` before the next one
+pub(crate) fn skip(handles: &Handles, blocks: u64) {
+  for handle in handles.snapshot.read().iter() {
+    if handle.is_active() {
+      handle.skipped(blocks);
+    }
+  }
+}
+
+/// Source of the device stream, other sources are only used while they're selected
+pub(crate) const DEVICE_SOURCE: u64 = 0;
+
+#[derive(Clone)]
 pub struct Listener {
-  handles: Arc<RwLock<HashMap<TypeId, Box<dyn AudioListenerTrait>>>>,
+  handles: Arc<Handles>,
+  next_id: Arc<AtomicU64>,
+  worker: Arc<RwLock<Option<Arc<Worker>>>>,
+  /// Source that updates listeners, blocks from any other source are ignored
+  source: Arc<AtomicU64>,
+  /// Reports blocks and errors of the device stream to the supervisor
+  health: Arc<Health>,
+  /// Reports errors and warnings of anything running for this listener to the app
+  reporter: Arc<Reporter>,
+  /// Drops every block while it's set, see [Host::pause](crate::Host::pause)
+  paused: Arc<AtomicBool>,
+}
+
+impl Debug for Listener {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Listener")
+      .field("handles", &self.handles)
+      .field("worker", &self.worker_stats())
+      .finish()
+  }
 }
 
 impl Listener {
   pub(crate) fn new() -> Self {
     Self {
       handles: Default::default(),
+      next_id: Default::default(),
+      worker: Default::default(),
+      source: Arc::new(AtomicU64::new(DEVICE_SOURCE)),
+      health: Default::default(),
+      reporter: Default::default(),
+      paused: Default::default(),
     }
   }
 
-  pub fn create<T: AudioData>(&mut self) -> AudioListener<T> {
-    let id = TypeId::of::<T>();
+  pub(crate) fn health(&self) -> Arc<Health> {
+    self.health.clone()
+  }
+
+  pub(crate) fn reporter(&self) -> Arc<Reporter> {
+    self.reporter.clone()
+  }
+
+  /// Stops or starts giving blocks of any source to listeners, see [Host::pause](crate::Host::pause)
+  pub(crate) fn set_paused(&self, paused: bool) {
+    self.paused.store(paused, Ordering::Release);
+    // Lets threads in [AudioListener::wait] return
+    self.handles.wake();
+  }
+
+  /// Whether the [Host](crate::Host) of these listeners is paused
+  pub fn is_paused(&self) -> bool {
+    self.paused.load(Ordering::Acquire)
+  }
+
+  /// Moves updating listeners from the audio callback to a separate thread,
+  /// the callback then only copies samples into a queue of `capacity` blocks
+  /// and blocks that don't fit are dropped instead of stalling the audio thread
+  pub fn enable_worker(&self, capacity: usize) -> Result<()> {
+    let worker = Worker::spawn(&self.handles, self.reporter(), capacity)?;
 
-    if let Entry::Vacant(e) = self.handles.write().unwrap().entry(id) {
-      e.insert(Box::new(AudioListener::<T>::new()));
+    if let Some(old) = self.worker.write().unwrap().replace(worker) {
+      old.stop();
     }
 
-    let value = self.handles.read().unwrap();
-    let value = &value[&id];
-    let value = value.downcast_ref::<AudioListener<T>>().unwrap();
+    Ok(())
+  }
+
+  /// Goes back to updating listeners inside the audio callback
+  pub fn disable_worker(&self) {
+    if let Some(old) = self.worker.write().unwrap().take() {
+      old.stop();
+    }
+  }
+
+  /// Gets stats of the worker thread, `None` if [Self::enable_worker] isn't used
+  pub fn worker_stats(&self) -> Option<WorkerStats> {
+    self
+      .worker
+      .read()
+      .unwrap()
+      .as_ref()
+      .map(|worker| worker.stats())
+  }
+
+  /// Creates a new listener starting from [Default::default]
+  pub fn create<T: AudioData>(&self) -> AudioListener<T> {
+    self.create_with(T::default())
+  }
+
+  /// Creates a new listener starting from `value`,
+  /// every listener has its own state even if another one has the same type
+  pub fn create_with<T: AudioData>(&self, value: T) -> AudioListener<T> {
+    let listener = AudioListener::new(self, value);
+
+    self.register(listener.id(), listener.shared.clone());
+
+    listener
+  }
+
+  /// Creates a new listener that's always updated with blocks of exactly `size`,
+  /// no matter what size the device gives
+  pub fn create_chunked<T: AudioData>(&self, size: BlockSize) -> AudioListener<T> {
+    self.create_chunked_with(T::default(), size)
+  }
+
+  /// Same as [Self::create_chunked] but starting from `value`
+  pub fn create_chunked_with<T: AudioData>(&self, value: T, size: BlockSize) -> AudioListener<T> {
+    self.create_prepared(value, Prepare::new(None, Some(size)))
+  }
+
+  /// Creates a new listener that's always updated with samples at `rate`,
+  /// no matter what rate the device runs at, see [Resample]
+  pub fn create_resampled<T: AudioData>(&self, rate: u32) -> AudioListener<T> {
+    self.create_resampled_with(T::default(), rate)
+  }
+
+  /// Same as [Self::create_resampled] but starting from `value`
+  pub fn create_resampled_with<T: AudioData>(&self, value: T, rate: u32) -> AudioListener<T> {
+    self.create_prepared(value, Prepare::new(Some(rate), None))
+  }
+
+  fn create_prepared<T: AudioData>(&self, value: T, prepare: Prepare) -> AudioListener<T> {
+    let listener = AudioListener::with_prepare(self, value, prepare);
+
+    self.register(listener.id(), listener.shared.clone());
 
-    value.clone()
+    listener
   }
 
-  pub(crate) fn callback(&self) -> DataCallback {
+  pub(crate) fn next_id(&self) -> ListenerId {
+    ListenerId(self.next_id.fetch_add(1, Ordering::Relaxed))
+  }
+
+  /// Adds a handle to be updated by the audio callback
+  pub(crate) fn register(&self, id: ListenerId, handle: Handle) {
+    self.handles.change(|handles| {
+      handles.insert(id, handle);
+    });
+  }
+
+  /// Gets an existing listener by id,
+  /// `None` if it doesn't exist, isn't a listener of `T` or was detached
+  pub fn get<T: AudioData>(&self, id: ListenerId) -> Option<AudioListener<T>> {
+    let handle = self.handles.get(id)?;
+    // A pipeline is registered by the id of its output
+    let handle = match handle.downcast_arc::<PipelineShared>() {
+      Ok(pipeline) => pipeline.output(),
+      Err(handle) => handle,
+    };
+    let shared = match handle.downcast_arc::<Shared<T>>() {
+      Ok(shared) => shared,
+      Err(handle) => handle.downcast_arc::<Publisher<T>>().ok()?.0.clone(),
+    };
+
+    shared.acquire().then(|| AudioListener {
+      shared,
+      seen: Default::default(),
+      missed: Default::default(),
+    })
+  }
+
+  /// Removes a listener so it no longer gets updated, existing handles keep their last value,
+  /// `false` if it doesn't exist, was already removed or every handle of it was dropped
+  pub fn remove(&self, id: ListenerId) -> bool {
+    self
+      .handles
+      .change(|handles| handles.remove(&id).map(|handle| handle.detach()).is_some())
+  }
+
+  /// Changes which source updates listeners, see [DEVICE_SOURCE]
+  #[cfg(feature = "file")]
+  pub(crate) fn select_source(&self, id: u64) {
+    self.source.store(id, Ordering::Release);
+  }
+
+  pub(crate) fn callback(&self, config: &StreamConfig) -> DataCallback {
+    let mut feed = self.feed(config);
+
+    DataCallback::new(move |data: &[f32], _: &_| feed(data))
+  }
+
+  /// Same as [Self::callback] for sources that aren't a cpal stream
+  pub(crate) fn feed(&self, config: &StreamConfig) -> impl FnMut(&[f32]) + Send + Sync + 'static {
+    self.source_feed(config, DEVICE_SOURCE)
+  }
+
+  /// Same as [Self::feed] but only updates listeners while `id` is selected
+  pub(crate) fn source_feed(
+    &self,
+    config: &StreamConfig,
+    id: u64,
+  ) -> impl FnMut(&[f32]) + Send + Sync + 'static {
     let handles = self.handles.clone();
+    let worker = self.worker.clone();
+    let source = self.source.clone();
+    let health = self.health.clone();
+    let paused = self.paused.clone();
+    let mut info = BlockInfo {
+      channels: config.channels,
+      sample_rate: config.sample_rate.0,
+      ..Default::default()
+    };
 
-    DataCallback::new(move |data: &[f32], _: &_| {
-      for handle in handles.read().unwrap().values() {
-        handle.update(data);
+    move |data: &[f32]| {
+      let now = Instant::now();
+
+      // The device stream is still alive while another source is selected
+      if id == DEVICE_SOURCE {
+        health.beat(now);
       }
-    })
+
+      if paused.load(Ordering::Acquire) || source.load(Ordering::Acquire) != id {
+        return;
+      }
+
+      info.timestamp = now;
+
+      // Only locked for writing while enabling or disabling the worker
+      match worker.try_read().as_deref() {
+        Ok(Some(worker)) => worker.push(data, &info),
+        _ => dispatch(&handles, data, &info),
+      }
+
+      info.position += (data.len() / info.channels.max(1) as usize) as u64;
+    }
   }
 }
@@ -0,0 +1,70 @@
+use std::{
+  sync::{
+    atomic::{fence, AtomicUsize, Ordering},
+    Condvar, Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+/// Lets threads sleep until the audio callback has something new for them
+#[derive(Debug, Default)]
+pub(crate) struct Notify {
+  waiters: AtomicUsize,
+  lock: Mutex<()>,
+  cond: Condvar,
+}
+
+impl Notify {
+  /// Wakes every waiting thread, only takes the lock while a thread waits
+  /// so the audio callback doesn't touch it otherwise
+  pub fn notify(&self) {
+    // Pairs with the fence in `wait_until` so either the waiter sees the new state
+    // or this sees the waiter
+    fence(Ordering::SeqCst);
+
+    if self.waiters.load(Ordering::SeqCst) > 0 {
+      // Waiters hold the lock from checking `ready` until they sleep,
+      // so taking it here means none of them is about to sleep and misses the wake-up
+      drop(self.lock.lock().unwrap());
+      self.cond.notify_all();
+    }
+  }
+
+  /// Waits until `ready` returns `true` or `timeout` passes
+  pub fn wait_until(&self, timeout: Option<Duration>, mut ready: impl FnMut() -> bool) -> bool {
+    if ready() {
+      return true;
+    }
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    self.waiters.fetch_add(1, Ordering::SeqCst);
+    fence(Ordering::SeqCst);
+
+    let mut guard = self.lock.lock().unwrap();
+
+    let ready = loop {
+      if ready() {
+        break true;
+      }
+
+      guard = match deadline {
+        Some(deadline) => {
+          let now = Instant::now();
+
+          if now >= deadline {
+            break false;
+          }
+
+          self.cond.wait_timeout(guard, deadline - now).unwrap().0
+        }
+        None => self.cond.wait(guard).unwrap(),
+      };
+    };
+
+    drop(guard);
+    self.waiters.fetch_sub(1, Ordering::SeqCst);
+
+    ready
+  }
+}
@@ -0,0 +1,246 @@
+use std::{
+  fmt::{Debug, Formatter},
+  io::Write,
+  time::Instant,
+};
+
+use crate::{chunk::Prepare, AnalysisSettings, Analyze, BlockInfo, Features, Processor, Result};
+
+/// Format of the rows written by [Offline]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum OutputFormat {
+  /// Header followed by one comma separated row per frame
+  #[default]
+  Csv,
+  /// One JSON object per line and frame, values that aren't finite are `null`
+  JsonLines,
+}
+
+/// Runs [Analyze] over samples as fast as they're given, without any device,
+/// and writes the [Features] of every video frame at `frame_rate` to `writer`
+///
+/// Every frame gets the features of the last window that ended at or before it,
+/// so the same samples and settings always give the same output,
+/// onsets of windows between two frames show up on the later frame
+///
+/// Works on samples from anywhere so it's always available,
+/// only [Self::analyze_file] needs the `file` feature to decode files
+pub struct Offline<W: Write> {
+  writer: W,
+  format: OutputFormat,
+  frame_rate: f64,
+  settings: AnalysisSettings,
+  prepare: Prepare,
+  analyze: Analyze,
+  window: Vec<f32>,
+  features: Features,
+  /// Onset of a window that ended after the last written frame
+  onset: bool,
+  /// Next frame to be written
+  frame: u64,
+  /// Frames pushed at the rate of the source
+  position: u64,
+  sample_rate: u32,
+  started: bool,
+}
+
+impl<W: Write> Offline<W> {
+  pub fn new(writer: W, format: OutputFormat, frame_rate: f64) -> Result<Self> {
+    Self::with_settings(writer, format, frame_rate, AnalysisSettings::default())
+  }
+
+  /// Fails if the settings can't be analyzed, see [Analyze::new]
+  pub fn with_settings(
+    writer: W,
+    format: OutputFormat,
+    frame_rate: f64,
+    settings: AnalysisSettings,
+  ) -> Result<Self> {
+    Ok(Self {
+      writer,
+      format,
+      frame_rate: frame_rate.max(f64::EPSILON),
+      settings,
+      prepare: Prepare::new(Some(settings.sample_rate), Some(settings.block_size())),
+      analyze: Analyze::new(settings)?,
+      window: Vec::new(),
+      features: Features::default(),
+      onset: false,
+      frame: 0,
+      position: 0,
+      sample_rate: 0,
+      started: false,
+    })
+  }
+
+  /// Analyzes a file, see [Self::push]
+  #[cfg(feature = "file")]
+  pub fn analyze_file(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+    let mut decoder = crate::platform::Decoder::open(path.as_ref())?;
+    let params = decoder.params();
+    let sample_rate = params.sample_rate.unwrap_or(44100);
+    let channels = params
+      .channels
+      .map_or(2, |channels| channels.count() as u16);
+    let mut samples = Vec::new();
+
+    while decoder.next(&mut samples)? {
+      self.push(&samples, channels, sample_rate)?;
+    }
+
+    Ok(())
+  }
+
+  /// Analyzes the next interleaved samples of the source,
+  /// samples are expected to follow the ones pushed before
+  pub fn push(&mut self, samples: &[f32], channels: u16, sample_rate: u32) -> Result<()> {
+    if !self.started {
+      self.started = true;
+      self.write_header()?;
+    }
+
+    let info = BlockInfo {
+      channels: channels.max(1),
+      sample_rate,
+      position: self.position,
+      timestamp: Instant::now(),
+    };
+
+    self.position += (samples.len() / info.channels as usize) as u64;
+    self.sample_rate = sample_rate;
+
+    let (analyze, window) = (&mut self.analyze, &mut self.window);
+    let mut windows = Vec::new();
+
+    self.prepare.run(samples, &info, |data, info| {
+      let mut info = *info;
+      let mut features = Features::default();
+
+      window.clear();
+      window.extend_from_slice(data);
+      analyze.process(window, &mut features, &mut info);
+      windows.push(features);
+    });
+
+    for features in windows {
+      let end =
+        (features.position + features.frames as u64) as f64 / self.settings.sample_rate as f64;
+
+      self.write_until(end)?;
+      self.onset |= features.onset;
+      self.features = features;
+    }
+
+    Ok(())
+  }
+
+  /// Writes the frames left until the end of the source and flushes `writer`,
+  /// returns the amount of frames written
+  pub fn finish(mut self) -> Result<u64> {
+    if !self.started {
+      self.write_header()?;
+    }
+
+    let end = self.position as f64 / self.sample_rate.max(1) as f64;
+
+    self.write_until(end)?;
+    self.writer.flush()?;
+
+    Ok(self.frame)
+  }
+
+  /// Amount of frames written so far
+  pub fn frames(&self) -> u64 {
+    self.frame
+  }
+
+  fn time(&self, frame: u64) -> f64 {
+    frame as f64 / self.frame_rate
+  }
+
+  /// Writes the current features for every frame before `end` seconds
+  fn write_until(&mut self, end: f64) -> Result<()> {
+    while self.time(self.frame) < end {
+      self.write_row()?;
+      self.frame += 1;
+    }
+
+    Ok(())
+  }
+
+  fn write_header(&mut self) -> Result<()> {
+    if self.format == OutputFormat::Csv {
+      write!(self.writer, "frame,time,rms,flux,onset,bpm")?;
+
+      for band in 0..self.settings.bands {
+        write!(self.writer, ",band_{band}")?;
+      }
+
+      writeln!(self.writer)?;
+    }
+
+    Ok(())
+  }
+
+  fn write_row(&mut self) -> Result<()> {
+    let frame = self.frame;
+    let time = self.time(frame);
+    let features = &self.features;
+    let onset = std::mem::take(&mut self.onset);
+    let bands =
+      (0..self.settings.bands).map(|band| features.bands.get(band).copied().unwrap_or_default());
+
+    match self.format {
+      OutputFormat::Csv => {
+        let bpm = features.bpm.map(|bpm| bpm.to_string()).unwrap_or_default();
+
+        write!(
+          self.writer,
+          "{frame},{time},{},{},{},{bpm}",
+          features.rms, features.flux, onset as u8
+        )?;
+
+        for band in bands {
+          write!(self.writer, ",{band}")?;
+        }
+
+        writeln!(self.writer)?;
+      }
+      OutputFormat::JsonLines => {
+        let bpm = features.bpm.map_or_else(|| String::from("null"), json);
+        let bands = bands.map(json).collect::<Vec<_>>();
+
+        writeln!(
+          self.writer,
+          r#"{{"frame":{frame},"time":{time},"rms":{},"flux":{},"onset":{},"bpm":{bpm},"bands":[{}]}}"#,
+          json(features.rms),
+          json(features.flux),
+          onset,
+          bands.join(",")
+        )?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Formats a JSON number, which can't be NaN or infinite
+fn json(value: f32) -> String {
+  match value.is_finite() {
+    true => value.to_string(),
+    false => String::from("null"),
+  }
+}
+
+impl<W: Write> Debug for Offline<W> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Offline")
+      .field("format", &self.format)
+      .field("frame_rate", &self.frame_rate)
+      .field("settings", &self.settings)
+      .field("frame", &self.frame)
+      .field("position", &self.position)
+      .finish()
+  }
+}
@@ -0,0 +1,348 @@
+use std::{
+  any::Any,
+  fmt::{Debug, Formatter},
+  marker::PhantomData,
+  sync::{Arc, Mutex},
+};
+
+use crate::{
+  chunk::Prepare,
+  command::Commands,
+  listener::{AudioListenerTrait, Handle, Publisher},
+  AudioListener, BlockInfo, BlockSize, Listener, ListenerId,
+};
+
+/// Single stage of a [Pipeline], turns an input into an output
+///
+/// `output` keeps its value from the last block, so stages can reuse allocations
+pub trait Processor: Send + 'static {
+  type Input: 'static;
+  type Output: Clone + Default + Debug + Send + Sync + 'static;
+
+  /// Processes one block, `info` can be changed for the stages after this one
+  /// (for example the amount of channels after mixing them down)
+  fn process(&mut self, input: &Self::Input, output: &mut Self::Output, info: &mut BlockInfo);
+}
+
+/// Type erased [Processor] with its output so stages of different types can be chained
+trait AnyStage: Send {
+  fn process(&mut self, input: &dyn Any, info: &mut BlockInfo);
+
+  fn output(&self) -> &dyn Any;
+}
+
+struct Stage<P: Processor> {
+  processor: P,
+  output: P::Output,
+  commands: Arc<Commands<P>>,
+}
+
+impl<P: Processor> AnyStage for Stage<P> {
+  fn process(&mut self, input: &dyn Any, info: &mut BlockInfo) {
+    // Input types are checked by `Pipeline::then` so this can't fail
+    let input = input.downcast_ref::<P::Input>().unwrap();
+
+    self.commands.apply(&mut self.processor);
+    self.processor.process(input, &mut self.output, info);
+  }
+
+  fn output(&self) -> &dyn Any {
+    &self.output
+  }
+}
+
+/// Changes the settings of a [Processor] while its [Pipeline] is running,
+/// see [Pipeline::then_controlled]
+pub struct Control<P> {
+  commands: Arc<Commands<P>>,
+}
+
+impl<P> Control<P> {
+  /// Changes the processor before it processes the next block,
+  /// changes are applied in the order they're sent so none of them get lost
+  pub fn modify(&self, f: impl FnOnce(&mut P) + Send + 'static) {
+    self.commands.push(f);
+  }
+}
+
+impl<P> Clone for Control<P> {
+  fn clone(&self) -> Self {
+    Self {
+      commands: self.commands.clone(),
+    }
+  }
+}
+
+impl<P> Debug for Control<P> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Control").finish_non_exhaustive()
+  }
+}
+
+/// Type erased [Publisher] for the output of a stage
+trait AnyTap: Send + Sync {
+  fn publish(&self, output: &dyn Any);
+
+  fn is_active(&self) -> bool;
+
+  fn is_detached(&self) -> bool;
+
+  fn detach(&self);
+
+  /// Gets the listener it publishes to
+  fn handle(&self) -> Handle;
+}
+
+impl<T: Clone + Send + Sync + 'static> AnyTap for Publisher<T> {
+  fn publish(&self, output: &dyn Any) {
+    if let Some(output) = output.downcast_ref::<T>() {
+      Publisher::publish(self, output);
+    }
+  }
+
+  fn is_active(&self) -> bool {
+    Publisher::is_active(self)
+  }
+
+  fn is_detached(&self) -> bool {
+    Publisher::is_detached(self)
+  }
+
+  fn detach(&self) {
+    Publisher::detach(self)
+  }
+
+  fn handle(&self) -> Handle {
+    Arc::new(self.clone())
+  }
+}
+
+#[derive(Default)]
+struct Stages {
+  /// Interleaved samples of the current block
+  source: Vec<f32>,
+  stages: Vec<Box<dyn AnyStage>>,
+}
+
+pub(crate) struct PipelineShared {
+  id: ListenerId,
+  /// Only touched by the audio callback
+  stages: Mutex<Stages>,
+  /// Only touched by the audio callback
+  prepare: Mutex<Prepare>,
+  /// Taps with the index of the stage they read, `0` being the source
+  taps: Vec<(usize, Box<dyn AnyTap>)>,
+}
+
+impl PipelineShared {
+  /// Gets the listener of the last stage, see [Pipeline::build]
+  pub fn output(&self) -> Handle {
+    // Added last by `Pipeline::build`
+    self.taps.last().unwrap().1.handle()
+  }
+
+  fn process(&self, stages: &mut Stages, data: &[f32], info: &BlockInfo) {
+    let Stages { source, stages } = stages;
+    let mut info = *info;
+
+    source.clear();
+    source.extend_from_slice(data);
+
+    self.publish(0, source);
+
+    let mut input = source as &dyn Any;
+
+    for (index, stage) in stages.iter_mut().enumerate() {
+      stage.process(input, &mut info);
+      input = stage.output();
+
+      self.publish(index + 1, input);
+    }
+  }
+
+  fn publish(&self, index: usize, output: &dyn Any) {
+    for (_, tap) in self.taps.iter().filter(|(i, _)| *i == index) {
+      if tap.is_active() {
+        tap.publish(output);
+      }
+    }
+  }
+}
+
+impl Debug for PipelineShared {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("PipelineShared")
+      .field("id", &self.id)
+      .field("taps", &self.taps.len())
+      .finish()
+  }
+}
+
+impl AudioListenerTrait for PipelineShared {
+  fn update(&self, data: &[f32], info: &BlockInfo) {
+    let Ok(mut stages) = self.stages.try_lock() else {
+      return;
+    };
+
+    let Ok(mut prepare) = self.prepare.try_lock() else {
+      return;
+    };
+
+    prepare.run(data, info, |data, info| {
+      self.process(&mut stages, data, info)
+    });
+  }
+
+  /// Only runs if at least one output is read
+  fn is_active(&self) -> bool {
+    self.taps.iter().any(|(_, tap)| tap.is_active())
+  }
+
+  fn is_detached(&self) -> bool {
+    self.taps.iter().all(|(_, tap)| tap.is_detached())
+  }
+
+  fn detach(&self) {
+    for (_, tap) in &self.taps {
+      tap.detach();
+    }
+  }
+
+  /// Other taps are registered on their own
+  fn wake(&self) {
+    self.output().wake();
+  }
+}
+
+/// Chain of [Processor]s that runs on every block of samples,
+/// starting from the interleaved samples as a `Vec<f32>`
+///
+/// Outputs of any stage can be read with [Self::tap] without computing them again,
+/// the output of the last stage with the listener returned by [Self::build]
+///
+/// ```no_run
+/// # use safav::*;
+/// # let host = Host::new()?;
+/// let mut pipeline = host.pipeline().then(MixChannels).then(HannWindow);
+/// let wave = pipeline.tap();
+/// let spectrum = pipeline.then(Spectrum::new(2048)).then(Smooth::new(0.5)).build();
+/// # Ok::<(), Error>(())
+/// ```
+pub struct Pipeline<T = Vec<f32>> {
+  listener: Listener,
+  sample_rate: Option<u32>,
+  block_size: Option<BlockSize>,
+  stages: Stages,
+  taps: Vec<(usize, Box<dyn AnyTap>)>,
+  _output: PhantomData<fn() -> T>,
+}
+
+impl Pipeline {
+  pub(crate) fn new(listener: &Listener) -> Self {
+    Self {
+      listener: listener.clone(),
+      sample_rate: None,
+      block_size: None,
+      stages: Stages::default(),
+      taps: Vec::new(),
+      _output: PhantomData,
+    }
+  }
+}
+
+impl<T: Clone + Default + Debug + Send + Sync + 'static> Pipeline<T> {
+  /// Adds a stage that processes the output of the last stage
+  pub fn then<P: Processor<Input = T>>(self, processor: P) -> Pipeline<P::Output> {
+    self.then_controlled(processor).0
+  }
+
+  /// Same as [Self::then] but also returns a [Control] to change the processor later
+  pub fn then_controlled<P: Processor<Input = T>>(
+    mut self,
+    processor: P,
+  ) -> (Pipeline<P::Output>, Control<P>) {
+    let commands = Arc::new(Commands::default());
+
+    self.stages.stages.push(Box::new(Stage {
+      processor,
+      output: P::Output::default(),
+      commands: commands.clone(),
+    }));
+
+    let pipeline = Pipeline {
+      listener: self.listener,
+      sample_rate: self.sample_rate,
+      block_size: self.block_size,
+      stages: self.stages,
+      taps: self.taps,
+      _output: PhantomData,
+    };
+
+    (pipeline, Control { commands })
+  }
+
+  /// Resamples blocks to `rate` before the first stage, see [Resample](crate::Resample)
+  pub fn sample_rate(self, rate: u32) -> Self {
+    Self {
+      sample_rate: Some(rate),
+      ..self
+    }
+  }
+
+  /// Runs the pipeline with blocks of exactly `size` instead of whatever size the device gives
+  pub fn block_size(self, size: BlockSize) -> Self {
+    Self {
+      block_size: Some(size),
+      ..self
+    }
+  }
+
+  /// Creates a listener that gets the output of the last stage added so far,
+  /// it can be found with [Listener::get] by its id
+  ///
+  /// [AudioListener::modify] only changes the next frame it gets, every block replaces it with the output again
+  pub fn tap(&mut self) -> AudioListener<T> {
+    let listener = self.add_tap();
+    let publisher = listener.publisher();
+
+    self.listener.register(listener.id(), Arc::new(publisher));
+
+    listener
+  }
+
+  fn add_tap(&mut self) -> AudioListener<T> {
+    let listener = AudioListener::new(&self.listener, T::default());
+    let index = self.stages.stages.len();
+
+    self.taps.push((index, Box::new(listener.publisher())));
+
+    listener
+  }
+
+  /// Registers the pipeline so it runs for every block,
+  /// returns a listener that gets the output of the last stage
+  ///
+  /// The pipeline stops once the returned listener and every tap are paused or dropped,
+  /// [Listener::remove] with the id of the returned listener removes all of them.
+  /// Its id finds the returned listener with [Listener::get] like the id of a tap, see [Self::tap]
+  pub fn build(mut self) -> AudioListener<T> {
+    let output = self.add_tap();
+    let shared = PipelineShared {
+      id: output.id(),
+      stages: Mutex::new(self.stages),
+      prepare: Mutex::new(Prepare::new(self.sample_rate, self.block_size)),
+      taps: self.taps,
+    };
+
+    self.listener.register(shared.id, Arc::new(shared));
+
+    output
+  }
+}
+
+impl Listener {
+  /// Starts a new [Pipeline]
+  pub fn pipeline(&self) -> Pipeline {
+    Pipeline::new(self)
+  }
+}
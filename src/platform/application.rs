@@ -0,0 +1,189 @@
+#![cfg(target_os = "linux")]
+
+//! Captures a single application by moving it to a null sink of its own,
+//! with a loopback from the monitor of that sink to the sink it played on so it stays audible
+
+use std::{cell::Cell, rc::Rc};
+
+use pulsectl::controllers::{
+  errors::ControllerError,
+  types::{ApplicationInfo, DeviceInfo},
+  AppControl, DeviceControl, SinkController, SourceController,
+};
+
+use crate::{Application, Error, Result};
+
+/// Prefix of the null sinks, their monitors aren't listed as devices
+pub(crate) const SINK_PREFIX: &str = "safav_app_";
+
+/// Lists sink inputs of clients, leaving out loopbacks and other streams of modules
+pub(crate) fn applications() -> Result<Vec<Application>> {
+  let mut controller = SinkController::create()?;
+
+  Ok(
+    controller
+      .list_applications()?
+      .iter()
+      .filter(|info| info.client.is_some())
+      .map(application)
+      .collect(),
+  )
+}
+
+fn application(info: &ApplicationInfo) -> Application {
+  let get = |key| info.proplist.get_str(key);
+
+  Application {
+    name: get("application.name")
+      .or_else(|| info.name.clone())
+      .unwrap_or_default(),
+    index: info.index,
+    binary: get("application.process.binary"),
+    process_id: get("application.process.id").and_then(|id| id.parse().ok()),
+  }
+}
+
+fn load_module(controller: &mut SinkController, name: &str, argument: &str) -> Result<u32> {
+  let index = Rc::new(Cell::new(u32::MAX));
+  let op = {
+    let index = index.clone();
+
+    controller
+      .handler
+      .introspect
+      .load_module(name, argument, move |module| index.set(module))
+  };
+
+  controller
+    .handler
+    .wait_for_operation(op)
+    .map_err(ControllerError::from)?;
+
+  match index.get() {
+    u32::MAX => Err(Error::ModuleLoadFailed(name.to_string())),
+    index => Ok(index),
+  }
+}
+
+fn unload_module(controller: &mut SinkController, index: u32) -> Result<()> {
+  let unloaded = Rc::new(Cell::new(false));
+  let op = {
+    let unloaded = unloaded.clone();
+
+    controller
+      .handler
+      .introspect
+      .unload_module(index, move |success| unloaded.set(success))
+  };
+
+  controller
+    .handler
+    .wait_for_operation(op)
+    .map_err(ControllerError::from)?;
+
+  match unloaded.get() {
+    true => Ok(()),
+    false => Err(Error::ModuleUnloadFailed(index)),
+  }
+}
+
+/// Routing of one captured application, it's restored when this is dropped
+pub(crate) struct AppCapture {
+  pub application: Application,
+  /// Monitor of the null sink, only `None` while it's being set up
+  source: Option<DeviceInfo>,
+  /// Sink the application played on before
+  sink: u32,
+  /// Loaded modules, unloaded in reverse
+  modules: Vec<u32>,
+}
+
+impl AppCapture {
+  pub fn new(application: &Application) -> Result<Self> {
+    let mut controller = SinkController::create()?;
+    let info = controller
+      .get_app_by_index(application.index)
+      .map_err(|_| Error::NoApplicationFound(application.name.clone()))?;
+    let sink = controller
+      .list_devices()?
+      .iter()
+      .find(|sink| sink.index == info.connection_id)
+      .and_then(|sink| sink.name.clone())
+      .ok_or(Error::NoDefaultDeviceFound)?;
+
+    let name = format!("{SINK_PREFIX}{}", application.index);
+    let monitor = format!("{name}.monitor");
+    // Anything that fails from here on unloads what was loaded when `capture` is dropped
+    let mut capture = Self {
+      application: application.clone(),
+      source: None,
+      sink: info.connection_id,
+      modules: Vec::new(),
+    };
+
+    let null = load_module(
+      &mut controller,
+      "module-null-sink",
+      &format!("sink_name={name} sink_properties=device.description={name}"),
+    )?;
+
+    capture.modules.push(null);
+
+    let loopback = load_module(
+      &mut controller,
+      "module-loopback",
+      &format!(
+        "source={monitor} sink={sink} latency_msec=20 source_dont_move=true sink_dont_move=true"
+      ),
+    )?;
+
+    capture.modules.push(loopback);
+
+    let null = controller
+      .list_devices()?
+      .into_iter()
+      .find(|sink| sink.name.as_deref() == Some(&name))
+      .ok_or_else(|| Error::NoDeviceFound(name.clone()))?;
+
+    controller.move_app_by_index(application.index, null.index)?;
+
+    capture.source = SourceController::create()?
+      .list_devices()?
+      .into_iter()
+      .find(|source| source.name.as_deref() == Some(&monitor))
+      .map(Some)
+      .ok_or(Error::NoDeviceFound(monitor))?;
+
+    Ok(capture)
+  }
+
+  /// Gets the monitor of the null sink, which only has the application in it
+  pub fn source(&self) -> &DeviceInfo {
+    self.source.as_ref().expect("set up in AppCapture::new")
+  }
+
+  /// Moves the application back to its sink and unloads the modules
+  pub fn restore(&mut self) -> Result<()> {
+    if self.modules.is_empty() {
+      return Ok(());
+    }
+
+    let mut controller = SinkController::create()?;
+
+    // The application might have closed already, anything left on the null sink is moved when it's unloaded
+    let _ = controller.move_app_by_index(self.application.index, self.sink);
+
+    while let Some(&module) = self.modules.last() {
+      unload_module(&mut controller, module)?;
+      self.modules.pop();
+    }
+
+    Ok(())
+  }
+}
+
+impl Drop for AppCapture {
+  fn drop(&mut self) {
+    let _ = self.restore();
+  }
+}
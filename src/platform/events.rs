@@ -0,0 +1,142 @@
+use std::{
+  fmt::{Debug, Formatter},
+  sync::{
+    atomic::AtomicBool,
+    mpsc::{self, Receiver, Sender},
+    Arc, Mutex, RwLock,
+  },
+  time::Duration,
+};
+
+use crate::Device;
+
+/// Change to the devices of the audio system, see [Host::device_events](crate::Host::device_events)
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum DeviceEvent {
+  Added(Device),
+  Removed(Device),
+  /// The system default changed, on Linux this is the monitor of the default sink
+  DefaultChanged(Device),
+}
+
+/// Receives [DeviceEvent]s from the moment it was created,
+/// [Host::devices](crate::Host::devices) only changes after [Host::refresh](crate::Host::refresh)
+pub type DeviceEvents = Events<DeviceEvent>;
+
+/// Receives every event sent by a [Host](crate::Host) from the moment it was created
+pub struct Events<T> {
+  receiver: Receiver<T>,
+}
+
+impl<T> Events<T> {
+  pub fn try_recv(&self) -> Option<T> {
+    self.receiver.try_recv().ok()
+  }
+
+  /// Waits for the next event, `None` once the [Host](crate::Host) was dropped
+  pub fn recv(&self) -> Option<T> {
+    self.receiver.recv().ok()
+  }
+
+  pub fn recv_timeout(&self, timeout: Duration) -> Option<T> {
+    self.receiver.recv_timeout(timeout).ok()
+  }
+}
+
+impl<T> Debug for Events<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Events").finish_non_exhaustive()
+  }
+}
+
+/// Sends events to every [Events], shared with the threads that produce them
+pub(crate) struct EventSenders<T = DeviceEvent> {
+  senders: Arc<Mutex<Vec<Sender<T>>>>,
+}
+
+impl<T> Clone for EventSenders<T> {
+  fn clone(&self) -> Self {
+    Self {
+      senders: self.senders.clone(),
+    }
+  }
+}
+
+impl<T> Default for EventSenders<T> {
+  fn default() -> Self {
+    Self {
+      senders: Default::default(),
+    }
+  }
+}
+
+impl<T: Clone> EventSenders<T> {
+  pub fn subscribe(&self) -> Events<T> {
+    let (sender, receiver) = mpsc::channel();
+
+    self.senders.lock().unwrap().push(sender);
+
+    Events { receiver }
+  }
+
+  /// Sends `event` to every receiver, forgetting the ones that were dropped
+  pub fn send(&self, event: T) {
+    self
+      .senders
+      .lock()
+      .unwrap()
+      .retain(|sender| sender.send(event.clone()).is_ok());
+  }
+}
+
+/// Shared between a backend and its watcher so capture can follow the default device
+#[derive(Debug, Default)]
+pub(crate) struct Follow {
+  #[cfg(feature = "events")]
+  pub enabled: AtomicBool,
+  /// Set while an application is captured instead of a device, which isn't moved
+  pub paused: AtomicBool,
+  /// Default that was followed or device the stream was restarted on,
+  /// the backend picks it up once it's in its list of devices
+  pub followed: Mutex<Option<Device>>,
+}
+
+impl Follow {
+  /// Changes `current` to the followed default once `devices` has it
+  pub fn resolve(&self, devices: &[Device], current: &RwLock<Option<usize>>) {
+    let mut followed = self.followed.lock().unwrap();
+    let index = followed
+      .as_ref()
+      .and_then(|device| devices.iter().position(|dev| dev.id == device.id));
+
+    if let Some(index) = index {
+      followed.take();
+      *current.write().unwrap() = Some(index);
+    }
+  }
+}
+
+/// Gets what changed between two lists of devices and their defaults,
+/// devices are compared by id so one becoming the default isn't removed and added again
+#[cfg(all(target_os = "linux", feature = "events"))]
+pub(crate) fn changes(
+  (devices, default): (&[Device], Option<&Device>),
+  (new_devices, new_default): (&[Device], Option<&Device>),
+) -> Vec<DeviceEvent> {
+  let contains =
+    |devices: &[Device], device: &Device| devices.iter().any(|dev| dev.id == device.id);
+
+  let removed = devices
+    .iter()
+    .filter(|dev| !contains(new_devices, dev))
+    .map(|dev| DeviceEvent::Removed(dev.clone()));
+  let added = new_devices
+    .iter()
+    .filter(|dev| !contains(devices, dev))
+    .map(|dev| DeviceEvent::Added(dev.clone()));
+  let default = new_default
+    .filter(|new| default.map(|default| &default.id) != Some(&new.id))
+    .map(|new| DeviceEvent::DefaultChanged(new.clone()));
+
+  removed.chain(added).chain(default).collect()
+}
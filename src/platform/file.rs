@@ -0,0 +1,561 @@
+#![cfg(feature = "file")]
+
+use std::{
+  fmt::{Debug, Formatter},
+  fs::File,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex, RwLock, Weak},
+  thread,
+  time::{Duration, Instant},
+};
+
+use cpal::{BufferSize, SampleRate, StreamConfig};
+use symphonia::core::{
+  audio::SampleBuffer,
+  codecs::{DecoderOptions, CODEC_TYPE_NULL},
+  errors::Error as DecodeError,
+  formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+  io::MediaSourceStream,
+  meta::MetadataOptions,
+  probe::Hint,
+  units::TimeBase,
+};
+
+use super::Reporter;
+use crate::{listener::DEVICE_SOURCE, notify::Notify, Device, DeviceKind, Error, Listener, Result};
+
+/// Frames given to listeners per block
+const BLOCK_SIZE: usize = 1024;
+
+/// How often a paused player checks if it should stop
+const IDLE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How fast a [FilePlayer] gives its samples to listeners
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Pace {
+  /// As fast as the file would play
+  #[default]
+  RealTime,
+  /// As fast as it can be decoded
+  Fast,
+}
+
+struct Control {
+  playing: bool,
+  looping: bool,
+  pace: Pace,
+  seek: Option<Duration>,
+  /// Frames played since the start of the file
+  position: u64,
+  finished: bool,
+  /// Whether it's the device listeners are updated by
+  selected: bool,
+}
+
+struct PlayerShared {
+  /// Source id given to [Listener::source_feed]
+  id: u64,
+  path: PathBuf,
+  device: Device,
+  sample_rate: u32,
+  channels: u16,
+  frames: Option<u64>,
+  control: Mutex<Control>,
+  notify: Notify,
+}
+
+/// Decoder of the default track of a file
+pub(crate) struct Decoder {
+  format: Box<dyn FormatReader>,
+  decoder: Box<dyn symphonia::core::codecs::Decoder>,
+  track: u32,
+  /// Unit of the track's timestamps, `None` if they're already frames
+  time_base: Option<TimeBase>,
+  sample_rate: u32,
+  buffer: Option<SampleBuffer<f32>>,
+  /// Frames to drop from the next packet after an accurate seek
+  skip: u64,
+}
+
+impl Decoder {
+  pub fn open(path: &Path) -> Result<Self> {
+    let file = File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+
+    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+      hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+      &hint,
+      stream,
+      &FormatOptions::default(),
+      &MetadataOptions::default(),
+    )?;
+
+    let track = probed
+      .format
+      .tracks()
+      .iter()
+      .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+      .ok_or_else(|| Error::NoTrackFound(path.display().to_string()))?;
+
+    let decoder =
+      symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    Ok(Self {
+      track: track.id,
+      time_base: track.codec_params.time_base,
+      sample_rate: track.codec_params.sample_rate.unwrap_or_default(),
+      format: probed.format,
+      decoder,
+      buffer: None,
+      skip: 0,
+    })
+  }
+
+  pub fn params(&self) -> &symphonia::core::codecs::CodecParameters {
+    self.decoder.codec_params()
+  }
+
+  /// Seeks to `time`, returns the frame it seeked to
+  pub fn seek(&mut self, time: Duration) -> Result<u64> {
+    let seeked = self.format.seek(
+      SeekMode::Accurate,
+      SeekTo::Time {
+        time: time.into(),
+        track_id: Some(self.track),
+      },
+    )?;
+
+    self.decoder.reset();
+
+    let required = self.frames(seeked.required_ts);
+
+    self.skip = required.saturating_sub(self.frames(seeked.actual_ts));
+
+    Ok(required)
+  }
+
+  /// Converts a timestamp of the track to frames
+  fn frames(&self, timestamp: u64) -> u64 {
+    match self.time_base {
+      Some(TimeBase { numer, denom }) if self.sample_rate > 0 => {
+        (timestamp as u128 * numer as u128 * self.sample_rate as u128 / denom.max(1) as u128) as u64
+      }
+      _ => timestamp,
+    }
+  }
+
+  /// Decodes the next packet into `samples`, `false` at the end of the file
+  pub fn next(&mut self, samples: &mut Vec<f32>) -> Result<bool> {
+    loop {
+      let packet = match self.format.next_packet() {
+        Ok(packet) => packet,
+        Err(DecodeError::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+          return Ok(false)
+        }
+        Err(err) => return Err(err.into()),
+      };
+
+      if packet.track_id() != self.track {
+        continue;
+      }
+
+      let decoded = match self.decoder.decode(&packet) {
+        Ok(decoded) => decoded,
+        // Skips corrupt packets like players do
+        Err(DecodeError::DecodeError(_)) => continue,
+        Err(err) => return Err(err.into()),
+      };
+
+      let spec = *decoded.spec();
+      let channels = spec.channels.count().max(1);
+      let capacity = decoded.capacity() as u64;
+
+      let buffer = match &mut self.buffer {
+        Some(buffer) if buffer.capacity() >= capacity as usize * channels => buffer,
+        buffer => buffer.insert(SampleBuffer::new(capacity, spec)),
+      };
+
+      buffer.copy_interleaved_ref(decoded);
+
+      let skip = (self.skip as usize * channels).min(buffer.samples().len());
+
+      self.skip -= (skip / channels) as u64;
+
+      // The whole packet was before the seek target
+      if skip == buffer.samples().len() {
+        continue;
+      }
+
+      samples.clear();
+      samples.extend_from_slice(&buffer.samples()[skip..]);
+
+      return Ok(true);
+    }
+  }
+}
+
+/// Audio file that shows up as a device of a [Host](crate::Host), see [Host::add_file](crate::Host::add_file)
+///
+/// Starts playing once it's selected with [Host::change_device](crate::Host::change_device),
+/// only one device updates listeners at a time
+#[derive(Clone)]
+pub struct FilePlayer {
+  shared: Arc<PlayerShared>,
+}
+
+impl FilePlayer {
+  pub(crate) fn open(path: &Path, id: u64, listener: &Listener) -> Result<Self> {
+    let decoder = Decoder::open(path)?;
+    let params = decoder.params();
+    let sample_rate = params.sample_rate.unwrap_or(44100);
+    let channels = params
+      .channels
+      .map_or(2, |channels| channels.count() as u16);
+    let name = path.file_name().map_or_else(
+      || path.display().to_string(),
+      |name| name.to_string_lossy().into_owned(),
+    );
+
+    let shared = Arc::new(PlayerShared {
+      id,
+      path: path.to_owned(),
+      device: Device {
+        name,
+        id: path.display().to_string(),
+        kind: DeviceKind::Virtual,
+        channels: Some(channels),
+        sample_rates: vec![sample_rate],
+        backend: "File",
+        path: Some(path.to_owned()),
+        ..Default::default()
+      },
+      sample_rate,
+      channels,
+      frames: params.n_frames,
+      control: Mutex::new(Control {
+        playing: true,
+        looping: false,
+        pace: Pace::default(),
+        seek: None,
+        position: 0,
+        finished: false,
+        selected: false,
+      }),
+      notify: Default::default(),
+    });
+
+    let config = StreamConfig {
+      channels,
+      sample_rate: SampleRate(sample_rate),
+      buffer_size: BufferSize::Fixed(BLOCK_SIZE as u32),
+    };
+
+    let feed = listener.source_feed(&config, id);
+    let reporter = listener.reporter();
+    let weak = Arc::downgrade(&shared);
+
+    thread::Builder::new()
+      .name(String::from("safav-file"))
+      .spawn(move || run(weak, decoder, feed, reporter))?;
+
+    Ok(Self { shared })
+  }
+
+  fn control<R>(&self, f: impl FnOnce(&mut Control) -> R) -> R {
+    let value = f(&mut self.shared.control.lock().unwrap());
+
+    self.shared.notify.notify();
+
+    value
+  }
+
+  /// Gets the device of this file
+  pub fn device(&self) -> &Device {
+    &self.shared.device
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.shared.path
+  }
+
+  pub fn sample_rate(&self) -> u32 {
+    self.shared.sample_rate
+  }
+
+  pub fn channels(&self) -> u16 {
+    self.shared.channels
+  }
+
+  /// Gets how long the file is, `None` if the format doesn't say
+  pub fn duration(&self) -> Option<Duration> {
+    let frames = self.shared.frames?;
+
+    Some(Duration::from_secs_f64(
+      frames as f64 / self.shared.sample_rate as f64,
+    ))
+  }
+
+  /// Gets how far into the file it is
+  pub fn position(&self) -> Duration {
+    let position = self.shared.control.lock().unwrap().position;
+
+    Duration::from_secs_f64(position as f64 / self.shared.sample_rate as f64)
+  }
+
+  /// Continues playing, from the start if it already finished
+  pub fn play(&self) {
+    self.control(|control| {
+      if control.finished {
+        control.seek = Some(Duration::ZERO);
+        control.finished = false;
+      }
+
+      control.playing = true;
+    });
+  }
+
+  pub fn pause(&self) {
+    self.control(|control| control.playing = false);
+  }
+
+  pub fn is_playing(&self) -> bool {
+    self.shared.control.lock().unwrap().playing
+  }
+
+  /// Whether it reached the end of the file without looping
+  pub fn is_finished(&self) -> bool {
+    self.shared.control.lock().unwrap().finished
+  }
+
+  /// Jumps to `time` into the file, even while paused
+  pub fn seek(&self, time: Duration) {
+    self.control(|control| {
+      control.seek = Some(time);
+      control.finished = false;
+    });
+  }
+
+  /// Whether it starts over at the end of the file, off by default
+  pub fn set_looping(&self, looping: bool) {
+    self.control(|control| control.looping = looping);
+  }
+
+  pub fn is_looping(&self) -> bool {
+    self.shared.control.lock().unwrap().looping
+  }
+
+  /// Changes how fast samples are given to listeners
+  pub fn set_pace(&self, pace: Pace) {
+    self.control(|control| control.pace = pace);
+  }
+
+  pub fn pace(&self) -> Pace {
+    self.shared.control.lock().unwrap().pace
+  }
+
+  fn select(&self, selected: bool) {
+    self.control(|control| control.selected = selected);
+  }
+}
+
+impl Debug for FilePlayer {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let control = self.shared.control.lock().unwrap();
+
+    f.debug_struct("FilePlayer")
+      .field("path", &self.shared.path)
+      .field("sample_rate", &self.shared.sample_rate)
+      .field("channels", &self.shared.channels)
+      .field("position", &control.position)
+      .field("playing", &control.playing)
+      .field("looping", &control.looping)
+      .field("pace", &control.pace)
+      .field("selected", &control.selected)
+      .finish()
+  }
+}
+
+/// Decodes and gives blocks to listeners until every handle of the player is dropped
+fn run(
+  shared: Weak<PlayerShared>,
+  mut decoder: Decoder,
+  mut feed: impl FnMut(&[f32]),
+  reporter: Arc<Reporter>,
+) {
+  let mut samples = Vec::new();
+  let mut offset = 0;
+  // When it started playing and how many frames it played since then
+  let mut clock: Option<(Instant, u64)> = None;
+
+  while let Some(shared) = shared.upgrade() {
+    let ready = shared.notify.wait_until(Some(IDLE_TIMEOUT), || {
+      let control = shared.control.lock().unwrap();
+
+      control.seek.is_some() || (control.playing && control.selected)
+    });
+
+    if !ready {
+      clock = None;
+      continue;
+    }
+
+    let channels = shared.channels.max(1) as usize;
+    let (seek, pace) = {
+      let mut control = shared.control.lock().unwrap();
+
+      (control.seek.take(), control.pace)
+    };
+
+    if let Some(time) = seek {
+      match decoder.seek(time) {
+        Ok(position) => shared.control.lock().unwrap().position = position,
+        Err(err) => reporter.warn(err),
+      }
+
+      samples.clear();
+      offset = 0;
+      clock = None;
+      continue;
+    }
+
+    if offset >= samples.len() {
+      offset = 0;
+
+      match decoder.next(&mut samples) {
+        Ok(true) => {}
+        Ok(false) => {
+          let mut control = shared.control.lock().unwrap();
+
+          samples.clear();
+
+          if control.looping {
+            control.seek = Some(Duration::ZERO);
+          } else {
+            control.playing = false;
+            control.finished = true;
+          }
+
+          continue;
+        }
+        Err(err) => {
+          reporter.warn(err);
+          shared.control.lock().unwrap().playing = false;
+          continue;
+        }
+      }
+    }
+
+    let end = (offset + BLOCK_SIZE * channels).min(samples.len());
+    let frames = ((end - offset) / channels) as u64;
+
+    feed(&samples[offset..end]);
+    offset = end;
+
+    shared.control.lock().unwrap().position += frames;
+
+    if pace == Pace::RealTime {
+      let (start, played) = clock.get_or_insert_with(|| (Instant::now(), 0));
+
+      *played += frames;
+
+      let target = *start + Duration::from_secs_f64(*played as f64 / shared.sample_rate as f64);
+
+      thread::sleep(target.saturating_duration_since(Instant::now()));
+    } else {
+      clock = None;
+    }
+  }
+}
+
+/// Files added to a [Host](crate::Host), listed after the devices of the backend
+#[derive(Debug, Default)]
+pub(crate) struct Files {
+  players: Vec<FilePlayer>,
+  /// Devices of the backend followed by the device of every file
+  devices: Vec<Device>,
+  /// Backend devices in `devices`
+  offset: usize,
+  selected: RwLock<Option<usize>>,
+}
+
+impl Files {
+  pub fn add(
+    &mut self,
+    path: &Path,
+    listener: &Listener,
+    devices: &[Device],
+  ) -> Result<FilePlayer> {
+    // Ids only need to be different from the device stream and each other
+    let id = DEVICE_SOURCE + 1 + self.players.len() as u64;
+    let player = FilePlayer::open(path, id, listener)?;
+
+    self.players.push(player.clone());
+    self.sync(devices);
+
+    Ok(player)
+  }
+
+  /// Updates the list of devices after the backend refreshed its devices
+  pub fn sync(&mut self, devices: &[Device]) {
+    self.offset = devices.len();
+    self.devices.clear();
+    self.devices.extend_from_slice(devices);
+    self
+      .devices
+      .extend(self.players.iter().map(|player| player.device().clone()));
+  }
+
+  /// Gets every device, `None` if no files were added
+  pub fn devices(&self) -> Option<&Vec<Device>> {
+    (!self.players.is_empty()).then_some(&self.devices)
+  }
+
+  /// Gets the index in [Self::devices] of the selected file
+  pub fn current_device_index(&self) -> Option<usize> {
+    self
+      .selected
+      .read()
+      .unwrap()
+      .map(|index| self.offset + index)
+  }
+
+  /// Gets the index of the file of `device`
+  pub fn find(&self, device: &Device) -> Option<usize> {
+    device.path.as_ref()?;
+
+    self
+      .players
+      .iter()
+      .position(|player| player.device() == device)
+  }
+
+  /// Gets the index of the file at `index` in [Self::devices]
+  pub fn find_index(&self, index: usize) -> Option<usize> {
+    index
+      .checked_sub(self.offset)
+      .filter(|index| *index < self.players.len())
+  }
+
+  /// Makes the file at `index` update listeners, or the backend if it's `None`
+  pub fn select(&self, index: Option<usize>, listener: &Listener) {
+    let mut selected = self.selected.write().unwrap();
+
+    if let Some(player) = selected.and_then(|index| self.players.get(index)) {
+      player.select(false);
+    }
+
+    match index.and_then(|index| self.players.get(index)) {
+      Some(player) => {
+        listener.select_source(player.shared.id);
+        player.select(true);
+      }
+      None => listener.select_source(DEVICE_SOURCE),
+    }
+
+    *selected = index;
+  }
+}
@@ -0,0 +1,122 @@
+use std::{
+  sync::mpsc::{self, SyncSender},
+  thread::{self, JoinHandle},
+};
+
+use cpal::{
+  traits::{DeviceTrait, StreamTrait},
+  FromSample, SampleFormat, SizedSample, Stream, StreamConfig, StreamError,
+};
+
+use crate::{Error, Listener, Result, Status};
+
+/// Builds an input stream for a device with any sample format and channel count,
+/// samples are converted to normalized `f32` before they reach `listener`
+pub(crate) fn build_input_stream(
+  device: &cpal::Device,
+  config: &StreamConfig,
+  format: SampleFormat,
+  listener: &Listener,
+) -> Result<Stream> {
+  match format {
+    SampleFormat::F32 => {
+      let data_cb = listener.callback(config).get();
+
+      Ok(device.build_input_stream(config, data_cb, error_callback(listener), None)?)
+    }
+    SampleFormat::I8 => build::<i8>(device, config, listener),
+    SampleFormat::I16 => build::<i16>(device, config, listener),
+    SampleFormat::I32 => build::<i32>(device, config, listener),
+    SampleFormat::I64 => build::<i64>(device, config, listener),
+    SampleFormat::U8 => build::<u8>(device, config, listener),
+    SampleFormat::U16 => build::<u16>(device, config, listener),
+    SampleFormat::U32 => build::<u32>(device, config, listener),
+    SampleFormat::U64 => build::<u64>(device, config, listener),
+    SampleFormat::F64 => build::<f64>(device, config, listener),
+    format => Err(Error::UnsupportedSampleFormat(format)),
+  }
+}
+
+/// Reports errors of the stream to the app and to a supervisor that can rebuild it
+fn error_callback(listener: &Listener) -> impl FnMut(StreamError) + Send + 'static {
+  let health = listener.health();
+  let reporter = listener.reporter();
+
+  move |err| {
+    health.fail(&err);
+    reporter.report(Status::StreamError(err.to_string()));
+  }
+}
+
+fn build<S>(device: &cpal::Device, config: &StreamConfig, listener: &Listener) -> Result<Stream>
+where
+  S: SizedSample,
+  f32: FromSample<S>,
+{
+  let mut callback = listener.callback(config).get();
+  // Only allocates until it fits the largest block
+  let mut converted = Vec::new();
+
+  let data_cb = move |data: &[S], info: &_| {
+    converted.clear();
+    converted.extend(data.iter().map(|sample| sample.to_sample::<f32>()));
+
+    callback(&converted, info);
+  };
+
+  Ok(device.build_input_stream(config, data_cb, error_callback(listener), None)?)
+}
+
+/// Plays a stream on a thread of its own, so it can be rebuilt from any thread
+/// even though a cpal [Stream] can't leave the thread it was built on
+pub(crate) struct StreamHandle {
+  stop: Option<SyncSender<()>>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl StreamHandle {
+  /// Builds and plays the stream returned by `build`, it's stopped when this is dropped
+  pub fn spawn(build: impl FnOnce() -> Result<Stream> + Send + 'static) -> Result<Self> {
+    let (ready, started) = mpsc::sync_channel(1);
+    let (stop, stopped) = mpsc::sync_channel::<()>(1);
+    let thread = thread::Builder::new()
+      .name(String::from("safav-stream"))
+      .spawn(move || {
+        let stream = build().and_then(|stream| {
+          stream.play()?;
+          Ok(stream)
+        });
+
+        match stream {
+          Ok(stream) => {
+            let _ = ready.send(Ok(()));
+            // Only returns once the handle is dropped
+            let _ = stopped.recv();
+
+            drop(stream);
+          }
+          Err(err) => {
+            let _ = ready.send(Err(err));
+          }
+        }
+      })?;
+
+    // Only disconnects without sending if `build` panicked
+    started.recv().map_err(|_| Error::StreamThreadStopped)??;
+
+    Ok(Self {
+      stop: Some(stop),
+      thread: Some(thread),
+    })
+  }
+}
+
+impl Drop for StreamHandle {
+  fn drop(&mut self) {
+    self.stop.take();
+
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
@@ -0,0 +1,484 @@
+#![cfg(all(target_os = "linux", feature = "jack"))]
+
+//! JACK backend, registers its own input ports and connects them to the ports of the chosen device
+//!
+//! Every client with audio outputs shows up as a device, along with a monitor of the system playback
+//! which connects to whatever is connected to the playback ports. Specific ports can be captured
+//! by changing to a device from [Device::jack_ports]:
+//!
+//! ```no_run
+//! # fn main() -> safav::Result<()> {
+//! let mut host = safav::Host::jack()?;
+//!
+//! host.listen()?;
+//! host.change_device(&safav::Device::jack_ports(&["synth:out_l", "synth:out_r"]))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Can be tried without any audio hardware by running jackd with the dummy driver:
+//!
+//! ```sh
+//! jackd -d dummy -r 48000 &
+//! cargo run --example jack --features jack
+//! ```
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use ::jack::{
+  AsyncClient, AudioIn, Client, ClientOptions, Control, Frames, NotificationHandler, Port,
+  PortFlags, ProcessHandler, ProcessScope,
+};
+use cpal::{BufferSize, SampleRate, StreamConfig};
+
+use super::{Backend, Reporter};
+use crate::{Device, DeviceKind, Error, Listener, Result, SampleFormat, Status};
+
+/// Port type of [AudioIn] and [AudioOut](::jack::AudioOut)
+const AUDIO: &str = "32 bit float mono audio";
+
+/// Id of the device that captures everything connected to the system playback ports
+const PLAYBACK_MONITOR: &str = "safav:playback-monitor";
+
+const CHANNELS: usize = 2;
+
+/// See [Device::backend]
+const BACKEND: &str = "JACK";
+
+type Feed = Box<dyn FnMut(&[f32]) + Send>;
+
+/// Interleaves the input ports into one block for the listener
+struct Process {
+  ports: Vec<Port<AudioIn>>,
+  feed: Feed,
+  samples: Vec<f32>,
+}
+
+impl ProcessHandler for Process {
+  fn process(&mut self, _: &Client, scope: &ProcessScope) -> Control {
+    let channels = self.ports.len();
+
+    // Only allocates if JACK runs with a bigger buffer than it said it would
+    self.samples.clear();
+    self
+      .samples
+      .resize(scope.n_frames() as usize * channels, 0.);
+
+    for (channel, port) in self.ports.iter().enumerate() {
+      for (frame, sample) in port.as_slice(scope).iter().enumerate() {
+        self.samples[frame * channels + channel] = *sample;
+      }
+    }
+
+    (self.feed)(&self.samples);
+
+    Control::Continue
+  }
+
+  fn buffer_size(&mut self, _: &Client, size: Frames) -> Control {
+    self.samples.reserve(size as usize * self.ports.len());
+
+    Control::Continue
+  }
+}
+
+/// Reports xruns, called on the notification thread of the client
+struct Notifications {
+  reporter: Arc<Reporter>,
+}
+
+impl NotificationHandler for Notifications {
+  fn xrun(&mut self, _: &Client) -> Control {
+    self.reporter.report(Status::Xrun);
+
+    Control::Continue
+  }
+}
+
+enum State {
+  Inactive(Client, Vec<Port<AudioIn>>),
+  Active(AsyncClient<Notifications, Process>),
+}
+
+impl State {
+  fn client(&self) -> &Client {
+    match self {
+      State::Inactive(client, _) => client,
+      State::Active(client) => client.as_client(),
+    }
+  }
+}
+
+pub struct JackHost {
+  pub devices: Vec<Device>,
+  pub listener: Listener,
+  pub current_device_index: RwLock<Option<usize>>,
+  /// Full names of the input ports of this client
+  ports: Vec<String>,
+  /// Device that was changed to, also kept if it isn't listed
+  device: RwLock<Option<Device>>,
+  state: Mutex<Option<State>>,
+}
+
+/// Lists the playback monitor and every other client with audio outputs, the first one is the default
+fn devices(client: &Client) -> Vec<Device> {
+  let mut devices = Vec::new();
+  let own = format!("{}:", client.name());
+  let sample_rate = client.sample_rate() as u32;
+
+  let playback = client.ports(
+    None,
+    Some(AUDIO),
+    PortFlags::IS_INPUT | PortFlags::IS_PHYSICAL,
+  );
+  // Ports of the audio hardware, every other port belongs to an application
+  let physical = client.ports(
+    None,
+    Some(AUDIO),
+    PortFlags::IS_OUTPUT | PortFlags::IS_PHYSICAL,
+  );
+
+  if !playback.is_empty() {
+    devices.push(Device {
+      name: String::from("System playback (monitor)"),
+      id: String::from(PLAYBACK_MONITOR),
+      kind: DeviceKind::OutputMonitor,
+      channels: Some(CHANNELS as u16),
+      ..Default::default()
+    });
+  }
+
+  for port in client.ports(None, Some(AUDIO), PortFlags::IS_OUTPUT) {
+    let Some((name, _)) = port.split_once(':') else {
+      continue;
+    };
+
+    if port.starts_with(&own) {
+      continue;
+    }
+
+    if let Some(device) = devices.iter_mut().find(|dev| dev.id == name) {
+      device.channels = device.channels.map(|channels| channels + 1);
+      continue;
+    }
+
+    let kind = match physical.contains(&port) {
+      true => DeviceKind::Input,
+      false => DeviceKind::Application,
+    };
+
+    devices.push(Device {
+      name: name.to_string(),
+      id: name.to_string(),
+      kind,
+      channels: Some(1),
+      index: devices.len() as u32,
+      ..Default::default()
+    });
+  }
+
+  for (index, device) in devices.iter_mut().enumerate() {
+    device.sample_rates = vec![sample_rate];
+    device.formats = vec![SampleFormat::F32];
+    device.default = index == 0;
+    device.backend = BACKEND;
+  }
+
+  devices
+}
+
+/// Gets the output ports that should be connected to every channel for `device`
+fn sources(client: &Client, device: &Device) -> Vec<Vec<String>> {
+  let ports = if device.id == PLAYBACK_MONITOR {
+    let playback = client.ports(
+      None,
+      Some(AUDIO),
+      PortFlags::IS_INPUT | PortFlags::IS_PHYSICAL,
+    );
+    let outputs = client.ports(None, Some(AUDIO), PortFlags::IS_OUTPUT);
+    let own = format!("{}:", client.name());
+
+    return (0..CHANNELS)
+      .map(|channel| {
+        let Some(playback) = playback.get(channel) else {
+          return Vec::new();
+        };
+
+        outputs
+          .iter()
+          .filter(|port| !port.starts_with(&own))
+          .filter(|port| {
+            client
+              .port_by_name(port)
+              .is_some_and(|port| port.is_connected_to(playback).unwrap_or(false))
+          })
+          .cloned()
+          .collect()
+      })
+      .collect();
+  } else if device.id.contains(':') {
+    device
+      .id
+      .split(',')
+      .map(str::trim)
+      .map(String::from)
+      .collect()
+  } else {
+    let prefix = format!("{}:", device.id);
+
+    client
+      .ports(None, Some(AUDIO), PortFlags::IS_OUTPUT)
+      .into_iter()
+      .filter(|port| port.starts_with(&prefix))
+      .collect::<Vec<_>>()
+  };
+
+  // Mono devices go to every channel
+  (0..CHANNELS)
+    .map(|channel| match ports.is_empty() {
+      true => Vec::new(),
+      false => vec![ports[channel % ports.len()].clone()],
+    })
+    .collect()
+}
+
+/// Opens a client with its input ports, along with their full names
+fn open() -> Result<(Client, Vec<Port<AudioIn>>, Vec<String>)> {
+  let (client, _) = Client::new("safav", ClientOptions::NO_START_SERVER)?;
+  let ports = (1..=CHANNELS)
+    .map(|channel| client.register_port(&format!("in_{channel}"), AudioIn))
+    .collect::<std::result::Result<Vec<_>, _>>()?;
+  let names = ports
+    .iter()
+    .map(Port::name)
+    .collect::<std::result::Result<Vec<_>, _>>()?;
+
+  Ok((client, ports, names))
+}
+
+impl JackHost {
+  pub fn new() -> Result<Self> {
+    let (client, ports, names) = open()?;
+
+    Ok(Self {
+      devices: devices(&client),
+      listener: Listener::new(),
+      current_device_index: RwLock::default(),
+      ports: names,
+      device: RwLock::default(),
+      state: Mutex::new(Some(State::Inactive(client, ports))),
+    })
+  }
+
+  pub fn current_device_index(&self) -> Option<usize> {
+    *self.current_device_index.read().unwrap()
+  }
+
+  pub fn current_device(&self) -> Option<&Device> {
+    self
+      .current_device_index()
+      .and_then(|i| self.devices.get(i))
+  }
+
+  pub fn default_device(&self) -> Result<&Device> {
+    self.devices.first().ok_or(Error::NoDefaultDeviceFound)
+  }
+
+  pub fn devices(&self) -> &Vec<Device> {
+    &self.devices
+  }
+
+  pub fn change_device_by_index(&self, index: usize) -> Result<()> {
+    let device = self
+      .devices
+      .get(index)
+      .ok_or(Error::InvalidDeviceIndex(index))?
+      .to_owned();
+
+    self.change_device(&device)
+  }
+
+  /// Connects the input ports to `device`, which doesn't have to be listed to capture specific ports
+  pub fn change_device(&self, device: &Device) -> Result<()> {
+    let state = self.state.lock().unwrap();
+
+    // Connections only stick once the client is active, so [Self::listen] connects it then
+    if let Some(State::Active(client)) = &*state {
+      self.connect(client.as_client(), device)?;
+    }
+
+    let index = self.devices.iter().position(|dev| dev.id == device.id);
+
+    *self.device.write().unwrap() = Some(device.clone());
+    *self.current_device_index.write().unwrap() = index;
+
+    Ok(())
+  }
+
+  fn connect(&self, client: &Client, device: &Device) -> Result<()> {
+    for port in &self.ports {
+      if let Some(port) = client.port_by_name(port) {
+        client.disconnect(&port)?;
+      }
+    }
+
+    for (sources, port) in sources(client, device).iter().zip(&self.ports) {
+      for source in sources {
+        client.connect_ports_by_name(source, port)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn listen(&mut self) -> Result<()> {
+    let mut state = self.state.lock().unwrap();
+
+    let device = match self.device.read().unwrap().clone() {
+      Some(device) => device,
+      None => self.default_device()?.clone(),
+    };
+    let (client, ports) = match state.take() {
+      Some(State::Inactive(client, ports)) => (client, ports),
+      Some(active) => {
+        *state = Some(active);
+        return Ok(());
+      }
+      // The client is gone after activating or deactivating it failed, so a new one is opened
+      None => {
+        let (client, ports, names) = open()?;
+
+        self.ports = names;
+        (client, ports)
+      }
+    };
+
+    let feed = self.listener.feed(&StreamConfig {
+      channels: CHANNELS as u16,
+      sample_rate: SampleRate(client.sample_rate() as u32),
+      buffer_size: BufferSize::Fixed(client.buffer_size()),
+    });
+    let process = Process {
+      samples: Vec::with_capacity(client.buffer_size() as usize * ports.len()),
+      ports,
+      feed: Box::new(feed),
+    };
+
+    let notifications = Notifications {
+      reporter: self.listener.reporter(),
+    };
+    let client = client.activate_async(notifications, process)?;
+    let connected = self.connect(client.as_client(), &device);
+
+    // Stays active even if a port is gone, so changing to another device still works
+    *state = Some(State::Active(client));
+    connected?;
+
+    let index = self.devices.iter().position(|dev| dev.id == device.id);
+
+    *self.device.write().unwrap() = Some(device);
+    *self.current_device_index.write().unwrap() = index;
+
+    Ok(())
+  }
+
+  /// Deactivates the client, keeping its ports so [Self::listen] can activate it again,
+  /// if deactivating fails the client is closed and [Self::listen] opens a new one
+  pub fn stop(&mut self) -> Result<()> {
+    let mut state = self.state.lock().unwrap();
+
+    match state.take() {
+      Some(State::Active(client)) => {
+        let (client, _, process) = client.deactivate()?;
+
+        *state = Some(State::Inactive(client, process.ports));
+      }
+      inactive => *state = inactive,
+    }
+
+    Ok(())
+  }
+
+  /// Lists clients again and reconnects the current device,
+  /// so the playback monitor picks up anything that started playing since
+  pub fn refresh(&mut self) -> Result<()> {
+    let state = self.state.lock().unwrap();
+    let Some(client) = state.as_ref().map(State::client) else {
+      return Ok(());
+    };
+
+    let current = self.device.read().unwrap().clone();
+    let devices = devices(client);
+
+    *self.current_device_index.write().unwrap() = current
+      .as_ref()
+      .and_then(|current| devices.iter().position(|dev| dev.id == current.id));
+
+    if let (Some(current), State::Active(_)) = (&current, state.as_ref().unwrap()) {
+      self.connect(client, current)?;
+    }
+
+    drop(state);
+    self.devices = devices;
+
+    Ok(())
+  }
+}
+
+impl Device {
+  /// Creates a device that captures specific JACK ports by their full names, like `system:capture_1`,
+  /// every port goes to the channel at its position and a single port goes to both
+  pub fn jack_ports(ports: &[&str]) -> Self {
+    Self {
+      name: ports.join(", "),
+      id: ports.join(","),
+      kind: DeviceKind::Virtual,
+      channels: Some(ports.len() as u16),
+      formats: vec![SampleFormat::F32],
+      backend: BACKEND,
+      ..Default::default()
+    }
+  }
+}
+
+impl Backend for JackHost {
+  fn listener(&self) -> &Listener {
+    &self.listener
+  }
+
+  fn current_device_index(&self) -> Option<usize> {
+    JackHost::current_device_index(self)
+  }
+
+  fn current_device(&self) -> Option<&Device> {
+    JackHost::current_device(self)
+  }
+
+  fn default_device(&self) -> Result<&Device> {
+    JackHost::default_device(self)
+  }
+
+  fn devices(&self) -> &Vec<Device> {
+    JackHost::devices(self)
+  }
+
+  fn change_device_by_index(&self, index: usize) -> Result<()> {
+    JackHost::change_device_by_index(self, index)
+  }
+
+  fn change_device(&self, device: &Device) -> Result<()> {
+    JackHost::change_device(self, device)
+  }
+
+  fn listen(&mut self) -> Result<()> {
+    JackHost::listen(self)
+  }
+
+  fn stop(&mut self) -> Result<()> {
+    JackHost::stop(self)
+  }
+
+  fn refresh(&mut self) -> Result<()> {
+    JackHost::refresh(self)
+  }
+}
@@ -1,22 +1,46 @@
 #![cfg(target_os = "linux")]
 
-use std::{sync::RwLock, thread::sleep, time::Duration};
+use std::{
+  sync::{atomic::Ordering, Arc, Mutex, RwLock},
+  thread::sleep,
+  time::Duration,
+};
 
 use cpal::{
-  traits::{DeviceTrait, HostTrait, StreamTrait},
-  Host, HostId, Stream,
+  traits::{DeviceTrait, HostTrait},
+  HostId,
 };
-use pulsectl::controllers::{types::ApplicationInfo, AppControl, DeviceControl, SourceController};
-
-use crate::{Device, Error, Listener, Result};
+use pulsectl::controllers::{types::ApplicationInfo, AppControl, SourceController};
+
+use super::{
+  application::{self, AppCapture},
+  events::Follow,
+  input::{build_input_stream, StreamHandle},
+  source,
+  supervisor::Restart,
+  Backend,
+};
+#[cfg(feature = "events")]
+use super::{
+  events::{DeviceEvents, EventSenders},
+  watch::Watcher,
+};
+use crate::{Application, Device, Error, Listener, Result};
 
 pub struct LinuxHost {
-  pub host: Host,
   pub devices: Vec<Device>,
   pub listener: Listener,
-  pub stream: Option<Stream>,
+  /// Shared with the restarter, which rebuilds it
+  pub(crate) stream: Arc<Mutex<Option<StreamHandle>>>,
   pub app: Option<ApplicationInfo>,
   pub current_device_index: RwLock<Option<usize>>,
+  /// Application that's captured instead of a device
+  pub(crate) application: Arc<Mutex<Option<AppCapture>>>,
+  #[cfg(feature = "events")]
+  pub(crate) events: EventSenders,
+  pub(crate) follow: Arc<Follow>,
+  #[cfg(feature = "events")]
+  pub(crate) watcher: Mutex<Option<Watcher>>,
 }
 
 fn get_application_name() -> Result<String> {
@@ -30,25 +54,48 @@ fn get_application_name() -> Result<String> {
     .ok_or(Error::NoApplicationName)
 }
 
+/// Finds the record stream of this application by its name
+fn find_app(controller: &mut SourceController) -> Result<ApplicationInfo> {
+  let name = get_application_name()?;
+  let apps = controller.list_applications()?;
+
+  Ok(
+    apps
+      .iter()
+      .find(|app| app.proplist.get_str("application.name").as_ref() == Some(&name))
+      .ok_or(Error::NoApplicationFound(name))?
+      .to_owned(),
+  )
+}
+
+/// Plays the default ALSA input, which goes through PulseAudio and is moved to the device that's listened to
+fn default_stream(listener: &Listener) -> Result<StreamHandle> {
+  let listener = listener.clone();
+
+  StreamHandle::spawn(move || {
+    let device = cpal::host_from_id(HostId::Alsa)?
+      .default_input_device()
+      .ok_or(Error::NoDefaultDeviceFound)?;
+    let supported = device.default_input_config()?;
+
+    build_input_stream(
+      &device,
+      &supported.config(),
+      supported.sample_format(),
+      &listener,
+    )
+  })
+}
+
+/// Lists sources with the default one first, see [source::mark_default]
 fn devices() -> Result<Vec<Device>> {
   let mut controller = SourceController::create()?;
-  let mut default = Some(0);
-  let mut devices = controller
-    .list_devices()?
-    .iter()
-    .enumerate()
-    .filter_map(|(index, info)| {
-      default = info.monitor.map(|_| index);
-
-      Some(Device {
-        name: info.description.clone()?,
-        index: info.index,
-        id: info.name.clone()?,
-      })
-    })
+  let mut devices = source::sources(&mut controller)?
+    .into_iter()
+    .map(|(device, _)| device)
     .collect::<Vec<_>>();
 
-  if let Some(default) = default {
+  if let Some(default) = source::mark_default(&mut controller, &mut devices)? {
     devices.swap(0, default);
   }
 
@@ -57,19 +104,29 @@ fn devices() -> Result<Vec<Device>> {
 
 impl LinuxHost {
   pub fn new() -> Result<Self> {
-    let host = cpal::host_from_id(HostId::Alsa)?;
+    // Checked here so it fails before listening, the stream opens its own on its thread
+    cpal::host_from_id(HostId::Alsa)?;
 
     Ok(Self {
-      host,
       devices: devices()?,
-      listener: Listener::default(),
-      stream: None,
+      listener: Listener::new(),
+      stream: Arc::default(),
       app: None,
       current_device_index: RwLock::default(),
+      application: Arc::default(),
+      #[cfg(feature = "events")]
+      events: EventSenders::default(),
+      follow: Arc::default(),
+      #[cfg(feature = "events")]
+      watcher: Mutex::default(),
     })
   }
 
   pub fn current_device_index(&self) -> Option<usize> {
+    self
+      .follow
+      .resolve(&self.devices, &self.current_device_index);
+
     *self.current_device_index.read().unwrap()
   }
 
@@ -88,32 +145,28 @@ impl LinuxHost {
   }
 
   fn _get_app(&self, controller: &mut SourceController) -> Result<ApplicationInfo> {
-    if let Some(app) = &self.app {
-      return Ok(controller.get_app_by_index(app.index)?);
-    };
-
-    let name = get_application_name()?;
-    let apps = controller.list_applications()?;
-
-    Ok(
-      apps
-        .iter()
-        .find(|app| app.proplist.get_str("application.name").as_ref() == Some(&name))
-        .ok_or(Error::NoApplicationFound(name))?
-        .to_owned(),
-    )
+    // A restarted stream gets a new index, so it's found by name again
+    let app = self
+      .app
+      .as_ref()
+      .and_then(|app| controller.get_app_by_index(app.index).ok());
+
+    match app {
+      Some(app) => Ok(app),
+      None => find_app(controller),
+    }
   }
 
   fn _change_device(
     &self,
     controller: &mut SourceController,
     app: &ApplicationInfo,
-    device: &Device,
+    source: u32,
   ) -> Result<()> {
     // Needs to have some delay, 20 ms seems to have no issues from my testing
     // don't know why this sometimes doesn't work if you do it too fast though
     sleep(Duration::from_millis(20));
-    controller.move_app_by_index(app.index, device.index)?;
+    controller.move_app_by_index(app.index, source)?;
 
     Ok(())
   }
@@ -138,9 +191,16 @@ impl LinuxHost {
   }
 
   pub fn change_device(&self, device: &Device) -> Result<()> {
-    match &self.app {
-      Some(app) => self._change_device(&mut SourceController::create()?, app, device)?,
-      None => (),
+    if let Some(mut capture) = self.application.lock().unwrap().take() {
+      capture.restore()?;
+      self.follow.paused.store(false, Ordering::Release);
+    }
+
+    if self.app.is_some() {
+      let mut controller = SourceController::create()?;
+      let app = self._get_app(&mut controller)?;
+
+      self._change_device(&mut controller, &app, device.index)?;
     }
 
     let index = Self::_get_device_index(&self.devices, device.index);
@@ -151,38 +211,37 @@ impl LinuxHost {
   }
 
   pub fn listen(&mut self) -> Result<()> {
-    let device = self
-      .host
-      .default_input_device()
-      .ok_or(Error::NoDefaultDeviceFound)?;
-
-    let config = device.default_input_config()?.config();
+    {
+      let mut stream = self.stream.lock().unwrap();
 
-    let data_cb = self.listener.callback().get();
-    let err_cb = |err| eprintln!("{err}");
-    let stream = device.build_input_stream(&config, data_cb, err_cb)?;
-
-    stream.play()?;
-
-    self.stream = Some(stream);
+      stream.take();
+      *stream = Some(default_stream(&self.listener)?);
+    }
 
     let mut controller = SourceController::create()?;
     let app = self._get_app(&mut controller)?;
-
-    match self.current_device() {
-      Some(device) => {
-        self._change_device(&mut controller, &app, device)?;
+    let application = self
+      .application
+      .lock()
+      .unwrap()
+      .as_ref()
+      .map(|capture| capture.source().index);
+
+    match (application, self.current_device()) {
+      (Some(source), _) => {
+        self._change_device(&mut controller, &app, source)?;
+      }
+      (None, Some(device)) => {
+        self._change_device(&mut controller, &app, device.index)?;
       }
-      None => {
+      (None, None) => {
         sleep(Duration::from_millis(20));
         let app = self._get_app(&mut controller)?;
 
-        println!("{} {}", app.connection_id, app.connection_id == u32::MAX);
-
         let index = if app.connection_id == u32::MAX {
           let default = self.default_device()?;
 
-          self._change_device(&mut controller, &app, default)?;
+          self._change_device(&mut controller, &app, default.index)?;
 
           Self::_get_device_index(&self.devices, default.index)
         } else {
@@ -198,6 +257,14 @@ impl LinuxHost {
     Ok(())
   }
 
+  pub fn stop(&mut self) -> Result<()> {
+    self.stream.lock().unwrap().take();
+    // Its record stream is gone, [Self::listen] finds the new one
+    self.app = None;
+
+    Ok(())
+  }
+
   pub fn refresh(&mut self) -> Result<()> {
     let devices = devices()?;
 
@@ -221,4 +288,205 @@ impl LinuxHost {
 
     Ok(())
   }
+
+  pub fn applications(&self) -> Result<Vec<Application>> {
+    application::applications()
+  }
+
+  pub fn current_application(&self) -> Option<Application> {
+    self
+      .application
+      .lock()
+      .unwrap()
+      .as_ref()
+      .map(|capture| capture.application.clone())
+  }
+
+  pub fn capture_application(&self, application: &Application) -> Result<()> {
+    let mut current = self.application.lock().unwrap();
+
+    if let Some(mut previous) = current.take() {
+      previous.restore()?;
+    }
+
+    let capture = AppCapture::new(application)?;
+
+    // Otherwise [Self::listen] moves it once it's listening
+    if self.app.is_some() {
+      let mut controller = SourceController::create()?;
+      let app = self._get_app(&mut controller)?;
+
+      self._change_device(&mut controller, &app, capture.source().index)?;
+    }
+
+    *current = Some(capture);
+    *self.current_device_index.write().unwrap() = None;
+    self.follow.paused.store(true, Ordering::Release);
+
+    Ok(())
+  }
+
+  pub fn stop_capturing_application(&self) -> Result<()> {
+    if self.application.lock().unwrap().is_none() {
+      return Ok(());
+    }
+
+    let default = self.default_device()?.clone();
+
+    self.change_device(&default)
+  }
+
+  /// Starts watching PulseAudio for changes if it isn't already
+  #[cfg(feature = "events")]
+  fn watch(&self) -> Result<()> {
+    let mut watcher = self.watcher.lock().unwrap();
+
+    if watcher.is_none() {
+      *watcher = Some(Watcher::spawn(
+        self.events.clone(),
+        self.listener.reporter(),
+        self.follow.clone(),
+        Box::new(|device| {
+          let mut controller = SourceController::create()?;
+
+          // Not listening yet, [Self::listen] moves it to the followed device
+          let Ok(app) = find_app(&mut controller) else {
+            return Ok(());
+          };
+
+          controller.move_app_by_index(app.index, device.index)?;
+
+          Ok(())
+        }),
+      )?);
+    }
+
+    Ok(())
+  }
+
+  #[cfg(feature = "events")]
+  pub fn device_events(&self) -> Result<DeviceEvents> {
+    let events = self.events.subscribe();
+
+    self.watch()?;
+
+    Ok(events)
+  }
+
+  #[cfg(feature = "events")]
+  pub fn follow_default_device(&self, follow: bool) -> Result<()> {
+    if follow {
+      self.watch()?;
+    }
+
+    self.follow.enabled.store(follow, Ordering::Release);
+
+    Ok(())
+  }
+
+  /// Rebuilds the stream and moves it to the device, or the application that's captured
+  pub(crate) fn restarter(&self) -> Result<Restart> {
+    let stream = self.stream.clone();
+    let application = self.application.clone();
+    let listener = self.listener.clone();
+    let follow = self.follow.clone();
+
+    Ok(Box::new(move |device| {
+      let mut stream = stream.lock().unwrap();
+
+      // Stops the old stream first so two never feed the listener at once
+      stream.take();
+      *stream = Some(default_stream(&listener)?);
+
+      let source = application
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|capture| capture.source().index);
+      let mut controller = SourceController::create()?;
+      let app = find_app(&mut controller)?;
+
+      sleep(Duration::from_millis(20));
+      controller.move_app_by_index(app.index, source.unwrap_or(device.index))?;
+
+      // Picked up by [Self::current_device_index] like a followed default
+      if source.is_none() {
+        *follow.followed.lock().unwrap() = Some(device.clone());
+      }
+
+      Ok(())
+    }))
+  }
+}
+
+impl Backend for LinuxHost {
+  fn listener(&self) -> &Listener {
+    &self.listener
+  }
+
+  fn current_device_index(&self) -> Option<usize> {
+    LinuxHost::current_device_index(self)
+  }
+
+  fn current_device(&self) -> Option<&Device> {
+    LinuxHost::current_device(self)
+  }
+
+  fn default_device(&self) -> Result<&Device> {
+    LinuxHost::default_device(self)
+  }
+
+  fn devices(&self) -> &Vec<Device> {
+    LinuxHost::devices(self)
+  }
+
+  fn change_device_by_index(&self, index: usize) -> Result<()> {
+    LinuxHost::change_device_by_index(self, index)
+  }
+
+  fn change_device(&self, device: &Device) -> Result<()> {
+    LinuxHost::change_device(self, device)
+  }
+
+  fn listen(&mut self) -> Result<()> {
+    LinuxHost::listen(self)
+  }
+
+  fn stop(&mut self) -> Result<()> {
+    LinuxHost::stop(self)
+  }
+
+  fn refresh(&mut self) -> Result<()> {
+    LinuxHost::refresh(self)
+  }
+
+  fn applications(&self) -> Result<Vec<Application>> {
+    LinuxHost::applications(self)
+  }
+
+  fn current_application(&self) -> Option<Application> {
+    LinuxHost::current_application(self)
+  }
+
+  fn capture_application(&self, application: &Application) -> Result<()> {
+    LinuxHost::capture_application(self, application)
+  }
+
+  fn stop_capturing_application(&self) -> Result<()> {
+    LinuxHost::stop_capturing_application(self)
+  }
+
+  #[cfg(feature = "events")]
+  fn device_events(&self) -> Result<DeviceEvents> {
+    LinuxHost::device_events(self)
+  }
+
+  #[cfg(feature = "events")]
+  fn follow_default_device(&self, follow: bool) -> Result<()> {
+    LinuxHost::follow_default_device(self, follow)
+  }
+
+  fn restarter(&self) -> Result<Restart> {
+    LinuxHost::restarter(self)
+  }
 }
@@ -0,0 +1,604 @@
+use std::{
+  collections::HashSet,
+  f64::consts::TAU,
+  fmt::{Debug, Display, Formatter},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, RwLock, Weak,
+  },
+  thread,
+  time::Duration,
+};
+
+use cpal::{BufferSize, SampleRate, StreamConfig};
+
+use super::{supervisor::Restart, Backend, Health, Reporter};
+use crate::{Device, DeviceKind, Error, Listener, Result, SampleFormat, Status};
+
+/// Signal generated by a [MockDevice], the same on every channel unless it's [Signal::Samples]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signal {
+  Silence,
+  Sine {
+    frequency: f32,
+    amplitude: f32,
+  },
+  /// Logarithmic sweep from `start` to `end` hz that starts over every `duration`
+  Sweep {
+    start: f32,
+    end: f32,
+    duration: Duration,
+    amplitude: f32,
+  },
+  /// White noise
+  Noise {
+    amplitude: f32,
+  },
+  /// Replays interleaved samples with the channels of the device, looping at the end
+  Samples(Arc<[f32]>),
+}
+
+impl Signal {
+  fn sample(&self, frame: u64, channel: usize, channels: usize, rate: u32, noise: &mut u32) -> f32 {
+    let t = frame as f64 / rate.max(1) as f64;
+
+    match self {
+      Signal::Silence => 0.,
+      Signal::Sine {
+        frequency,
+        amplitude,
+      } => amplitude * (TAU * *frequency as f64 * t).sin() as f32,
+      Signal::Sweep {
+        start,
+        end,
+        duration,
+        amplitude,
+      } => {
+        let length = duration.as_secs_f64().max(f64::EPSILON);
+        let (start, end) = (start.max(f32::EPSILON) as f64, end.max(f32::EPSILON) as f64);
+        let k = (end / start).ln();
+        let t = t % length;
+        let phase = match k {
+          k if k.abs() < f64::EPSILON => TAU * start * t,
+          k => TAU * start * length / k * ((t / length * k).exp() - 1.),
+        };
+
+        amplitude * phase.sin() as f32
+      }
+      Signal::Noise { amplitude } => {
+        // Only changes once per frame so every channel gets the same sample
+        if channel == 0 {
+          *noise ^= *noise << 13;
+          *noise ^= *noise >> 17;
+          *noise ^= *noise << 5;
+        }
+
+        amplitude * (*noise as f32 / u32::MAX as f32 * 2. - 1.)
+      }
+      Signal::Samples(samples) if samples.is_empty() => 0.,
+      Signal::Samples(samples) => {
+        let index = (frame as usize * channels + channel) % samples.len();
+
+        samples[index]
+      }
+    }
+  }
+}
+
+/// Fake device of a [MockHost]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockDevice {
+  pub name: String,
+  pub sample_rate: u32,
+  pub channels: u16,
+  /// Frames given to listeners per block
+  pub block_size: usize,
+  pub signal: Signal,
+  /// Gives no blocks while the signal is [Signal::Silence], like a WASAPI loopback
+  pub stops_when_silent: bool,
+}
+
+impl MockDevice {
+  /// Silent stereo device at 48000 hz with blocks of 512 frames
+  pub fn new(name: impl Into<String>) -> Self {
+    Self {
+      name: name.into(),
+      sample_rate: 48000,
+      channels: 2,
+      block_size: 512,
+      signal: Signal::Silence,
+      stops_when_silent: false,
+    }
+  }
+
+  pub fn with_sample_rate(self, sample_rate: u32) -> Self {
+    Self {
+      sample_rate,
+      ..self
+    }
+  }
+
+  pub fn with_channels(self, channels: u16) -> Self {
+    Self { channels, ..self }
+  }
+
+  pub fn with_block_size(self, block_size: usize) -> Self {
+    Self { block_size, ..self }
+  }
+
+  pub fn with_signal(self, signal: Signal) -> Self {
+    Self { signal, ..self }
+  }
+
+  pub fn with_stops_when_silent(self, stops_when_silent: bool) -> Self {
+    Self {
+      stops_when_silent,
+      ..self
+    }
+  }
+}
+
+/// How a [MockHost] moves forward in time
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Clock {
+  /// Only generates audio when told to by [MockClock]
+  #[default]
+  Manual,
+  /// Generates audio on a separate thread as fast as a real device would
+  RealTime,
+}
+
+type Feed = Box<dyn FnMut(&[f32]) + Send>;
+
+struct Generator {
+  device: Option<MockDevice>,
+  feed: Option<Feed>,
+  /// Frames generated since it started listening
+  position: u64,
+  /// Part of a frame left over from the last [MockClock::advance]
+  remainder: f64,
+  noise: u32,
+  block: Vec<f32>,
+  /// Names of devices that fail to start, see [MockClock::set_unavailable]
+  unavailable: HashSet<String>,
+}
+
+impl Generator {
+  fn start(&mut self, device: &MockDevice, listener: &Listener) -> Result<()> {
+    if self.unavailable.contains(&device.name) {
+      return Err(Error::NoDeviceFound(device.name.clone()));
+    }
+
+    let config = StreamConfig {
+      channels: device.channels,
+      sample_rate: SampleRate(device.sample_rate),
+      buffer_size: BufferSize::Fixed(device.block_size as u32),
+    };
+
+    self.device = Some(device.clone());
+    self.feed = Some(Box::new(listener.feed(&config)));
+    self.position = 0;
+    self.remainder = 0.;
+
+    Ok(())
+  }
+
+  fn generate(&mut self, frames: usize) {
+    let (Some(device), Some(feed)) = (&self.device, &mut self.feed) else {
+      return;
+    };
+
+    if device.stops_when_silent && device.signal == Signal::Silence {
+      return;
+    }
+
+    let channels = device.channels.max(1) as usize;
+
+    self.block.clear();
+
+    for frame in self.position..self.position + frames as u64 {
+      for channel in 0..channels {
+        let sample = device.signal.sample(
+          frame,
+          channel,
+          channels,
+          device.sample_rate,
+          &mut self.noise,
+        );
+
+        self.block.push(sample);
+      }
+    }
+
+    feed(&self.block);
+
+    self.position += frames as u64;
+  }
+
+  fn advance_frames(&mut self, mut frames: u64) {
+    let block_size = self
+      .device
+      .as_ref()
+      .map_or(1, |device| device.block_size.max(1));
+
+    while frames > 0 {
+      let size = frames.min(block_size as u64);
+
+      self.generate(size as usize);
+
+      frames -= size;
+    }
+  }
+
+  fn stop(&mut self) {
+    self.device = None;
+    self.feed = None;
+  }
+
+  fn block_duration(&self) -> Option<Duration> {
+    let device = self.device.as_ref()?;
+
+    Some(Duration::from_secs_f64(
+      device.block_size.max(1) as f64 / device.sample_rate.max(1) as f64,
+    ))
+  }
+}
+
+/// Drives the audio of a [MockHost], can be cloned and used after giving the host to [Host::mock](crate::Host::mock)
+#[derive(Clone)]
+pub struct MockClock {
+  generator: Arc<Mutex<Generator>>,
+  health: Arc<Health>,
+  reporter: Arc<Reporter>,
+}
+
+impl MockClock {
+  /// Generates `duration` worth of audio in blocks of [MockDevice::block_size],
+  /// does nothing if the host isn't listening
+  pub fn advance(&self, duration: Duration) {
+    let mut generator = self.generator.lock().unwrap();
+    let rate = generator
+      .device
+      .as_ref()
+      .map_or(0, |device| device.sample_rate);
+    let frames = duration.as_secs_f64() * rate as f64 + generator.remainder;
+
+    generator.remainder = frames.fract();
+    generator.advance_frames(frames as u64);
+  }
+
+  /// Generates exactly `frames` frames
+  pub fn advance_frames(&self, frames: u64) {
+    self.generator.lock().unwrap().advance_frames(frames);
+  }
+
+  /// Generates a single block
+  pub fn step(&self) {
+    let mut generator = self.generator.lock().unwrap();
+    let block_size = generator
+      .device
+      .as_ref()
+      .map_or(0, |device| device.block_size);
+
+    generator.advance_frames(block_size as u64);
+  }
+
+  /// Changes the signal of the device that's being listened to
+  pub fn set_signal(&self, signal: Signal) {
+    if let Some(device) = &mut self.generator.lock().unwrap().device {
+      device.signal = signal;
+    }
+  }
+
+  /// Gets how many frames were generated since the host started listening
+  pub fn position(&self) -> u64 {
+    self.generator.lock().unwrap().position
+  }
+
+  /// Reports `message` like a stream error and stops generating audio until the device is started again,
+  /// see [Host::supervise](crate::Host::supervise)
+  pub fn fail(&self, message: impl Display) {
+    self.generator.lock().unwrap().feed = None;
+    self.health.fail(&message);
+    self
+      .reporter
+      .report(Status::StreamError(message.to_string()));
+  }
+
+  /// Stops generating audio without an error until the device is started again
+  pub fn stall(&self) {
+    self.generator.lock().unwrap().feed = None;
+  }
+
+  /// Makes a device fail to start like it was unplugged, failing it if it's being listened to
+  pub fn set_unavailable(&self, name: &str, unavailable: bool) {
+    let mut generator = self.generator.lock().unwrap();
+
+    if !unavailable {
+      generator.unavailable.remove(name);
+      return;
+    }
+
+    generator.unavailable.insert(name.to_string());
+
+    let listened = generator
+      .device
+      .as_ref()
+      .is_some_and(|device| device.name == name);
+
+    // Unlocked first since [Self::fail] locks it again
+    drop(generator);
+
+    if listened {
+      self.fail(format!("{name} was removed"));
+    }
+  }
+}
+
+impl Debug for MockClock {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("MockClock")
+      .field("position", &self.position())
+      .finish()
+  }
+}
+
+/// Backend for [Host::mock](crate::Host::mock) that generates audio from fake devices,
+/// so code using listeners can run without an audio system
+///
+/// ```
+/// # use safav::*;
+/// # use std::time::Duration;
+/// let mock = MockHost::new().with_device(
+///   MockDevice::new("Test").with_signal(Signal::Sine { frequency: 440., amplitude: 1. }),
+/// );
+/// let clock = mock.clock();
+/// let mut host = Host::mock(mock);
+/// let listener = host.create_listener::<Vec<f32>>();
+///
+/// host.listen()?;
+/// clock.advance(Duration::from_millis(100));
+///
+/// assert!(listener.poll().iter().any(|sample| *sample != 0.));
+/// # Ok::<(), Error>(())
+/// ```
+pub struct MockHost {
+  devices: Vec<Device>,
+  mocks: Vec<MockDevice>,
+  clock: Clock,
+  listener: Listener,
+  generator: Arc<Mutex<Generator>>,
+  /// Shared with the restarter, which changes it when it falls back to another device
+  current_device_index: Arc<RwLock<Option<usize>>>,
+  listening: bool,
+  /// Keeps the [Clock::RealTime] thread running
+  running: Arc<AtomicBool>,
+}
+
+impl MockHost {
+  /// Creates a host without any devices and a [Clock::Manual]
+  pub fn new() -> Self {
+    Self {
+      devices: Vec::new(),
+      mocks: Vec::new(),
+      clock: Clock::default(),
+      listener: Listener::new(),
+      generator: Arc::new(Mutex::new(Generator {
+        device: None,
+        feed: None,
+        position: 0,
+        remainder: 0.,
+        noise: 0x9E3779B9,
+        block: Vec::new(),
+        unavailable: HashSet::new(),
+      })),
+      current_device_index: Arc::default(),
+      listening: false,
+      running: Arc::new(AtomicBool::new(false)),
+    }
+  }
+
+  /// Adds a device, the first one is the default device
+  pub fn with_device(mut self, device: MockDevice) -> Self {
+    self.devices.push(Device {
+      name: device.name.clone(),
+      id: device.name.clone(),
+      kind: DeviceKind::Virtual,
+      channels: Some(device.channels),
+      sample_rates: vec![device.sample_rate],
+      formats: vec![SampleFormat::F32],
+      default: self.devices.is_empty(),
+      backend: "Mock",
+      stops_when_silent: device.stops_when_silent,
+      ..Default::default()
+    });
+    self.mocks.push(device);
+    self
+  }
+
+  pub fn with_clock(mut self, clock: Clock) -> Self {
+    self.clock = clock;
+    self
+  }
+
+  /// Gets a handle to drive the audio with
+  pub fn clock(&self) -> MockClock {
+    MockClock {
+      generator: self.generator.clone(),
+      health: self.listener.health(),
+      reporter: self.listener.reporter(),
+    }
+  }
+
+  fn _start(&self, index: usize) -> Result<()> {
+    self
+      .generator
+      .lock()
+      .unwrap()
+      .start(&self.mocks[index], &self.listener)
+  }
+
+  fn _spawn_clock(&self) -> Result<()> {
+    if self.running.swap(true, Ordering::AcqRel) {
+      return Ok(());
+    }
+
+    let generator = Arc::downgrade(&self.generator);
+    let running = self.running.clone();
+
+    thread::Builder::new()
+      .name(String::from("safav-mock"))
+      .spawn(move || run_clock(generator, running))?;
+
+    Ok(())
+  }
+}
+
+/// Generates a block every time a real device would
+fn run_clock(generator: Weak<Mutex<Generator>>, running: Arc<AtomicBool>) {
+  while running.load(Ordering::Acquire) {
+    // Stops once the host is gone
+    let Some(generator) = generator.upgrade() else {
+      break;
+    };
+
+    let duration = generator.lock().unwrap().block_duration();
+
+    match duration {
+      Some(duration) => {
+        thread::sleep(duration);
+
+        let mut generator = generator.lock().unwrap();
+        let block_size = generator
+          .device
+          .as_ref()
+          .map_or(0, |device| device.block_size);
+
+        generator.advance_frames(block_size as u64);
+      }
+      None => thread::sleep(Duration::from_millis(10)),
+    }
+  }
+}
+
+impl Default for MockHost {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Drop for MockHost {
+  fn drop(&mut self) {
+    self.running.store(false, Ordering::Release);
+  }
+}
+
+impl Debug for MockHost {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("MockHost")
+      .field("devices", &self.mocks)
+      .field("clock", &self.clock)
+      .field("current_device_index", &self.current_device_index())
+      .field("listening", &self.listening)
+      .finish()
+  }
+}
+
+impl Backend for MockHost {
+  fn listener(&self) -> &Listener {
+    &self.listener
+  }
+
+  fn current_device_index(&self) -> Option<usize> {
+    *self.current_device_index.read().unwrap()
+  }
+
+  fn current_device(&self) -> Option<&Device> {
+    self
+      .current_device_index()
+      .and_then(|i| self.devices.get(i))
+  }
+
+  fn default_device(&self) -> Result<&Device> {
+    self.devices.first().ok_or(Error::NoDefaultDeviceFound)
+  }
+
+  fn devices(&self) -> &Vec<Device> {
+    &self.devices
+  }
+
+  fn change_device_by_index(&self, index: usize) -> Result<()> {
+    if index >= self.devices.len() {
+      return Err(Error::InvalidDeviceIndex(index));
+    }
+
+    if self.listening {
+      self._start(index)?;
+    }
+
+    *self.current_device_index.write().unwrap() = Some(index);
+
+    Ok(())
+  }
+
+  fn change_device(&self, device: &Device) -> Result<()> {
+    let index = self
+      .devices
+      .iter()
+      .position(|dev| dev.id == device.id)
+      .ok_or_else(|| Error::NoDeviceFound(device.name.to_owned()))?;
+
+    self.change_device_by_index(index)
+  }
+
+  fn listen(&mut self) -> Result<()> {
+    let index = match self.current_device_index() {
+      Some(index) => index,
+      None if !self.devices.is_empty() => 0,
+      None => return Err(Error::NoDefaultDeviceFound),
+    };
+
+    self._start(index)?;
+    self.listening = true;
+
+    *self.current_device_index.write().unwrap() = Some(index);
+
+    if self.clock == Clock::RealTime {
+      self._spawn_clock()?;
+    }
+
+    Ok(())
+  }
+
+  fn stop(&mut self) -> Result<()> {
+    self.generator.lock().unwrap().stop();
+    self.listening = false;
+
+    Ok(())
+  }
+
+  fn refresh(&mut self) -> Result<()> {
+    Ok(())
+  }
+
+  fn restarter(&self) -> Result<Restart> {
+    let generator = self.generator.clone();
+    let devices = self.devices.clone();
+    let mocks = self.mocks.clone();
+    let listener = self.listener.clone();
+    let current = self.current_device_index.clone();
+
+    Ok(Box::new(move |device| {
+      let index = devices
+        .iter()
+        .position(|dev| dev.id == device.id)
+        .ok_or_else(|| Error::NoDeviceFound(device.name.to_owned()))?;
+
+      generator.lock().unwrap().start(&mocks[index], &listener)?;
+
+      *current.write().unwrap() = Some(index);
+
+      Ok(())
+    }))
+  }
+}
@@ -1,38 +1,201 @@
 use std::fmt::{Display, Formatter};
 
-use crate::{AudioData, AudioListener, Result};
+pub use events::{DeviceEvent, DeviceEvents, Events};
+#[cfg(feature = "file")]
+pub(crate) use file::Decoder;
+#[cfg(feature = "file")]
+pub use file::{FilePlayer, Pace};
+pub use mock::*;
+pub(crate) use status::Reporter;
+pub use status::{Status, Statuses};
+pub(crate) use supervisor::Health;
+pub use supervisor::{StreamState, StreamStates, SupervisorSettings};
+
+use crate::{AudioData, AudioListener, Error, Listener, ListenerId, Result};
+use events::EventSenders;
+use supervisor::{Restart, Supervisor};
+
+mod application;
+mod events;
+mod file;
+mod input;
+mod mock;
+mod source;
+mod status;
+mod supervisor;
+
+#[cfg(all(target_os = "linux", feature = "events"))]
+mod watch;
 
 #[cfg(target_os = "linux")]
 pub(crate) mod linux;
 
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+pub(crate) mod pipewire;
+
+#[cfg(all(target_os = "linux", feature = "pulse"))]
+pub(crate) mod pulse;
+
+#[cfg(all(target_os = "linux", feature = "jack"))]
+pub(crate) mod jack;
+
 #[cfg(windows)]
 pub(crate) mod windows;
 
+/// Audio system a [Host] listens through
+pub(crate) trait Backend {
+  fn listener(&self) -> &Listener;
+
+  fn current_device_index(&self) -> Option<usize>;
+
+  fn current_device(&self) -> Option<&Device>;
+
+  fn default_device(&self) -> Result<&Device>;
+
+  fn devices(&self) -> &Vec<Device>;
+
+  fn change_device_by_index(&self, index: usize) -> Result<()>;
+
+  fn change_device(&self, device: &Device) -> Result<()>;
+
+  fn listen(&mut self) -> Result<()>;
+
+  /// Stops the stream so it can be started again with [Self::listen], keeping the current device
+  fn stop(&mut self) -> Result<()>;
+
+  fn refresh(&mut self) -> Result<()>;
+
+  fn applications(&self) -> Result<Vec<Application>> {
+    Ok(Vec::new())
+  }
+
+  fn current_application(&self) -> Option<Application> {
+    None
+  }
+
+  fn capture_application(&self, _application: &Application) -> Result<()> {
+    Err(Error::ApplicationCaptureUnsupported)
+  }
+
+  fn stop_capturing_application(&self) -> Result<()> {
+    Ok(())
+  }
+
+  fn device_events(&self) -> Result<DeviceEvents> {
+    Err(Error::DeviceEventsUnsupported)
+  }
+
+  fn follow_default_device(&self, _follow: bool) -> Result<()> {
+    Err(Error::DeviceEventsUnsupported)
+  }
+
+  /// Creates what a [Supervisor] rebuilds the stream with, only called once it's listening
+  fn restarter(&self) -> Result<Restart> {
+    Err(Error::SupervisionUnsupported)
+  }
+}
+
+/// Picks the most direct backend that's enabled and has a running daemon
+#[cfg(target_os = "linux")]
+fn linux_backend() -> Result<Box<dyn Backend>> {
+  #[cfg(feature = "pipewire")]
+  if let Ok(host) = pipewire::PipeWireHost::new() {
+    return Ok(Box::new(host));
+  }
+
+  #[cfg(feature = "pulse")]
+  if let Ok(host) = pulse::PulseHost::new() {
+    return Ok(Box::new(host));
+  }
+
+  Ok(Box::new(linux::LinuxHost::new()?))
+}
+
 pub struct Host {
-  #[cfg(windows)]
-  inner: windows::WindowsHost,
-  #[cfg(target_os = "linux")]
-  inner: linux::LinuxHost,
+  /// Declared first so it stops before the backend it restarts is dropped
+  supervisor: Option<Supervisor>,
+  inner: Box<dyn Backend>,
+  #[cfg(feature = "file")]
+  files: file::Files,
+  /// Only [State::Error] if it couldn't start, errors of the stream come from the reporter
+  state: State,
+  /// Kept while paused so it's supervised again once it resumes
+  supervising: Option<SupervisorSettings>,
+  states: EventSenders<StreamState>,
 }
 
 impl Host {
   pub fn new() -> Result<Self> {
-    Ok(Self {
-      #[cfg(windows)]
-      inner: windows::WindowsHost::new()?,
+    #[cfg(windows)]
+    let inner: Box<dyn Backend> = Box::new(windows::WindowsHost::new()?);
 
-      #[cfg(target_os = "linux")]
-      inner: linux::LinuxHost::new()?,
-    })
+    #[cfg(target_os = "linux")]
+    let inner = linux_backend()?;
+
+    Ok(Self::with_backend(inner))
+  }
+
+  fn with_backend(inner: Box<dyn Backend>) -> Self {
+    Self {
+      supervisor: None,
+      inner,
+      #[cfg(feature = "file")]
+      files: Default::default(),
+      state: State::Idle,
+      supervising: None,
+      states: Default::default(),
+    }
+  }
+
+  /// Creates a host that captures through PipeWire directly, without the pulse compatibility layer
+  #[cfg(all(target_os = "linux", feature = "pipewire"))]
+  pub fn pipewire() -> Result<Self> {
+    Ok(Self::with_backend(Box::new(pipewire::PipeWireHost::new()?)))
+  }
+
+  /// Creates a host that records straight from PulseAudio sources and monitors by their name
+  #[cfg(all(target_os = "linux", feature = "pulse"))]
+  pub fn pulse() -> Result<Self> {
+    Ok(Self::with_backend(Box::new(pulse::PulseHost::new()?)))
+  }
+
+  /// Creates a host that captures through its own JACK ports, see [Device::jack_ports] to capture specific ports
+  #[cfg(all(target_os = "linux", feature = "jack"))]
+  pub fn jack() -> Result<Self> {
+    Ok(Self::with_backend(Box::new(jack::JackHost::new()?)))
+  }
+
+  /// Creates a host that generates audio instead of using the audio system, see [MockHost]
+  pub fn mock(mock: MockHost) -> Self {
+    Self::with_backend(Box::new(mock))
+  }
+
+  /// Adds an audio file as a device, it's listed after every other device
+  /// and returns a [FilePlayer] to control its playback
+  #[cfg(feature = "file")]
+  pub fn add_file(&mut self, path: impl AsRef<std::path::Path>) -> Result<FilePlayer> {
+    self
+      .files
+      .add(path.as_ref(), self.inner.listener(), self.inner.devices())
   }
 
   /// Gets the current device that is being listened too by index
   pub fn current_device_index(&self) -> Option<usize> {
+    #[cfg(feature = "file")]
+    if let Some(index) = self.files.current_device_index() {
+      return Some(index);
+    }
+
     self.inner.current_device_index()
   }
 
   /// Gets the current device that is being listened too
   pub fn current_device(&self) -> Option<&Device> {
+    #[cfg(feature = "file")]
+    if let Some(index) = self.files.current_device_index() {
+      return self.devices().get(index);
+    }
+
     self.inner.current_device()
   }
 
@@ -43,41 +206,376 @@ impl Host {
 
   /// Get a list of devices
   pub fn devices(&self) -> &Vec<Device> {
+    #[cfg(feature = "file")]
+    if let Some(devices) = self.files.devices() {
+      return devices;
+    }
+
     self.inner.devices()
   }
 
   /// change the audio device to listen too by index of [Self::devices]
   pub fn change_device_by_index(&self, index: usize) -> Result<()> {
-    self.inner.change_device_by_index(index)
+    #[cfg(feature = "file")]
+    if let Some(file) = self.files.find_index(index) {
+      self.files.select(Some(file), self.inner.listener());
+
+      return Ok(());
+    }
+
+    self.inner.change_device_by_index(index)?;
+
+    if let (Some(supervisor), Some(device)) = (&self.supervisor, self.inner.devices().get(index)) {
+      supervisor.set_device(device);
+    }
+
+    #[cfg(feature = "file")]
+    self.files.select(None, self.inner.listener());
+
+    Ok(())
   }
 
   /// Changes the audio device to listen too
   pub fn change_device(&self, device: &Device) -> Result<()> {
-    self.inner.change_device(device)
+    #[cfg(feature = "file")]
+    if let Some(file) = self.files.find(device) {
+      self.files.select(Some(file), self.inner.listener());
+
+      return Ok(());
+    }
+
+    self.inner.change_device(device)?;
+
+    if let Some(supervisor) = &self.supervisor {
+      supervisor.set_device(device);
+    }
+
+    #[cfg(feature = "file")]
+    self.files.select(None, self.inner.listener());
+
+    Ok(())
   }
 
-  /// Starts the listener to listen to audio
+  /// Starts the listener to listen to audio, also resumes it after [Self::pause]
   pub fn listen(&mut self) -> Result<()> {
-    self.inner.listen()
+    if let Err(err) = self.inner.listen() {
+      self.state = State::Error(err.to_string());
+
+      return Err(err);
+    }
+
+    let listener = self.inner.listener();
+
+    listener.reporter().clear_error();
+    listener.set_paused(false);
+    self.state = State::Listening;
+
+    match self.supervising {
+      Some(settings) if self.supervisor.is_none() => self.supervise(settings),
+      _ => Ok(()),
+    }
+  }
+
+  /// Stops the stream and supervising it, [Self::listen] starts it again on the same device
+  ///
+  /// Audio files added with `add_file` keep playing, they have their own `FilePlayer::pause`
+  pub fn stop(&mut self) -> Result<()> {
+    self.stop_supervising();
+    self.inner.stop()?;
+    self.inner.listener().set_paused(false);
+    self.state = State::Idle;
+
+    Ok(())
+  }
+
+  /// Stops the stream until [Self::resume] to save CPU, like while the app is minimized,
+  /// listeners keep their last value and [AudioListener::is_paused] meanwhile
+  pub fn pause(&mut self) -> Result<()> {
+    match self.state {
+      State::Listening => {}
+      State::Paused => return Ok(()),
+      State::Idle | State::Error(_) => return Err(Error::NotListening),
+    }
+
+    // Would rebuild the stream once it notices no audio arrives
+    self.supervisor.take();
+    // Only flagged once it stopped, so listeners aren't paused if stopping fails
+    self.inner.stop()?;
+    self.inner.listener().set_paused(true);
+    self.state = State::Paused;
+
+    Ok(())
+  }
+
+  /// Starts the stream again after [Self::pause], supervising it again if it was
+  pub fn resume(&mut self) -> Result<()> {
+    match self.state {
+      State::Paused => self.listen(),
+      State::Listening => Ok(()),
+      State::Idle | State::Error(_) => Err(Error::NotListening),
+    }
+  }
+
+  /// Gets whether it's listening, [State::Error] while the stream has an error it didn't recover from
+  pub fn state(&self) -> State {
+    match (&self.state, self.inner.listener().reporter().error()) {
+      (State::Listening, Some(err)) => State::Error(err),
+      (state, _) => state.clone(),
+    }
+  }
+
+  /// Watches the stream on a separate thread and rebuilds it when it reports an error
+  /// or no audio arrives for [SupervisorSettings::stall_timeout], like when the device is unplugged
+  ///
+  /// Devices that [stop when silent](Device::stops_when_silent) are only rebuilt after an error,
+  /// since a stall can't be told apart from nothing playing.
+  /// It's rebuilt on the current device first and then on the default device, waiting longer after every attempt,
+  /// see [Self::stream_states] for what happens meanwhile. Supported by the default, the PulseAudio,
+  /// the Windows and the mock backend, only once it's listening. While it's paused it starts once it resumes
+  pub fn supervise(&mut self, settings: SupervisorSettings) -> Result<()> {
+    match self.state {
+      State::Listening => {}
+      State::Paused => {
+        self.supervising = Some(settings);
+
+        return Ok(());
+      }
+      State::Idle | State::Error(_) => return Err(Error::NotListening),
+    }
+
+    let restart = self.inner.restarter()?;
+    let fallback = self.inner.default_device()?.clone();
+    let device = self
+      .inner
+      .current_device()
+      .cloned()
+      .unwrap_or_else(|| fallback.clone());
+
+    // Only one supervisor restarts the stream at a time
+    self.supervisor.take();
+    self.supervisor = Some(Supervisor::spawn(
+      settings,
+      self.inner.listener().health(),
+      restart,
+      (device, fallback),
+      self.states.clone(),
+      self.inner.listener().reporter(),
+    )?);
+    self.supervising = Some(settings);
+
+    Ok(())
+  }
+
+  /// Stops watching the stream, it keeps running as it is
+  pub fn stop_supervising(&mut self) {
+    self.supervisor.take();
+    self.supervising = None;
+  }
+
+  /// Creates a receiver of every [StreamState] the stream goes through while it's supervised
+  pub fn stream_states(&self) -> StreamStates {
+    self.states.subscribe()
+  }
+
+  /// Lists applications that are playing audio, always empty if the backend can't capture them
+  pub fn applications(&self) -> Result<Vec<Application>> {
+    self.inner.applications()
+  }
+
+  /// Gets the application that's being captured instead of a device
+  pub fn current_application(&self) -> Option<Application> {
+    self.inner.current_application()
+  }
+
+  /// Captures only `application` instead of a device, it still plays on the device it played on
+  ///
+  /// Supported by the default and the PulseAudio backend on Linux,
+  /// which route the application through a null sink of its own until the capture stops
+  pub fn capture_application(&self, application: &Application) -> Result<()> {
+    self.inner.capture_application(application)?;
+
+    #[cfg(feature = "file")]
+    self.files.select(None, self.inner.listener());
+
+    Ok(())
+  }
+
+  /// Stops capturing the application and goes back to the default device,
+  /// changing to another device also stops it
+  pub fn stop_capturing_application(&self) -> Result<()> {
+    self.inner.stop_capturing_application()
+  }
+
+  /// Creates a receiver of devices being added, removed and the default device changing,
+  /// the audio system is watched from the first time this or [Self::follow_default_device] is called
+  ///
+  /// Supported by the default backend on Linux with the `events` feature and by the PulseAudio backend
+  pub fn device_events(&self) -> Result<DeviceEvents> {
+    self.inner.device_events()
+  }
+
+  /// Moves capture to the new default device whenever it changes, like when headphones are plugged in,
+  /// [Self::current_device] catches up after [Self::refresh] if the device is new
+  pub fn follow_default_device(&self, follow: bool) -> Result<()> {
+    self.inner.follow_default_device(follow)
+  }
+
+  /// Creates a receiver of stream errors, dropped blocks, device changes and warnings of the backend,
+  /// they're also logged with the `log` feature
+  pub fn statuses(&self) -> Statuses {
+    self.inner.listener().reporter().subscribe()
+  }
+
+  /// Calls `handler` with every [Status] on the thread it happened on, which can be the audio thread,
+  /// so it shouldn't block
+  pub fn on_status(&self, handler: impl Fn(&Status) + Send + Sync + 'static) {
+    self
+      .inner
+      .listener()
+      .reporter()
+      .add_handler(Box::new(handler));
   }
 
   /// Creates a new listener that can be shared between threads since host itself can't be shared
   pub fn create_listener<T: AudioData>(&self) -> AudioListener<T> {
-    self.inner.listener.clone().create()
+    self.inner.listener().create()
+  }
+
+  /// Creates a new listener starting from `value`, independent of any other listener of the same type
+  pub fn create_listener_with<T: AudioData>(&self, value: T) -> AudioListener<T> {
+    self.inner.listener().create_with(value)
+  }
+
+  /// Creates a new listener that's always updated with blocks of exactly `size`,
+  /// so analysis behaves the same no matter what size the device gives
+  pub fn create_chunked_listener<T: AudioData>(&self, size: crate::BlockSize) -> AudioListener<T> {
+    self.inner.listener().create_chunked(size)
+  }
+
+  /// Creates a new listener that's always updated with samples at `rate`,
+  /// so thresholds and band layouts don't shift with the rate of the device
+  pub fn create_resampled_listener<T: AudioData>(&self, rate: u32) -> AudioListener<T> {
+    self.inner.listener().create_resampled(rate)
+  }
+
+  /// Creates a new listener that's updated with the [Features](crate::Features) of every window,
+  /// matching what [Offline](crate::Offline) writes for the same samples
+  pub fn create_analysis_listener(
+    &self,
+    settings: crate::AnalysisSettings,
+  ) -> Result<AudioListener<crate::Features>> {
+    self.inner.listener().create_analysis(settings)
+  }
+
+  /// Gets an existing listener by its id
+  pub fn get_listener<T: AudioData>(&self, id: ListenerId) -> Option<AudioListener<T>> {
+    self.inner.listener().get(id)
+  }
+
+  /// Creates a new [AudioStream](crate::AudioStream) that buffers up to `capacity` frames
+  #[cfg(feature = "stream")]
+  pub fn create_stream<T: AudioData>(&self, capacity: usize) -> crate::AudioStream<T> {
+    self.inner.listener().create_stream(capacity)
+  }
+
+  /// Creates a new [AudioStream](crate::AudioStream) starting from `value`
+  #[cfg(feature = "stream")]
+  pub fn create_stream_with<T: AudioData>(
+    &self,
+    value: T,
+    capacity: usize,
+  ) -> crate::AudioStream<T> {
+    self.inner.listener().create_stream_with(value, capacity)
+  }
+
+  /// Creates a [BlockReceiver](crate::BlockReceiver) that gets every captured block in order,
+  /// keeping up to `capacity` blocks before reporting overruns
+  pub fn subscribe(&self, capacity: usize) -> crate::BlockReceiver {
+    self.inner.listener().subscribe(capacity)
+  }
+
+  /// Creates a [Recorder](crate::Recorder) that writes captured blocks to WAV files,
+  /// keeping the last `history` of samples so a clip can be saved after something happened
+  #[cfg(feature = "record")]
+  pub fn create_recorder(&self, history: Option<std::time::Duration>) -> Result<crate::Recorder> {
+    self.inner.listener().create_recorder(history)
+  }
+
+  /// Starts a new [Pipeline](crate::Pipeline) of processing stages
+  pub fn pipeline(&self) -> crate::Pipeline {
+    self.inner.listener().pipeline()
+  }
+
+  /// Updates listeners on a separate thread instead of the audio callback,
+  /// see [Listener::enable_worker](crate::Listener::enable_worker)
+  pub fn enable_worker(&self, capacity: usize) -> Result<()> {
+    self.inner.listener().enable_worker(capacity)
+  }
+
+  /// Goes back to updating listeners inside the audio callback
+  pub fn disable_worker(&self) {
+    self.inner.listener().disable_worker()
+  }
+
+  /// Gets queue depth and processing times of the worker thread
+  pub fn worker_stats(&self) -> Option<crate::WorkerStats> {
+    self.inner.listener().worker_stats()
+  }
+
+  /// Removes a listener so it stops being updated, see [Listener::remove](crate::Listener::remove)
+  pub fn remove_listener(&self, id: ListenerId) -> bool {
+    self.inner.listener().remove(id)
   }
 
   /// Refreshes audio devices
   pub fn refresh(&mut self) -> Result<()> {
-    self.inner.refresh()
+    self.inner.refresh()?;
+
+    #[cfg(feature = "file")]
+    self.files.sync(self.inner.devices());
+
+    Ok(())
   }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+/// Whether a [Host] is listening, see [Host::state]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub enum State {
+  /// Not listening yet or stopped with [Host::stop]
+  #[default]
+  Idle,
+  Listening,
+  /// Stopped with [Host::pause] until [Host::resume]
+  Paused,
+  /// Couldn't start listening, or the stream failed and didn't recover yet
+  Error(String),
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 /// represents an audio device
 pub struct Device {
   /// on linux this would be `device.description`
   name: String,
 
+  /// on linux this would be `device.name`
+  id: String,
+
+  kind: DeviceKind,
+
+  channels: Option<u16>,
+
+  sample_rates: Vec<u32>,
+
+  formats: Vec<SampleFormat>,
+
+  /// Set on the device [Host::default_device] gave when the devices were listed
+  default: bool,
+
+  backend: &'static str,
+
+  /// Set if it gives no blocks while nothing plays, see [Device::stops_when_silent]
+  stops_when_silent: bool,
+
   #[cfg(windows)]
   sample_rate: u32,
 
@@ -88,16 +586,63 @@ pub struct Device {
   /// PulseAudio device index
   index: u32,
 
-  #[cfg(target_os = "linux")]
-  /// on linux this would be `device.name`
-  id: String,
+  #[cfg(feature = "file")]
+  /// Set if it's an audio file added with [Host::add_file]
+  path: Option<std::path::PathBuf>,
 }
 
 impl Device {
-  /// Gets the devices name
+  /// Gets the devices name, meant to be shown to users
   pub fn name(&self) -> &str {
     &self.name
   }
+
+  /// Gets an id that stays the same while the device exists, even after [Host::refresh],
+  /// like the PulseAudio source name, the JACK client name or the WASAPI endpoint ID
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  pub fn kind(&self) -> DeviceKind {
+    self.kind
+  }
+
+  /// Gets the native channel count if the backend reported it
+  pub fn channels(&self) -> Option<u16> {
+    self.channels
+  }
+
+  /// Gets the sample rates it supports natively, in ascending order and empty if the backend didn't report them
+  pub fn sample_rates(&self) -> &[u32] {
+    &self.sample_rates
+  }
+
+  /// Gets the sample formats it supports natively, empty if the backend didn't report them
+  pub fn formats(&self) -> &[SampleFormat] {
+    &self.formats
+  }
+
+  /// Gets whether it was the default device when the devices were listed, see [Host::refresh]
+  pub fn is_default(&self) -> bool {
+    self.default
+  }
+
+  /// Gets the name of the audio system it was listed by, like `PulseAudio` or `WASAPI`
+  pub fn backend(&self) -> &str {
+    self.backend
+  }
+
+  /// Gets whether it stops giving blocks while nothing plays, like a WASAPI loopback,
+  /// [Host::supervise] doesn't count that as a stall
+  pub fn stops_when_silent(&self) -> bool {
+    self.stops_when_silent
+  }
+
+  /// Gets the path of the audio file if it's a file added with [Host::add_file]
+  #[cfg(feature = "file")]
+  pub fn path(&self) -> Option<&std::path::Path> {
+    self.path.as_deref()
+  }
 }
 
 impl Display for Device {
@@ -105,3 +650,92 @@ impl Display for Device {
     Display::fmt(&self.name, f)
   }
 }
+
+/// What a [Device] captures, see [Device::kind]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum DeviceKind {
+  /// Whatever plays on an output device, through its monitor or loopback
+  OutputMonitor,
+  /// A microphone, line in or other capture hardware
+  #[default]
+  Input,
+  /// Created in software, like a filter, a JACK port, a mock device or an audio file
+  Virtual,
+  /// Audio of a single application, like a JACK client
+  Application,
+}
+
+/// Format of the samples a [Device] gives natively, listeners always get them as `f32`
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum SampleFormat {
+  U8,
+  U16,
+  U32,
+  U64,
+  I8,
+  I16,
+  /// Packed in 3 bytes
+  I24,
+  I32,
+  I64,
+  F32,
+  F64,
+}
+
+impl TryFrom<cpal::SampleFormat> for SampleFormat {
+  type Error = Error;
+
+  fn try_from(format: cpal::SampleFormat) -> Result<Self> {
+    Ok(match format {
+      cpal::SampleFormat::U8 => Self::U8,
+      cpal::SampleFormat::U16 => Self::U16,
+      cpal::SampleFormat::U32 => Self::U32,
+      cpal::SampleFormat::U64 => Self::U64,
+      cpal::SampleFormat::I8 => Self::I8,
+      cpal::SampleFormat::I16 => Self::I16,
+      cpal::SampleFormat::I32 => Self::I32,
+      cpal::SampleFormat::I64 => Self::I64,
+      cpal::SampleFormat::F32 => Self::F32,
+      cpal::SampleFormat::F64 => Self::F64,
+      format => return Err(Error::UnsupportedSampleFormat(format)),
+    })
+  }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+/// represents an application that's playing audio, see [Host::capture_application]
+pub struct Application {
+  /// on linux this would be `application.name`
+  name: String,
+
+  #[cfg(target_os = "linux")]
+  /// PulseAudio sink input index
+  index: u32,
+
+  binary: Option<String>,
+
+  process_id: Option<u32>,
+}
+
+impl Application {
+  /// Gets the applications name
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// Gets the name of the executable if the application reported it
+  pub fn binary(&self) -> Option<&str> {
+    self.binary.as_deref()
+  }
+
+  /// Gets the process id if the application reported it
+  pub fn process_id(&self) -> Option<u32> {
+    self.process_id
+  }
+}
+
+impl Display for Application {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(&self.name, f)
+  }
+}
@@ -0,0 +1,661 @@
+#![cfg(all(target_os = "linux", feature = "pipewire"))]
+
+//! Native PipeWire backend, captures sink monitors and sources by linking straight to their node,
+//! following it if it goes away and comes back
+//!
+//! Can be tried without touching the desktop session by running a separate daemon with a null sink:
+//!
+//! ```sh
+//! export XDG_RUNTIME_DIR=$(mktemp -d)
+//! pipewire & wireplumber &
+//! pw-cli create-node adapter '{ factory.name=support.null-audio-sink node.name=safav-null media.class=Audio/Sink object.linger=true audio.position=[FL FR] }'
+//! cargo run --example pipewire --features pipewire
+//! cargo test --test pipewire --features pipewire -- --ignored
+//! ```
+
+use std::{
+  cell::{Cell, RefCell},
+  rc::Rc,
+  sync::{mpsc, Arc, RwLock},
+  thread::{self, JoinHandle},
+};
+
+use ::pipewire as pw;
+use cpal::{BufferSize, SampleRate, StreamConfig};
+use pw::{
+  metadata::{Metadata, MetadataListener},
+  properties::properties,
+  spa::{
+    self,
+    param::{
+      audio::{AudioFormat, AudioInfoRaw},
+      format::{MediaSubtype, MediaType},
+      format_utils, ParamType,
+    },
+    pod::{serialize::PodSerializer, Object, Pod, Value},
+    utils::{Direction, SpaTypes},
+  },
+  stream::{StreamFlags, StreamListener},
+  types::ObjectType,
+};
+
+use super::Backend;
+use crate::{Device, DeviceKind, Error, Listener, Result, Status};
+
+/// Node that can be captured from
+#[derive(Debug, Clone)]
+struct Node {
+  device: Device,
+  /// Whether it's a sink, which is captured from its monitor
+  monitor: bool,
+}
+
+enum Command {
+  Connect(Node),
+  Disconnect,
+  Quit,
+}
+
+/// Names of the default nodes, kept up to date from the `default` metadata
+#[derive(Debug, Default)]
+struct Defaults {
+  sink: Option<String>,
+  source: Option<String>,
+}
+
+impl Defaults {
+  fn is_default(&self, node: &Node) -> bool {
+    let default = match node.monitor {
+      true => &self.sink,
+      false => &self.source,
+    };
+
+    default.as_deref() == Some(node.device.id.as_str())
+  }
+}
+
+type Feed = Box<dyn FnMut(&[f32]) + Send>;
+
+/// Negotiated format of the capture stream, only touched by the PipeWire threads
+struct Format {
+  listener: Listener,
+  info: AudioInfoRaw,
+  feed: Option<Feed>,
+  samples: Vec<f32>,
+}
+
+/// Capture stream linked to one node, dropping it disconnects the stream
+struct Capture {
+  _listener: StreamListener<Format>,
+  _stream: pw::stream::Stream,
+}
+
+pub struct PipeWireHost {
+  pub devices: Vec<Device>,
+  pub listener: Listener,
+  pub current_device_index: RwLock<Option<usize>>,
+  /// Nodes kept up to date by the registry
+  nodes: Arc<RwLock<Vec<Node>>>,
+  defaults: Arc<RwLock<Defaults>>,
+  /// Devices are only connected to while listening
+  listening: bool,
+  sender: pw::channel::Sender<Command>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl PipeWireHost {
+  pub fn new() -> Result<Self> {
+    let listener = Listener::new();
+    let nodes = Arc::new(RwLock::new(Vec::new()));
+    let defaults = Arc::new(RwLock::new(Defaults::default()));
+    let (sender, receiver) = pw::channel::channel();
+    let (ready, connected) = mpsc::channel();
+
+    let thread = {
+      let listener = listener.clone();
+      let nodes = nodes.clone();
+      let defaults = defaults.clone();
+
+      thread::Builder::new()
+        .name(String::from("safav-pipewire"))
+        .spawn(move || {
+          if let Err(err) = run(listener, nodes, defaults, receiver, ready.clone()) {
+            let _ = ready.send(Err(err));
+          }
+        })?
+    };
+
+    // Waits until every node that already exists was seen
+    connected.recv().unwrap_or(Err(pw::Error::CreationFailed))?;
+
+    let mut host = Self {
+      devices: Vec::new(),
+      listener,
+      current_device_index: RwLock::default(),
+      nodes,
+      defaults,
+      listening: false,
+      sender,
+      thread: Some(thread),
+    };
+
+    host.refresh()?;
+
+    Ok(host)
+  }
+
+  pub fn current_device_index(&self) -> Option<usize> {
+    *self.current_device_index.read().unwrap()
+  }
+
+  pub fn current_device(&self) -> Option<&Device> {
+    self
+      .current_device_index()
+      .and_then(|i| self.devices.get(i))
+  }
+
+  /// Gets the monitor of the default sink, or the default source if there's no default sink
+  pub fn default_device(&self) -> Result<&Device> {
+    let defaults = self.devices.iter().filter(|device| device.default);
+
+    defaults
+      .clone()
+      .find(|device| device.kind == DeviceKind::OutputMonitor)
+      .or_else(|| defaults.clone().next())
+      .or_else(|| self.devices.first())
+      .ok_or(Error::NoDefaultDeviceFound)
+  }
+
+  pub fn devices(&self) -> &Vec<Device> {
+    &self.devices
+  }
+
+  pub fn change_device_by_index(&self, index: usize) -> Result<()> {
+    let device = self
+      .devices
+      .get(index)
+      .ok_or(Error::InvalidDeviceIndex(index))?
+      .to_owned();
+
+    self.change_device(&device)
+  }
+
+  pub fn change_device(&self, device: &Device) -> Result<()> {
+    let node = self
+      .nodes
+      .read()
+      .unwrap()
+      .iter()
+      .find(|node| node.device.id == device.id)
+      .cloned()
+      .ok_or_else(|| Error::NoDeviceFound(device.name.clone()))?;
+
+    // Only remembered until listening, so nothing is captured before that
+    if self.listening {
+      self
+        .sender
+        .send(Command::Connect(node))
+        .map_err(|_| pw::Error::CreationFailed)?;
+    }
+
+    let index = self.devices.iter().position(|dev| dev.id == device.id);
+
+    *self.current_device_index.write().unwrap() = index;
+
+    Ok(())
+  }
+
+  pub fn listen(&mut self) -> Result<()> {
+    let device = match self.current_device() {
+      Some(device) => device,
+      None => self.default_device()?,
+    }
+    .clone();
+
+    self.listening = true;
+
+    if let Err(err) = self.change_device(&device) {
+      self.listening = false;
+
+      return Err(err);
+    }
+
+    Ok(())
+  }
+
+  pub fn stop(&mut self) -> Result<()> {
+    self.listening = false;
+    self
+      .sender
+      .send(Command::Disconnect)
+      .map_err(|_| pw::Error::CreationFailed)?;
+
+    Ok(())
+  }
+
+  /// Copies the nodes seen by the registry, keeping the current device selected by its id
+  pub fn refresh(&mut self) -> Result<()> {
+    let current = self.current_device().map(|device| device.id.clone());
+    let defaults = self.defaults.read().unwrap();
+    let devices = self
+      .nodes
+      .read()
+      .unwrap()
+      .iter()
+      .map(|node| Device {
+        default: defaults.is_default(node),
+        ..node.device.clone()
+      })
+      .collect::<Vec<_>>();
+
+    drop(defaults);
+
+    *self.current_device_index.write().unwrap() =
+      current.and_then(|id| devices.iter().position(|dev| dev.id == id));
+
+    self.devices = devices;
+
+    Ok(())
+  }
+}
+
+impl Drop for PipeWireHost {
+  fn drop(&mut self) {
+    if self.sender.send(Command::Quit).is_ok() {
+      if let Some(thread) = self.thread.take() {
+        let _ = thread.join();
+      }
+    }
+  }
+}
+
+/// Turns a registry global into a node, if it's a sink or a source
+fn node(global: &pw::registry::GlobalObject<&spa::utils::dict::DictRef>) -> Option<Node> {
+  if global.type_ != ObjectType::Node {
+    return None;
+  }
+
+  let props = global.props?;
+  let monitor = match props.get(*pw::keys::MEDIA_CLASS)? {
+    "Audio/Sink" => true,
+    "Audio/Source" => false,
+    _ => return None,
+  };
+
+  let id = props.get(*pw::keys::NODE_NAME)?.to_string();
+  let name = props
+    .get(*pw::keys::NODE_DESCRIPTION)
+    .map_or_else(|| id.clone(), String::from);
+  let kind = match (monitor, props.get(*pw::keys::NODE_VIRTUAL)) {
+    (true, _) => DeviceKind::OutputMonitor,
+    (false, Some("true")) => DeviceKind::Virtual,
+    (false, _) => DeviceKind::Input,
+  };
+  // Only set on nodes that were created with a fixed format, like null sinks
+  let channels = props
+    .get(*pw::keys::AUDIO_CHANNELS)
+    .and_then(|channels| channels.parse().ok());
+  let sample_rate = props
+    .get(*pw::keys::AUDIO_RATE)
+    .and_then(|rate| rate.parse().ok());
+
+  Some(Node {
+    device: Device {
+      name,
+      id,
+      kind,
+      channels,
+      sample_rates: sample_rate.into_iter().collect(),
+      backend: "PipeWire",
+      index: global.id,
+      ..Default::default()
+    },
+    monitor,
+  })
+}
+
+/// Keeps sink monitors before sources, each in the order they were created in
+fn insert(nodes: &mut Vec<Node>, node: Node) {
+  let index = match node.monitor {
+    true => nodes.iter().take_while(|node| node.monitor).count(),
+    false => nodes.len(),
+  };
+
+  nodes.insert(index, node);
+}
+
+/// Gets the node name out of a `default` metadata value like `{ "name": "alsa_output.pci" }`
+fn metadata_name(value: &str) -> Option<&str> {
+  let (_, rest) = value.split_once("\"name\"")?;
+  let (_, rest) = rest.split_once('"')?;
+
+  rest.split_once('"').map(|(name, _)| name)
+}
+
+/// Node that's captured from while listening, reconnected whenever it comes back
+#[derive(Default)]
+struct Target {
+  node: Option<Node>,
+  capture: Option<Capture>,
+}
+
+impl Target {
+  fn connect(&mut self, core: &pw::core::Core, listener: &Listener, node: Node) {
+    // Disconnects the old stream before linking the new one
+    self.capture = None;
+
+    match connect(core, listener, &node) {
+      Ok(capture) => self.capture = Some(capture),
+      Err(err) => listener
+        .reporter()
+        .report(Status::StreamError(err.to_string())),
+    }
+
+    self.node = Some(node);
+  }
+}
+
+/// Runs the PipeWire main loop until [Command::Quit]
+fn run(
+  listener: Listener,
+  nodes: Arc<RwLock<Vec<Node>>>,
+  defaults: Arc<RwLock<Defaults>>,
+  receiver: pw::channel::Receiver<Command>,
+  ready: mpsc::Sender<std::result::Result<(), pw::Error>>,
+) -> std::result::Result<(), pw::Error> {
+  let mainloop = pw::main_loop::MainLoop::new(None)?;
+  let context = pw::context::Context::new(&mainloop)?;
+  let core = context.connect(None)?;
+  let registry = Rc::new(core.get_registry()?);
+  let target = Rc::new(RefCell::new(Target::default()));
+  // Kept alive by the registry listener so the defaults stay up to date
+  let metadata = RefCell::new(None::<(Metadata, MetadataListener)>);
+
+  let _registry = registry
+    .add_listener_local()
+    .global({
+      let nodes = nodes.clone();
+      let registry = Rc::downgrade(&registry);
+      let target = target.clone();
+      let core = core.clone();
+      let listener = listener.clone();
+
+      move |global| {
+        if global.type_ == ObjectType::Metadata {
+          let name = global.props.and_then(|props| props.get("metadata.name"));
+
+          if let (Some("default"), Some(registry)) = (name, registry.upgrade()) {
+            *metadata.borrow_mut() = watch_defaults(&registry, global, defaults.clone());
+          }
+
+          return;
+        }
+
+        let Some(node) = node(global) else {
+          return;
+        };
+
+        insert(&mut nodes.write().unwrap(), node.clone());
+
+        // Links the stream again once the node it was following comes back
+        let mut target = target.borrow_mut();
+        let returned = matches!(&target.node, Some(old) if old.device.id == node.device.id);
+
+        if returned && target.capture.is_none() {
+          target.connect(&core, &listener, node);
+        }
+      }
+    })
+    .global_remove({
+      let nodes = nodes.clone();
+      let target = target.clone();
+      let listener = listener.clone();
+
+      move |id| {
+        nodes
+          .write()
+          .unwrap()
+          .retain(|node| node.device.index != id);
+
+        let mut target = target.borrow_mut();
+        let lost = target
+          .node
+          .as_ref()
+          .filter(|node| node.device.index == id)
+          .map(|node| node.device.to_string());
+
+        if let Some(device) = lost {
+          listener.reporter().report(Status::Warning(format!(
+            "{device} went away, capturing it again once it's back"
+          )));
+
+          target.capture = None;
+        }
+      }
+    })
+    .register();
+
+  // Every existing node was announced once the server answers this,
+  // the second answer comes after the values of the metadata bound while announcing them
+  let pending = Cell::new(core.sync(0)?);
+  let rounds = Cell::new(0);
+  let ready = RefCell::new(Some(ready));
+  let _core = core
+    .add_listener_local()
+    .done({
+      let core = core.clone();
+
+      move |id, seq| {
+        if id != pw::core::PW_ID_CORE || seq != pending.get() {
+          return;
+        }
+
+        rounds.set(rounds.get() + 1);
+
+        let result = match rounds.get() {
+          1 => core.sync(0).map(|seq| pending.set(seq)),
+          _ => Ok(()),
+        };
+
+        if rounds.get() > 1 || result.is_err() {
+          if let Some(ready) = ready.borrow_mut().take() {
+            let _ = ready.send(result);
+          }
+        }
+      }
+    })
+    .register();
+
+  let _receiver = receiver.attach(mainloop.loop_(), {
+    let mainloop = mainloop.clone();
+    let core = core.clone();
+
+    move |command| match command {
+      Command::Connect(node) => target.borrow_mut().connect(&core, &listener, node),
+      Command::Disconnect => *target.borrow_mut() = Target::default(),
+      Command::Quit => mainloop.quit(),
+    }
+  });
+
+  mainloop.run();
+
+  Ok(())
+}
+
+/// Keeps `defaults` up to date with the `default.audio.sink` and `default.audio.source` metadata
+fn watch_defaults(
+  registry: &pw::registry::Registry,
+  global: &pw::registry::GlobalObject<&spa::utils::dict::DictRef>,
+  defaults: Arc<RwLock<Defaults>>,
+) -> Option<(Metadata, MetadataListener)> {
+  let metadata = registry.bind::<Metadata, _>(global).ok()?;
+  let listener = metadata
+    .add_listener_local()
+    .property(move |_, key, _, value| {
+      let name = value.and_then(metadata_name).map(String::from);
+      let mut defaults = defaults.write().unwrap();
+
+      match key {
+        Some("default.audio.sink") => defaults.sink = name,
+        Some("default.audio.source") => defaults.source = name,
+        // Every key of the subject was removed
+        None => *defaults = Defaults::default(),
+        _ => {}
+      }
+
+      0
+    })
+    .register();
+
+  Some((metadata, listener))
+}
+
+/// Creates a stream that captures `node`, in the native rate and channels of the graph
+fn connect(core: &pw::core::Core, listener: &Listener, node: &Node) -> Result<Capture> {
+  let mut props = properties! {
+    *pw::keys::MEDIA_TYPE => "Audio",
+    *pw::keys::MEDIA_CATEGORY => "Capture",
+    *pw::keys::MEDIA_ROLE => "Music",
+    *pw::keys::NODE_NAME => "safav",
+    *pw::keys::TARGET_OBJECT => node.device.id.as_str(),
+    // Stays unlinked instead of being moved to another node if this one goes away
+    *pw::keys::NODE_DONT_RECONNECT => "true",
+  };
+
+  if node.monitor {
+    props.insert(*pw::keys::STREAM_CAPTURE_SINK, "true");
+  }
+
+  let stream = pw::stream::Stream::new(core, "safav", props)?;
+  let format = Format {
+    listener: listener.clone(),
+    info: AudioInfoRaw::new(),
+    feed: None,
+    samples: Vec::new(),
+  };
+
+  let stream_listener = stream
+    .add_local_listener_with_user_data(format)
+    .param_changed(|_, format, id, param| {
+      let Some(param) = param else {
+        return;
+      };
+
+      if id != ParamType::Format.as_raw() {
+        return;
+      }
+
+      match format_utils::parse_format(param) {
+        Ok((MediaType::Audio, MediaSubtype::Raw)) => {}
+        _ => return,
+      }
+
+      if format.info.parse(param).is_err() {
+        return;
+      }
+
+      let config = StreamConfig {
+        channels: format.info.channels() as u16,
+        sample_rate: SampleRate(format.info.rate()),
+        buffer_size: BufferSize::Default,
+      };
+
+      format.feed = Some(Box::new(format.listener.feed(&config)));
+    })
+    .process(|stream, format| {
+      let (Some(mut buffer), Some(feed)) = (stream.dequeue_buffer(), &mut format.feed) else {
+        return;
+      };
+
+      let Some(data) = buffer.datas_mut().first_mut() else {
+        return;
+      };
+
+      let offset = data.chunk().offset() as usize;
+      let size = data.chunk().size() as usize;
+
+      if let Some(bytes) = data.data() {
+        let bytes = &bytes[offset.min(bytes.len())..(offset + size).min(bytes.len())];
+
+        // Only allocates until it fits the largest buffer
+        format.samples.clear();
+        format.samples.extend(
+          bytes
+            .chunks_exact(4)
+            .map(|sample| f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]])),
+        );
+
+        feed(&format.samples);
+      }
+    })
+    .register()?;
+
+  // Only asks for f32 samples, leaving the rate and channels to the graph
+  let mut info = AudioInfoRaw::new();
+  info.set_format(AudioFormat::F32LE);
+
+  let object = Object {
+    type_: SpaTypes::ObjectParamFormat.as_raw(),
+    id: ParamType::EnumFormat.as_raw(),
+    properties: info.into(),
+  };
+  let bytes = PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &Value::Object(object))
+    .map_err(|_| pw::Error::CreationFailed)?
+    .0
+    .into_inner();
+  let mut params = [Pod::from_bytes(&bytes).ok_or(pw::Error::CreationFailed)?];
+
+  stream.connect(
+    Direction::Input,
+    None,
+    StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::RT_PROCESS,
+    &mut params,
+  )?;
+
+  Ok(Capture {
+    _listener: stream_listener,
+    _stream: stream,
+  })
+}
+
+impl Backend for PipeWireHost {
+  fn listener(&self) -> &Listener {
+    &self.listener
+  }
+
+  fn current_device_index(&self) -> Option<usize> {
+    PipeWireHost::current_device_index(self)
+  }
+
+  fn current_device(&self) -> Option<&Device> {
+    PipeWireHost::current_device(self)
+  }
+
+  fn default_device(&self) -> Result<&Device> {
+    PipeWireHost::default_device(self)
+  }
+
+  fn devices(&self) -> &Vec<Device> {
+    PipeWireHost::devices(self)
+  }
+
+  fn change_device_by_index(&self, index: usize) -> Result<()> {
+    PipeWireHost::change_device_by_index(self, index)
+  }
+
+  fn change_device(&self, device: &Device) -> Result<()> {
+    PipeWireHost::change_device(self, device)
+  }
+
+  fn listen(&mut self) -> Result<()> {
+    PipeWireHost::listen(self)
+  }
+
+  fn stop(&mut self) -> Result<()> {
+    PipeWireHost::stop(self)
+  }
+
+  fn refresh(&mut self) -> Result<()> {
+    PipeWireHost::refresh(self)
+  }
+}
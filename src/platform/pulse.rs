@@ -0,0 +1,515 @@
+#![cfg(all(target_os = "linux", feature = "pulse"))]
+
+//! PulseAudio backend that records straight from a source or sink monitor by its name,
+//! so nothing depends on finding the stream of this application and moving it
+//!
+//! Can be tried against a separate daemon with a null sink:
+//!
+//! ```sh
+//! pulseaudio --daemonize --exit-idle-time=-1
+//! pactl load-module module-null-sink sink_name=safav-null
+//! cargo run --example pulse --features pulse
+//! ```
+
+use std::{
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, RwLock,
+  },
+  thread::{self, JoinHandle},
+};
+
+use cpal::{BufferSize, SampleRate, StreamConfig};
+use libpulse_binding::{
+  def::BufferAttr,
+  sample::{Format, Spec},
+  stream::Direction,
+};
+use libpulse_simple_binding::Simple;
+use pulsectl::controllers::SourceController;
+
+use super::{
+  application::{self, AppCapture},
+  events::{DeviceEvents, EventSenders, Follow},
+  source,
+  supervisor::Restart,
+  watch::Watcher,
+  Backend,
+};
+use crate::{Application, Device, DeviceKind, Error, Listener, Result, Status};
+
+/// Record stream of one source, dropping it stops reading
+struct Capture {
+  running: Arc<AtomicBool>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Capture {
+  fn drop(&mut self) {
+    self.running.store(false, Ordering::Release);
+
+    // Every read only waits for ~10ms of samples, so this doesn't take long
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+pub struct PulseHost {
+  pub devices: Vec<Device>,
+  pub listener: Listener,
+  pub current_device_index: RwLock<Option<usize>>,
+  /// Native format of every device, in the same order
+  specs: Vec<Spec>,
+  default_device_index: Option<usize>,
+  /// Shared with the watcher and the restarter, which reconnect it to follow the default device or recover
+  capture: Arc<Mutex<Option<Capture>>>,
+  /// Application that's captured instead of a device
+  application: Arc<Mutex<Option<AppCapture>>>,
+  events: EventSenders,
+  follow: Arc<Follow>,
+  watcher: Mutex<Option<Watcher>>,
+}
+
+/// Gets every source with its native format and the index of the monitor of the default sink
+fn sources() -> Result<(Vec<Device>, Vec<Spec>, Option<usize>)> {
+  let mut controller = SourceController::create()?;
+  let (mut devices, specs): (Vec<_>, Vec<_>) = source::sources(&mut controller)?
+    .into_iter()
+    .map(|(device, info)| (device, info.sample_spec))
+    .unzip();
+  let default = source::mark_default(&mut controller, &mut devices)?;
+
+  Ok((devices, specs, default))
+}
+
+impl PulseHost {
+  pub fn new() -> Result<Self> {
+    let (devices, specs, default_device_index) = sources()?;
+
+    Ok(Self {
+      devices,
+      listener: Listener::new(),
+      current_device_index: RwLock::default(),
+      specs,
+      default_device_index,
+      capture: Arc::default(),
+      application: Arc::default(),
+      events: EventSenders::default(),
+      follow: Arc::default(),
+      watcher: Mutex::default(),
+    })
+  }
+
+  pub fn current_device_index(&self) -> Option<usize> {
+    self
+      .follow
+      .resolve(&self.devices, &self.current_device_index);
+
+    *self.current_device_index.read().unwrap()
+  }
+
+  pub fn current_device(&self) -> Option<&Device> {
+    self
+      .current_device_index()
+      .and_then(|i| self.devices.get(i))
+  }
+
+  /// Gets the monitor of the default sink, or the default source if it has no monitor
+  pub fn default_device(&self) -> Result<&Device> {
+    self
+      .default_device_index
+      .and_then(|index| self.devices.get(index))
+      .or_else(|| self.devices.first())
+      .ok_or(Error::NoDefaultDeviceFound)
+  }
+
+  pub fn devices(&self) -> &Vec<Device> {
+    &self.devices
+  }
+
+  pub fn change_device_by_index(&self, index: usize) -> Result<()> {
+    let device = self
+      .devices
+      .get(index)
+      .ok_or(Error::InvalidDeviceIndex(index))?;
+    let mut capture = self.capture.lock().unwrap();
+
+    if let Some(mut application) = self.application.lock().unwrap().take() {
+      application.restore()?;
+      self.follow.paused.store(false, Ordering::Release);
+    }
+
+    *self.current_device_index.write().unwrap() = Some(index);
+
+    // Only reconnects once it's listening, otherwise [Self::listen] connects to it
+    if capture.is_some() {
+      // Stops the old stream first so two never feed the listener at once
+      capture.take();
+      *capture = Some(connect(&self.listener, device, self.specs[index])?);
+    }
+
+    Ok(())
+  }
+
+  pub fn change_device(&self, device: &Device) -> Result<()> {
+    let index = self
+      .devices
+      .iter()
+      .position(|dev| dev.id == device.id)
+      .ok_or_else(|| Error::NoDeviceFound(device.name.clone()))?;
+
+    self.change_device_by_index(index)
+  }
+
+  pub fn listen(&mut self) -> Result<()> {
+    {
+      let mut capture = self.capture.lock().unwrap();
+
+      if let Some(application) = &*self.application.lock().unwrap() {
+        capture.take();
+        *capture = Some(connect_application(&self.listener, application)?);
+
+        return Ok(());
+      }
+    }
+
+    let index = match self.current_device_index() {
+      Some(index) => index,
+      None => {
+        let default = self.default_device()?;
+
+        self
+          .devices
+          .iter()
+          .position(|dev| dev == default)
+          .unwrap_or(0)
+      }
+    };
+
+    let mut capture = self.capture.lock().unwrap();
+
+    capture.take();
+    *capture = Some(connect(
+      &self.listener,
+      &self.devices[index],
+      self.specs[index],
+    )?);
+    *self.current_device_index.write().unwrap() = Some(index);
+
+    Ok(())
+  }
+
+  pub fn stop(&mut self) -> Result<()> {
+    self.capture.lock().unwrap().take();
+
+    Ok(())
+  }
+
+  /// Lists sources again, keeping the current device selected by its name
+  pub fn refresh(&mut self) -> Result<()> {
+    let current = self.current_device().map(|device| device.id.clone());
+    let (devices, specs, default_device_index) = sources()?;
+
+    *self.current_device_index.write().unwrap() =
+      current.and_then(|id| devices.iter().position(|dev| dev.id == id));
+
+    self.devices = devices;
+    self.specs = specs;
+    self.default_device_index = default_device_index;
+
+    Ok(())
+  }
+
+  pub fn applications(&self) -> Result<Vec<Application>> {
+    application::applications()
+  }
+
+  pub fn current_application(&self) -> Option<Application> {
+    self
+      .application
+      .lock()
+      .unwrap()
+      .as_ref()
+      .map(|application| application.application.clone())
+  }
+
+  pub fn capture_application(&self, application: &Application) -> Result<()> {
+    let mut capture = self.capture.lock().unwrap();
+    let mut current = self.application.lock().unwrap();
+
+    if let Some(mut previous) = current.take() {
+      previous.restore()?;
+    }
+
+    let application = AppCapture::new(application)?;
+
+    // Only reconnects once it's listening, otherwise [Self::listen] connects to it
+    if capture.is_some() {
+      capture.take();
+      *capture = Some(connect_application(&self.listener, &application)?);
+    }
+
+    *current = Some(application);
+    *self.current_device_index.write().unwrap() = None;
+    self.follow.paused.store(true, Ordering::Release);
+
+    Ok(())
+  }
+
+  pub fn stop_capturing_application(&self) -> Result<()> {
+    if self.application.lock().unwrap().is_none() {
+      return Ok(());
+    }
+
+    let default = self.default_device()?.clone();
+
+    self.change_device(&default)
+  }
+
+  /// Starts watching PulseAudio for changes if it isn't already
+  fn watch(&self) -> Result<()> {
+    let mut watcher = self.watcher.lock().unwrap();
+
+    if watcher.is_some() {
+      return Ok(());
+    }
+
+    let capture = self.capture.clone();
+    let listener = self.listener.clone();
+
+    *watcher = Some(Watcher::spawn(
+      self.events.clone(),
+      self.listener.reporter(),
+      self.follow.clone(),
+      Box::new(move |device| {
+        let mut capture = capture.lock().unwrap();
+
+        // Not listening yet, [PulseHost::listen] connects to the followed device
+        if capture.is_none() {
+          return Ok(());
+        }
+
+        reconnect(&mut capture, &listener, device)
+      }),
+    )?);
+
+    Ok(())
+  }
+
+  pub fn device_events(&self) -> Result<DeviceEvents> {
+    let events = self.events.subscribe();
+
+    self.watch()?;
+
+    Ok(events)
+  }
+
+  pub fn follow_default_device(&self, follow: bool) -> Result<()> {
+    if follow {
+      self.watch()?;
+    }
+
+    self.follow.enabled.store(follow, Ordering::Release);
+
+    Ok(())
+  }
+
+  /// Reconnects to the device, or the application that's captured
+  pub(crate) fn restarter(&self) -> Result<Restart> {
+    let capture = self.capture.clone();
+    let application = self.application.clone();
+    let listener = self.listener.clone();
+    let follow = self.follow.clone();
+
+    Ok(Box::new(move |device| {
+      let mut capture = capture.lock().unwrap();
+
+      if let Some(application) = &*application.lock().unwrap() {
+        capture.take();
+        *capture = Some(connect_application(&listener, application)?);
+
+        return Ok(());
+      }
+
+      reconnect(&mut capture, &listener, device)?;
+
+      // Picked up by [PulseHost::current_device_index] like a followed default
+      *follow.followed.lock().unwrap() = Some(device.clone());
+
+      Ok(())
+    }))
+  }
+}
+
+/// Looks up the format of `device` again and connects to it, it might have changed since it was listed
+fn reconnect(capture: &mut Option<Capture>, listener: &Listener, device: &Device) -> Result<()> {
+  let (devices, specs, _) = sources()?;
+  let index = devices
+    .iter()
+    .position(|dev| dev.id == device.id)
+    .ok_or_else(|| Error::NoDeviceFound(device.name.clone()))?;
+
+  // Stops the old stream first so two never feed the listener at once
+  capture.take();
+  *capture = Some(connect(listener, device, specs[index])?);
+
+  Ok(())
+}
+
+/// Records the monitor of the null sink the application was moved to
+fn connect_application(listener: &Listener, application: &AppCapture) -> Result<Capture> {
+  let source = application.source();
+  let device = Device {
+    name: application.application.name.clone(),
+    id: source.name.clone().unwrap_or_default(),
+    kind: DeviceKind::Application,
+    backend: "PulseAudio",
+    index: source.index,
+    ..Default::default()
+  };
+
+  connect(listener, &device, source.sample_spec)
+}
+
+/// Opens a record stream on `device` and reads it on its own thread until it's dropped
+fn connect(listener: &Listener, device: &Device, native: Spec) -> Result<Capture> {
+  let spec = Spec {
+    format: Format::FLOAT32NE,
+    rate: native.rate,
+    channels: native.channels,
+  };
+
+  // Asks for ~10ms at a time so the latency stays close to the other backends
+  let frames = (spec.rate / 100).max(1);
+  let bytes = frames * spec.channels.max(1) as u32 * 4;
+  let attr = BufferAttr {
+    maxlength: u32::MAX,
+    tlength: u32::MAX,
+    prebuf: u32::MAX,
+    minreq: u32::MAX,
+    fragsize: bytes,
+  };
+
+  let simple = Simple::new(
+    None,
+    "safav",
+    Direction::Record,
+    Some(&device.id),
+    "capture",
+    &spec,
+    None,
+    Some(&attr),
+  )?;
+
+  let mut feed = listener.feed(&StreamConfig {
+    channels: spec.channels as u16,
+    sample_rate: SampleRate(spec.rate),
+    buffer_size: BufferSize::Default,
+  });
+  let health = listener.health();
+  let reporter = listener.reporter();
+  let running = Arc::new(AtomicBool::new(true));
+  let thread = {
+    let running = running.clone();
+
+    thread::Builder::new()
+      .name(String::from("safav-pulse"))
+      .spawn(move || {
+        let mut bytes = vec![0u8; bytes as usize];
+        let mut samples = Vec::with_capacity(bytes.len() / 4);
+
+        while running.load(Ordering::Acquire) {
+          if let Err(err) = simple.read(&mut bytes) {
+            health.fail(err);
+            reporter.report(Status::StreamError(err.to_string()));
+            break;
+          }
+
+          samples.clear();
+          samples.extend(
+            bytes
+              .chunks_exact(4)
+              .map(|sample| f32::from_ne_bytes([sample[0], sample[1], sample[2], sample[3]])),
+          );
+
+          feed(&samples);
+        }
+      })?
+  };
+
+  Ok(Capture {
+    running,
+    thread: Some(thread),
+  })
+}
+
+impl Backend for PulseHost {
+  fn listener(&self) -> &Listener {
+    &self.listener
+  }
+
+  fn current_device_index(&self) -> Option<usize> {
+    PulseHost::current_device_index(self)
+  }
+
+  fn current_device(&self) -> Option<&Device> {
+    PulseHost::current_device(self)
+  }
+
+  fn default_device(&self) -> Result<&Device> {
+    PulseHost::default_device(self)
+  }
+
+  fn devices(&self) -> &Vec<Device> {
+    PulseHost::devices(self)
+  }
+
+  fn change_device_by_index(&self, index: usize) -> Result<()> {
+    PulseHost::change_device_by_index(self, index)
+  }
+
+  fn change_device(&self, device: &Device) -> Result<()> {
+    PulseHost::change_device(self, device)
+  }
+
+  fn listen(&mut self) -> Result<()> {
+    PulseHost::listen(self)
+  }
+
+  fn stop(&mut self) -> Result<()> {
+    PulseHost::stop(self)
+  }
+
+  fn refresh(&mut self) -> Result<()> {
+    PulseHost::refresh(self)
+  }
+
+  fn applications(&self) -> Result<Vec<Application>> {
+    PulseHost::applications(self)
+  }
+
+  fn current_application(&self) -> Option<Application> {
+    PulseHost::current_application(self)
+  }
+
+  fn capture_application(&self, application: &Application) -> Result<()> {
+    PulseHost::capture_application(self, application)
+  }
+
+  fn stop_capturing_application(&self) -> Result<()> {
+    PulseHost::stop_capturing_application(self)
+  }
+
+  fn device_events(&self) -> Result<DeviceEvents> {
+    PulseHost::device_events(self)
+  }
+
+  fn follow_default_device(&self, follow: bool) -> Result<()> {
+    PulseHost::follow_default_device(self, follow)
+  }
+
+  fn restarter(&self) -> Result<Restart> {
+    PulseHost::restarter(self)
+  }
+}
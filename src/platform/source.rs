@@ -0,0 +1,86 @@
+#![cfg(target_os = "linux")]
+
+//! Lists PulseAudio sources as devices for the default Linux backend, the PulseAudio backend and the watcher
+
+use std::borrow::Cow;
+
+use pulsectl::controllers::{types::DeviceInfo, DeviceControl, SourceController};
+
+use super::application::SINK_PREFIX;
+use crate::{Device, DeviceKind, Result, SampleFormat};
+
+/// Lists sources with what PulseAudio knows about them, leaving out the monitors of the sinks applications are moved to
+pub(crate) fn sources(controller: &mut SourceController) -> Result<Vec<(Device, DeviceInfo)>> {
+  Ok(
+    controller
+      .list_devices()?
+      .into_iter()
+      .filter(|info| {
+        !info
+          .name
+          .as_ref()
+          .is_some_and(|name| name.starts_with(SINK_PREFIX))
+      })
+      .filter_map(|info| Some((device(&info)?, info)))
+      .collect(),
+  )
+}
+
+/// Gets the index of the monitor of the default sink, or else the default source, and marks it as default
+pub(crate) fn mark_default(
+  controller: &mut SourceController,
+  devices: &mut [Device],
+) -> Result<Option<usize>> {
+  let server = controller.get_server_info()?;
+  // Loopback captures whatever plays on the default sink, through its monitor
+  let monitor = server
+    .default_sink_name
+    .map(|sink| format!("{sink}.monitor"));
+
+  let default = [monitor, server.default_source_name]
+    .into_iter()
+    .flatten()
+    .find_map(|name| devices.iter().position(|dev| dev.id == name));
+
+  if let Some(default) = default {
+    devices[default].default = true;
+  }
+
+  Ok(default)
+}
+
+fn device(info: &DeviceInfo) -> Option<Device> {
+  // Filters and other modules don't have a class of `sound`
+  let kind = match info.monitor {
+    Some(_) => DeviceKind::OutputMonitor,
+    None if info.proplist.get_str("device.class").as_deref() == Some("sound") => DeviceKind::Input,
+    None => DeviceKind::Virtual,
+  };
+  let spec = info.sample_spec;
+  let format = spec.format.to_string().and_then(sample_format);
+
+  Some(Device {
+    name: info.description.clone()?,
+    id: info.name.clone()?,
+    kind,
+    channels: Some(spec.channels.into()),
+    sample_rates: vec![spec.rate],
+    formats: format.into_iter().collect(),
+    backend: "PulseAudio",
+    index: info.index,
+    ..Default::default()
+  })
+}
+
+/// Matches the name PulseAudio gives a format, libpulse-binding is only a dependency with `events`
+fn sample_format(name: Cow<str>) -> Option<SampleFormat> {
+  match &*name {
+    "u8" => Some(SampleFormat::U8),
+    "s16le" | "s16be" => Some(SampleFormat::I16),
+    "s24le" | "s24be" => Some(SampleFormat::I24),
+    // 24 bit samples padded to 32 bits read like 32 bit ones
+    "s24-32le" | "s24-32be" | "s32le" | "s32be" => Some(SampleFormat::I32),
+    "float32le" | "float32be" => Some(SampleFormat::F32),
+    _ => None,
+  }
+}
@@ -0,0 +1,102 @@
+use std::{
+  fmt::{Display, Formatter},
+  sync::{Mutex, RwLock},
+};
+
+use super::events::{EventSenders, Events};
+use crate::{DeviceEvent, StreamState};
+
+/// Receives every [Status] reported by a [Host](crate::Host) from the moment it was created
+pub type Statuses = Events<Status>;
+
+/// Something that happened while listening, see [Host::statuses](crate::Host::statuses)
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Status {
+  /// The stream reported an error, it might not give any audio anymore
+  StreamError(String),
+  /// The audio system reported an overrun or underrun, only reported by JACK
+  Xrun,
+  /// Blocks were dropped because the worker fell behind, see [WorkerStats::dropped](crate::WorkerStats::dropped)
+  Dropped { blocks: u64 },
+  /// Only reported while devices are watched, see [Host::device_events](crate::Host::device_events)
+  Device(DeviceEvent),
+  /// Only reported while the stream is supervised, see [Host::supervise](crate::Host::supervise)
+  Stream(StreamState),
+  /// Something failed without stopping the stream, like following the default device or decoding a file
+  Warning(String),
+}
+
+impl Display for Status {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Status::StreamError(err) => write!(f, "Stream error: {err}"),
+      Status::Xrun => write!(f, "Xrun"),
+      Status::Dropped { blocks } => write!(f, "Dropped {blocks} blocks"),
+      Status::Device(DeviceEvent::Added(device)) => write!(f, "Added {device}"),
+      Status::Device(DeviceEvent::Removed(device)) => write!(f, "Removed {device}"),
+      Status::Device(DeviceEvent::DefaultChanged(device)) => write!(f, "Default is now {device}"),
+      Status::Stream(state) => write!(f, "Stream {state}"),
+      Status::Warning(warning) => Display::fmt(warning, f),
+    }
+  }
+}
+
+type Handler = Box<dyn Fn(&Status) + Send + Sync>;
+
+/// Kept by a [Listener](crate::Listener) so anything running for it can report what happened
+#[derive(Default)]
+pub(crate) struct Reporter {
+  senders: EventSenders<Status>,
+  handlers: RwLock<Vec<Handler>>,
+  /// Last error of the stream, until it's running again
+  error: Mutex<Option<String>>,
+}
+
+impl Reporter {
+  pub fn subscribe(&self) -> Statuses {
+    self.senders.subscribe()
+  }
+
+  pub fn add_handler(&self, handler: Handler) {
+    self.handlers.write().unwrap().push(handler);
+  }
+
+  /// Logs `status` with the `log` feature, then gives it to every handler and receiver
+  pub fn report(&self, status: Status) {
+    match &status {
+      Status::StreamError(err) | Status::Stream(StreamState::Failed(err)) => {
+        *self.error.lock().unwrap() = Some(err.clone());
+      }
+      Status::Stream(StreamState::Running(_)) => self.clear_error(),
+      _ => {}
+    }
+
+    #[cfg(feature = "log")]
+    match &status {
+      Status::StreamError(_) => log::error!("{status}"),
+      Status::Xrun | Status::Dropped { .. } | Status::Warning(_) => log::warn!("{status}"),
+      Status::Device(_) | Status::Stream(_) => log::info!("{status}"),
+    }
+
+    for handler in self.handlers.read().unwrap().iter() {
+      handler(&status);
+    }
+
+    self.senders.send(status);
+  }
+
+  #[cfg(any(feature = "file", feature = "events", feature = "pipewire"))]
+  pub fn warn(&self, warning: impl Display) {
+    self.report(Status::Warning(warning.to_string()));
+  }
+
+  /// Gets the last error of the stream if it didn't recover from it
+  pub fn error(&self) -> Option<String> {
+    self.error.lock().unwrap().clone()
+  }
+
+  /// Forgets the last error once the stream was started again
+  pub fn clear_error(&self) {
+    self.error.lock().unwrap().take();
+  }
+}
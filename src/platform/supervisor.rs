@@ -0,0 +1,340 @@
+use std::{
+  fmt::{Display, Formatter},
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex,
+  },
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
+
+use super::{
+  events::{EventSenders, Events},
+  Reporter,
+};
+use crate::{Device, Result, Status};
+
+/// Receives every [StreamState] a supervised [Host](crate::Host) goes through
+pub type StreamStates = Events<StreamState>;
+
+/// Rebuilds the stream of a backend on a device, called from the supervisor thread
+pub(crate) type Restart = Box<dyn FnMut(&Device) -> Result<()> + Send>;
+
+/// State of a stream supervised with [Host::supervise](crate::Host::supervise)
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum StreamState {
+  /// Blocks arrive like they should, sent when supervising starts and once the stream was rebuilt
+  Running(Device),
+  /// No block arrived for [SupervisorSettings::stall_timeout]
+  Stalled,
+  /// The stream reported an error or rebuilding it failed
+  Failed(String),
+  /// Rebuilding the stream, `attempt` starts at 1 and resets once it's running again
+  Recovering { attempt: u32, device: Device },
+  /// Stopped rebuilding after [SupervisorSettings::max_attempts]
+  GaveUp,
+}
+
+impl Display for StreamState {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      StreamState::Running(device) => write!(f, "running on {device}"),
+      StreamState::Stalled => write!(f, "stalled"),
+      StreamState::Failed(err) => write!(f, "failed: {err}"),
+      StreamState::Recovering { attempt, device } => {
+        write!(f, "recovering on {device}, attempt {attempt}")
+      }
+      StreamState::GaveUp => write!(f, "gave up recovering"),
+    }
+  }
+}
+
+/// How a supervised stream is watched and rebuilt
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SupervisorSettings {
+  /// How long without a block until the stream counts as stalled
+  pub stall_timeout: Duration,
+  /// Wait after the first failed attempt, doubled after every attempt that fails after it
+  pub backoff: Duration,
+  pub max_backoff: Duration,
+  /// Attempts on the same device before falling back to the default device
+  pub attempts_per_device: u32,
+  /// Attempts until it gives up, `None` to keep trying
+  pub max_attempts: Option<u32>,
+}
+
+impl Default for SupervisorSettings {
+  fn default() -> Self {
+    Self {
+      stall_timeout: Duration::from_millis(500),
+      backoff: Duration::from_millis(100),
+      max_backoff: Duration::from_secs(5),
+      attempts_per_device: 3,
+      max_attempts: None,
+    }
+  }
+}
+
+/// Kept by a [Listener](crate::Listener) so the stream callbacks can report to a [Supervisor]
+#[derive(Debug)]
+pub(crate) struct Health {
+  start: Instant,
+  /// Nanoseconds since `start` when the last block of the device arrived
+  last_block: AtomicU64,
+  error: Mutex<Option<String>>,
+}
+
+impl Default for Health {
+  fn default() -> Self {
+    Self {
+      start: Instant::now(),
+      last_block: AtomicU64::new(0),
+      error: Mutex::default(),
+    }
+  }
+}
+
+impl Health {
+  fn nanos(&self, time: Instant) -> u64 {
+    time.saturating_duration_since(self.start).as_nanos() as u64
+  }
+
+  /// Called for every block of the device stream with the time it arrived
+  pub fn beat(&self, time: Instant) {
+    self.last_block.store(self.nanos(time), Ordering::Relaxed);
+  }
+
+  /// Called by the error callback of the device stream
+  pub fn fail(&self, err: impl Display) {
+    *self.error.lock().unwrap() = Some(err.to_string());
+  }
+
+  fn take_error(&self) -> Option<String> {
+    self.error.lock().unwrap().take()
+  }
+
+  /// Whether a block arrived after `time`
+  fn beat_since(&self, time: Instant) -> bool {
+    self.last_block.load(Ordering::Relaxed) >= self.nanos(time)
+  }
+
+  fn since_last_block(&self) -> Duration {
+    let last = Duration::from_nanos(self.last_block.load(Ordering::Relaxed));
+
+    self.start.elapsed().saturating_sub(last)
+  }
+}
+
+/// Device the stream is rebuilt on, shared with the [Host](crate::Host) so changing devices updates it
+pub(crate) type Current = Arc<Mutex<Device>>;
+
+/// Thread that watches the stream of a backend and rebuilds it until it's dropped
+pub(crate) struct Supervisor {
+  running: Arc<AtomicBool>,
+  current: Current,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl Supervisor {
+  pub fn spawn(
+    settings: SupervisorSettings,
+    health: Arc<Health>,
+    restart: Restart,
+    (device, fallback): (Device, Device),
+    states: EventSenders<StreamState>,
+    reporter: Arc<Reporter>,
+  ) -> Result<Self> {
+    let running = Arc::new(AtomicBool::new(true));
+
+    let current = Arc::new(Mutex::new(device));
+    let thread = {
+      let running = running.clone();
+      let current = current.clone();
+
+      thread::Builder::new()
+        .name(String::from("safav-supervisor"))
+        .spawn(move || {
+          let mut supervised = Supervised {
+            settings,
+            health,
+            restart,
+            current,
+            fallback,
+            states,
+            reporter,
+            running,
+          };
+
+          supervised.run()
+        })?
+    };
+
+    Ok(Self {
+      running,
+      current,
+      thread: Some(thread),
+    })
+  }
+
+  /// Changes the device the stream is rebuilt on
+  pub fn set_device(&self, device: &Device) {
+    *self.current.lock().unwrap() = device.clone();
+  }
+}
+
+impl Drop for Supervisor {
+  fn drop(&mut self) {
+    self.running.store(false, Ordering::Release);
+
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+struct Supervised {
+  settings: SupervisorSettings,
+  health: Arc<Health>,
+  restart: Restart,
+  current: Current,
+  fallback: Device,
+  states: EventSenders<StreamState>,
+  reporter: Arc<Reporter>,
+  running: Arc<AtomicBool>,
+}
+
+impl Supervised {
+  /// Sends `state` to [StreamStates] and reports it as [Status::Stream]
+  fn send(&self, state: StreamState) {
+    self.states.send(state.clone());
+    self.reporter.report(Status::Stream(state));
+  }
+
+  /// How often the stream is checked
+  fn interval(&self) -> Duration {
+    (self.settings.stall_timeout / 4).clamp(Duration::from_millis(1), Duration::from_millis(50))
+  }
+
+  /// Sleeps for `duration` unless it's stopped first
+  fn sleep(&self, duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+
+    while self.running.load(Ordering::Acquire) {
+      let now = Instant::now();
+
+      if now >= deadline {
+        return true;
+      }
+
+      thread::sleep((deadline - now).min(self.interval()));
+    }
+
+    false
+  }
+
+  /// Gets what went wrong with the stream, if anything did
+  fn check(&self) -> Option<StreamState> {
+    if let Some(err) = self.health.take_error() {
+      return Some(StreamState::Failed(err));
+    }
+
+    if self.current.lock().unwrap().stops_when_silent() {
+      return None;
+    }
+
+    (self.health.since_last_block() > self.settings.stall_timeout).then_some(StreamState::Stalled)
+  }
+
+  fn run(&mut self) {
+    let device = self.current.lock().unwrap().clone();
+
+    // Counts the time before the first block as a block so it isn't stalled right away,
+    // errors from before it was supervised are left out but not ones after it says it's running
+    self.health.beat(Instant::now());
+    self.health.take_error();
+    self.send(StreamState::Running(device));
+
+    while self.sleep(self.interval()) {
+      let Some(state) = self.check() else {
+        continue;
+      };
+
+      self.send(state);
+
+      if !self.recover() {
+        break;
+      }
+    }
+  }
+
+  /// Rebuilds the stream until a block arrives, `false` if it gave up or was stopped
+  fn recover(&mut self) -> bool {
+    let mut backoff = self.settings.backoff;
+    let mut attempt = 1;
+
+    loop {
+      let device = match attempt <= self.settings.attempts_per_device {
+        true => self.current.lock().unwrap().clone(),
+        false => self.fallback.clone(),
+      };
+
+      self.send(StreamState::Recovering {
+        attempt,
+        device: device.clone(),
+      });
+
+      let started = Instant::now();
+
+      match (self.restart)(&device) {
+        Ok(()) if self.wait_for_block(&device, started) => {
+          *self.current.lock().unwrap() = device.clone();
+          self.send(StreamState::Running(device));
+
+          return true;
+        }
+        Ok(()) => {}
+        Err(err) => self.send(StreamState::Failed(err.to_string())),
+      }
+
+      if self.settings.max_attempts.is_some_and(|max| attempt >= max) {
+        self.send(StreamState::GaveUp);
+
+        return false;
+      }
+
+      if !self.sleep(backoff) {
+        return false;
+      }
+
+      backoff = (backoff * 2).min(self.settings.max_backoff);
+      attempt += 1;
+    }
+  }
+
+  /// Waits up to [SupervisorSettings::stall_timeout] for a block after `started`,
+  /// a device that [stops when silent](Device::stops_when_silent) counts as started right away
+  fn wait_for_block(&self, device: &Device, started: Instant) -> bool {
+    if device.stops_when_silent() {
+      self.health.take_error();
+
+      return true;
+    }
+
+    let deadline = started + self.settings.stall_timeout;
+
+    while Instant::now() < deadline {
+      if self.health.beat_since(started) {
+        // Errors from the old stream don't count against the new one
+        self.health.take_error();
+
+        return true;
+      }
+
+      if !self.sleep(self.interval()) {
+        return false;
+      }
+    }
+
+    self.health.beat_since(started)
+  }
+}
@@ -0,0 +1,222 @@
+#![cfg(all(target_os = "linux", feature = "events"))]
+
+//! Watches PulseAudio, or PipeWire through its pulse server, for sources and sinks being added,
+//! removed or the default one changing
+
+use std::{
+  cell::Cell,
+  rc::Rc,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc, Arc,
+  },
+  thread::{self, JoinHandle},
+};
+
+use libpulse_binding::{
+  context::{subscribe::InterestMaskSet, Context, FlagSet, State},
+  mainloop::standard::{IterateResult, Mainloop},
+  time::MicroSeconds,
+};
+use pulsectl::controllers::SourceController;
+
+use super::{
+  events::{self, EventSenders, Follow},
+  source, Reporter,
+};
+use crate::{Device, Error, Result, Status};
+
+/// How long the watcher thread waits for events before checking if it should stop
+const IDLE_TIMEOUT: MicroSeconds = MicroSeconds(100_000);
+
+/// Moves capture to a device, called by the watcher thread when it follows the default
+pub(crate) type Route = Box<dyn FnMut(&Device) -> Result<()> + Send>;
+
+/// Lists sources with the monitor of the default sink as default, or else the default source
+fn snapshot() -> Result<(Vec<Device>, Option<Device>)> {
+  let mut controller = SourceController::create()?;
+  let mut devices = source::sources(&mut controller)?
+    .into_iter()
+    .map(|(device, _)| device)
+    .collect::<Vec<_>>();
+  let default =
+    source::mark_default(&mut controller, &mut devices)?.map(|index| devices[index].clone());
+
+  Ok((devices, default))
+}
+
+fn connect() -> Result<(Mainloop, Context)> {
+  let mut mainloop = Mainloop::new().ok_or(Error::PulseConnectionFailed)?;
+  let mut context = Context::new(&mainloop, "safav-watch").ok_or(Error::PulseConnectionFailed)?;
+
+  context.connect(None, FlagSet::NOFLAGS, None)?;
+
+  loop {
+    match mainloop.iterate(true) {
+      IterateResult::Success(_) => {}
+      IterateResult::Err(err) => return Err(err.into()),
+      IterateResult::Quit(_) => return Err(Error::PulseConnectionFailed),
+    }
+
+    match context.get_state() {
+      State::Ready => return Ok((mainloop, context)),
+      State::Failed | State::Terminated => return Err(Error::PulseConnectionFailed),
+      _ => {}
+    }
+  }
+}
+
+/// Thread that sends [DeviceEvent](crate::DeviceEvent)s and reports them as [Status::Device] until it's dropped
+pub(crate) struct Watcher {
+  running: Arc<AtomicBool>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+  pub fn spawn(
+    events: EventSenders,
+    reporter: Arc<Reporter>,
+    follow: Arc<Follow>,
+    route: Route,
+  ) -> Result<Self> {
+    let running = Arc::new(AtomicBool::new(true));
+    // The connection can't leave its thread, so only whether it connected is sent back
+    let (ready, connected) = mpsc::sync_channel(1);
+    let thread = {
+      let running = running.clone();
+
+      thread::Builder::new()
+        .name(String::from("safav-watch"))
+        .spawn(move || {
+          let state =
+            connect().and_then(|(mainloop, context)| Ok((mainloop, context, snapshot()?)));
+
+          match state {
+            Ok((mainloop, context, snapshot)) => {
+              let _ = ready.send(Ok(()));
+
+              let watched = Watched {
+                events,
+                reporter,
+                follow,
+                route,
+              };
+
+              run(mainloop, context, snapshot, running, watched);
+            }
+            Err(err) => {
+              let _ = ready.send(Err(err));
+            }
+          }
+        })?
+    };
+
+    connected
+      .recv()
+      .expect("watcher thread always sends if it connected")?;
+
+    Ok(Self {
+      running,
+      thread: Some(thread),
+    })
+  }
+}
+
+impl Drop for Watcher {
+  fn drop(&mut self) {
+    self.running.store(false, Ordering::Release);
+
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+/// What the watcher thread gives changes to
+struct Watched {
+  events: EventSenders,
+  reporter: Arc<Reporter>,
+  follow: Arc<Follow>,
+  route: Route,
+}
+
+fn run(
+  mut mainloop: Mainloop,
+  mut context: Context,
+  (mut devices, mut default): (Vec<Device>, Option<Device>),
+  running: Arc<AtomicBool>,
+  mut watched: Watched,
+) {
+  let changed = Rc::new(Cell::new(false));
+
+  {
+    let changed = changed.clone();
+
+    context.set_subscribe_callback(Some(Box::new(move |_, _, _| changed.set(true))));
+  }
+
+  context.subscribe(
+    InterestMaskSet::SINK | InterestMaskSet::SOURCE | InterestMaskSet::SERVER,
+    |_| {},
+  );
+
+  while running.load(Ordering::Acquire) {
+    let iterated = mainloop
+      .prepare(Some(IDLE_TIMEOUT))
+      .and_then(|_| mainloop.poll())
+      .and_then(|_| mainloop.dispatch());
+
+    if let Err(err) = iterated {
+      watched
+        .reporter
+        .warn(format!("Stopped watching devices: {err}"));
+      break;
+    }
+
+    // One snapshot for however many events came in since the last one
+    if !changed.replace(false) {
+      continue;
+    }
+
+    let (new_devices, new_default) = match snapshot() {
+      Ok(snapshot) => snapshot,
+      Err(err) => {
+        watched.reporter.warn(err);
+        continue;
+      }
+    };
+
+    let changes = events::changes(
+      (&devices, default.as_ref()),
+      (&new_devices, new_default.as_ref()),
+    );
+
+    for event in changes {
+      watched.events.send(event.clone());
+      watched.reporter.report(Status::Device(event));
+    }
+
+    let follow = &watched.follow;
+    let following =
+      follow.enabled.load(Ordering::Acquire) && !follow.paused.load(Ordering::Acquire);
+
+    if let Some(new) = new_default
+      .as_ref()
+      .filter(|new| Some(*new) != default.as_ref())
+    {
+      if following {
+        match (watched.route)(new) {
+          Ok(()) => *follow.followed.lock().unwrap() = Some(new.clone()),
+          Err(err) => watched
+            .reporter
+            .warn(format!("Couldn't follow {new}: {err}")),
+        }
+      }
+    }
+
+    devices = new_devices;
+    default = new_default;
+  }
+
+  context.disconnect();
+}
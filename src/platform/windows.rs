@@ -1,34 +1,210 @@
 #![cfg(windows)]
 
-use std::{cell::RefCell, collections::HashMap};
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  sync::{Arc, Mutex},
+};
 
 use cpal::{
-  BufferSize,
-  Host, HostId, SampleFormat, SampleRate, Stream, StreamConfig, traits::{DeviceTrait, HostTrait, StreamTrait},
+  traits::{DeviceTrait, HostTrait},
+  BufferSize, Host, HostId, SampleRate, StreamConfig, SupportedStreamConfig,
+  SupportedStreamConfigRange,
+};
+
+use windows::{
+  core::PWSTR,
+  Win32::{
+    Devices::FunctionDiscovery::PKEY_Device_FriendlyName,
+    Media::Audio::{
+      eAll, eConsole, eRender, IMMDeviceEnumerator, MMDeviceEnumerator, DEVICE_STATE_ACTIVE,
+    },
+    System::Com::{
+      CoCreateInstance, CoInitializeEx, CoTaskMemFree, StructuredStorage::PropVariantClear,
+      CLSCTX_ALL, COINIT_APARTMENTTHREADED, STGM_READ,
+    },
+    UI::Shell::PropertiesSystem::PropVariantToStringAlloc,
+  },
 };
 
-use crate::{Device, Error, Listener, Result};
+use super::{
+  input::{build_input_stream, StreamHandle},
+  supervisor::Restart,
+  Backend,
+};
+use crate::{Device, DeviceKind, Error, Listener, Result, SampleFormat};
 
 pub struct WindowsHost {
   pub host: Host,
   pub devices: Vec<Device>,
-  pub native_devices: HashMap<Device, cpal::Device>,
+  /// Native device of every device by its id
+  pub native_devices: HashMap<String, cpal::Device>,
   pub listener: Listener,
   pub current_device_index: RefCell<Option<usize>>,
-  pub stream: RefCell<Option<Stream>>,
+  /// Shared with the restarter, which rebuilds it
+  pub(crate) stream: Arc<Mutex<Option<StreamHandle>>>,
+  /// Device the restarter moved to, picked up by [WindowsHost::current_device_index]
+  restarted: Arc<Mutex<Option<Device>>>,
 }
 
-fn filter_device(device: cpal::Device) -> Option<(cpal::Device, Device)> {
-  let name = device.name().ok()?;
-
+/// Output devices are captured through loopback, so they only have an output config
+fn supported_config(device: &cpal::Device) -> Result<SupportedStreamConfig> {
   let input = device.default_input_config();
   let output = device.default_output_config();
-  let supported = input.or(output).ok()?;
 
-  if !(supported.channels() == 2 && supported.sample_format() == SampleFormat::F32) {
-    return None;
+  Ok(input.or(output)?)
+}
+
+/// Builds and plays a stream of `native` with the config of `device` on a thread of its own
+fn device_stream(
+  native: &cpal::Device,
+  device: &Device,
+  listener: &Listener,
+) -> Result<StreamHandle> {
+  let native = native.clone();
+  let (sample_rate, buffer_size) = (device.sample_rate, device.buffer_size);
+  let listener = listener.clone();
+
+  StreamHandle::spawn(move || {
+    let supported = supported_config(&native)?;
+    let config = StreamConfig {
+      channels: supported.channels(),
+      sample_rate: SampleRate(sample_rate),
+      buffer_size: buffer_size
+        .map(BufferSize::Fixed)
+        .unwrap_or(BufferSize::Default),
+    };
+
+    build_input_stream(&native, &config, supported.sample_format(), &listener)
+  })
+}
+
+/// Active endpoint, cpal only gives the name of its devices
+#[derive(PartialEq)]
+struct Endpoint {
+  id: String,
+  name: String,
+}
+
+/// Times the devices are listed again when they change while they're listed
+const ATTEMPTS: usize = 3;
+
+/// Copies a string allocated by COM and frees it
+unsafe fn take_string(value: PWSTR) -> String {
+  let string = String::from_utf16_lossy(value.as_wide());
+
+  CoTaskMemFree(Some(value.as_ptr() as _));
+  string
+}
+
+/// Lists the active endpoints in the order cpal lists its devices,
+/// along with the endpoint ID of the default output device
+fn endpoints() -> Result<(Vec<Endpoint>, String)> {
+  let mut endpoints = Vec::new();
+
+  unsafe {
+    // Already initialized by cpal on its own threads, this one stays initialized like those
+    let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+
+    let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+    let collection = enumerator.EnumAudioEndpoints(eAll, DEVICE_STATE_ACTIVE)?;
+
+    for index in 0..collection.GetCount()? {
+      let endpoint = collection.Item(index)?;
+      let mut value = endpoint
+        .OpenPropertyStore(STGM_READ)?
+        .GetValue(&PKEY_Device_FriendlyName)?;
+      let name = PropVariantToStringAlloc(&value);
+
+      PropVariantClear(&mut value)?;
+
+      endpoints.push(Endpoint {
+        id: take_string(endpoint.GetId()?),
+        name: take_string(name?),
+      });
+    }
+
+    let default = enumerator
+      .GetDefaultAudioEndpoint(eRender, eConsole)
+      .map_err(|_| Error::NoDefaultDeviceFound)?;
+
+    Ok((endpoints, take_string(default.GetId()?)))
+  }
+}
+
+/// Pairs every native device with the endpoint ID of its endpoint, along with the one of the default output device
+///
+/// cpal enumerates the same collection in the same order, so they're paired by position.
+/// The endpoints are listed again around it, if a device was added or removed in between everything is listed again
+fn native_devices(host: &Host) -> Result<(Vec<(String, cpal::Device)>, String)> {
+  for _ in 0..ATTEMPTS {
+    let (endpoints, default) = endpoints()?;
+    let natives = host.devices()?.collect::<Vec<_>>();
+    let unchanged = endpoints()?.0 == endpoints
+      && natives.len() == endpoints.len()
+      && natives
+        .iter()
+        .zip(&endpoints)
+        .all(|(native, endpoint)| native.name().is_ok_and(|name| name == endpoint.name));
+
+    if unchanged {
+      let ids = endpoints.into_iter().map(|endpoint| endpoint.id);
+
+      return Ok((ids.zip(natives).collect(), default));
+    }
   }
 
+  Err(Error::DevicesChanged)
+}
+
+/// Common sample rates listed for devices that support a range of them
+const SAMPLE_RATES: [u32; 11] = [
+  8000, 11025, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000,
+];
+
+/// Gets the common sample rates within `ranges`, along with their bounds
+fn sample_rates(ranges: &[SupportedStreamConfigRange], native: u32) -> Vec<u32> {
+  let mut rates = ranges
+    .iter()
+    .flat_map(|range| {
+      let (min, max) = (range.min_sample_rate().0, range.max_sample_rate().0);
+
+      SAMPLE_RATES
+        .into_iter()
+        .filter(move |rate| (min..=max).contains(rate))
+        .chain([min, max])
+    })
+    .chain([native])
+    .collect::<Vec<_>>();
+
+  rates.sort_unstable();
+  rates.dedup();
+  rates
+}
+
+fn filter_device(device: cpal::Device, id: String) -> Option<(cpal::Device, Device)> {
+  let name = device.name().ok()?;
+
+  // Same order as [supported_config], outputs only have output configs
+  let (kind, ranges): (_, Vec<_>) = match device.default_input_config() {
+    Ok(_) => (
+      DeviceKind::Input,
+      device.supported_input_configs().ok()?.collect(),
+    ),
+    Err(_) => (
+      DeviceKind::OutputMonitor,
+      device.supported_output_configs().ok()?.collect(),
+    ),
+  };
+  let mut formats = ranges
+    .iter()
+    .filter_map(|range| SampleFormat::try_from(range.sample_format()).ok())
+    .collect::<Vec<_>>();
+
+  formats.sort_unstable();
+  formats.dedup();
+
+  let supported = supported_config(&device).ok()?;
   let config = supported.config();
   let sample_rate = config.sample_rate.0;
   let buffer_size = match config.buffer_size {
@@ -39,20 +215,27 @@ fn filter_device(device: cpal::Device) -> Option<(cpal::Device, Device)> {
   Some((
     device,
     Device {
+      id,
       name,
+      kind,
+      channels: Some(config.channels),
+      sample_rates: sample_rates(&ranges, sample_rate),
+      formats,
+      backend: "WASAPI",
+      // Loopback streams only call back while something plays
+      stops_when_silent: kind == DeviceKind::OutputMonitor,
       sample_rate,
       buffer_size,
+      ..Default::default()
     },
   ))
 }
 
-fn filtered_devices(host: &Host) -> Result<(HashMap<Device, cpal::Device>, Vec<Device>)> {
-  let default = host
-    .default_output_device()
-    .ok_or(Error::NoDefaultDeviceFound)?
-    .name()?;
-
-  let filtered = host.devices()?.filter_map(filter_device);
+fn filtered_devices(host: &Host) -> Result<(HashMap<String, cpal::Device>, Vec<Device>)> {
+  let (natives, default) = native_devices(host)?;
+  let filtered = natives
+    .into_iter()
+    .filter_map(|(id, device)| filter_device(device, id));
   let (lower, upper) = filtered.size_hint();
   let size = upper.unwrap_or(lower);
   let mut native_devices = HashMap::with_capacity(size);
@@ -60,13 +243,14 @@ fn filtered_devices(host: &Host) -> Result<(HashMap<Device, cpal::Device>, Vec<D
 
   let mut default_index = 0;
 
-  for (index, (native, device)) in filtered.enumerate() {
-    if device.name == default {
+  for (index, (native, mut device)) in filtered.enumerate() {
+    if device.id == default {
+      device.default = true;
       default_index = index;
     }
 
-    devices.push(device.clone());
-    native_devices.insert(device, native);
+    native_devices.insert(device.id.clone(), native);
+    devices.push(device);
   }
 
   if !devices.is_empty() {
@@ -88,11 +272,16 @@ impl WindowsHost {
       native_devices,
       listener,
       current_device_index: RefCell::new(None),
-      stream: RefCell::new(None),
+      stream: Arc::default(),
+      restarted: Arc::default(),
     })
   }
 
   pub fn current_device_index(&self) -> Option<usize> {
+    if let Some(device) = self.restarted.lock().unwrap().take() {
+      *self.current_device_index.borrow_mut() = self._get_device_index(&device);
+    }
+
     *self.current_device_index.borrow()
   }
 
@@ -113,31 +302,20 @@ impl WindowsHost {
   fn _change_stream(&self, device: &Device) -> Result<()> {
     let native = self
       .native_devices
-      .get(device)
+      .get(&device.id)
       .ok_or_else(|| Error::NoDeviceFound(device.name.to_owned()))?;
 
-    let config = StreamConfig {
-      channels: 2,
-      sample_rate: SampleRate(device.sample_rate),
-      buffer_size: device
-        .buffer_size
-        .map(BufferSize::Fixed)
-        .unwrap_or(BufferSize::Default),
-    };
-
-    let data_cb = self.listener.callback().get();
-    let err_cb = |err| eprintln!("{err}");
-    let stream = native.build_input_stream(&config, data_cb, err_cb, None)?;
-
-    stream.play()?;
+    let mut stream = self.stream.lock().unwrap();
 
-    *self.stream.borrow_mut() = Some(stream);
+    // Stops the old stream first so two never feed the listener at once
+    stream.take();
+    *stream = Some(device_stream(native, device, &self.listener)?);
 
     Ok(())
   }
 
   fn _get_device_index(&self, device: &Device) -> Option<usize> {
-    self.devices.iter().position(|dev| dev == device)
+    self.devices.iter().position(|dev| dev.id == device.id)
   }
 
   pub fn change_device_by_index(&self, index: usize) -> Result<()> {
@@ -151,7 +329,7 @@ impl WindowsHost {
   }
 
   pub fn change_device(&self, device: &Device) -> Result<()> {
-    if self.stream.borrow().is_some() {
+    if self.stream.lock().unwrap().is_some() {
       self._change_stream(device)?;
     }
 
@@ -179,26 +357,92 @@ impl WindowsHost {
     Ok(())
   }
 
-  pub fn refresh(&mut self) -> Result<()> {
-    let (native_devices, devices) = filtered_devices(&self.host)?;
-
-    let current = self
-      .current_device_index()
-      .and_then(|index| self.devices.get(index));
+  pub fn stop(&mut self) -> Result<()> {
+    self.stream.lock().unwrap().take();
 
-    let same_index = self
-      .current_device_index()
-      .and_then(|index| devices.get(index));
+    Ok(())
+  }
 
-    if current != same_index {
-      if let Some(current) = current {
-        *self.current_device_index.borrow_mut() = self._get_device_index(current)
-      }
-    }
+  pub fn refresh(&mut self) -> Result<()> {
+    let (native_devices, devices) = filtered_devices(&self.host)?;
+    let current = self.current_device().cloned();
 
     self.devices = devices;
     self.native_devices = native_devices;
 
+    // Follows the current device to its new index, it keeps the old one if the device is gone
+    if let Some(index) = current.and_then(|current| self._get_device_index(&current)) {
+      *self.current_device_index.borrow_mut() = Some(index);
+    }
+
     Ok(())
   }
+
+  /// Rebuilds the stream of the device, with the devices as they were listed when it started supervising
+  pub(crate) fn restarter(&self) -> Result<Restart> {
+    let native_devices = self.native_devices.clone();
+    let listener = self.listener.clone();
+    let stream = self.stream.clone();
+    let restarted = self.restarted.clone();
+
+    Ok(Box::new(move |device| {
+      let native = native_devices
+        .get(&device.id)
+        .ok_or_else(|| Error::NoDeviceFound(device.name.to_owned()))?;
+      let mut stream = stream.lock().unwrap();
+
+      // Stops the old stream first so two never feed the listener at once
+      stream.take();
+      *stream = Some(device_stream(native, device, &listener)?);
+      *restarted.lock().unwrap() = Some(device.clone());
+
+      Ok(())
+    }))
+  }
+}
+
+impl Backend for WindowsHost {
+  fn listener(&self) -> &Listener {
+    &self.listener
+  }
+
+  fn current_device_index(&self) -> Option<usize> {
+    WindowsHost::current_device_index(self)
+  }
+
+  fn current_device(&self) -> Option<&Device> {
+    WindowsHost::current_device(self)
+  }
+
+  fn default_device(&self) -> Result<&Device> {
+    WindowsHost::default_device(self)
+  }
+
+  fn devices(&self) -> &Vec<Device> {
+    WindowsHost::devices(self)
+  }
+
+  fn change_device_by_index(&self, index: usize) -> Result<()> {
+    WindowsHost::change_device_by_index(self, index)
+  }
+
+  fn change_device(&self, device: &Device) -> Result<()> {
+    WindowsHost::change_device(self, device)
+  }
+
+  fn listen(&mut self) -> Result<()> {
+    WindowsHost::listen(self)
+  }
+
+  fn stop(&mut self) -> Result<()> {
+    WindowsHost::stop(self)
+  }
+
+  fn refresh(&mut self) -> Result<()> {
+    WindowsHost::refresh(self)
+  }
+
+  fn restarter(&self) -> Result<Restart> {
+    WindowsHost::restarter(self)
+  }
 }
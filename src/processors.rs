@@ -0,0 +1,240 @@
+use std::f32::consts::{PI, TAU};
+
+use crate::{BlockInfo, Processor, FFT};
+
+/// Mixes interleaved channels down to one channel
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MixChannels;
+
+impl Processor for MixChannels {
+  type Input = Vec<f32>;
+  type Output = Vec<f32>;
+
+  fn process(&mut self, input: &Vec<f32>, output: &mut Vec<f32>, info: &mut BlockInfo) {
+    let channels = info.channels.max(1) as usize;
+
+    output.clear();
+    output.extend(
+      input
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32),
+    );
+
+    info.channels = 1;
+  }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FilterKind {
+  LowPass,
+  HighPass,
+}
+
+/// One pole low or high pass filter, keeps its state for every channel between blocks
+#[derive(Debug, Clone)]
+pub struct Filter {
+  kind: FilterKind,
+  cutoff: f32,
+  state: Vec<f32>,
+}
+
+impl Filter {
+  pub fn new(kind: FilterKind, cutoff: f32) -> Self {
+    Self {
+      kind,
+      cutoff,
+      state: Vec::new(),
+    }
+  }
+
+  pub fn low_pass(cutoff: f32) -> Self {
+    Self::new(FilterKind::LowPass, cutoff)
+  }
+
+  pub fn high_pass(cutoff: f32) -> Self {
+    Self::new(FilterKind::HighPass, cutoff)
+  }
+}
+
+impl Processor for Filter {
+  type Input = Vec<f32>;
+  type Output = Vec<f32>;
+
+  fn process(&mut self, input: &Vec<f32>, output: &mut Vec<f32>, info: &mut BlockInfo) {
+    let channels = info.channels.max(1) as usize;
+    let alpha = 1. - (-TAU * self.cutoff / info.sample_rate.max(1) as f32).exp();
+
+    self.state.resize(channels, 0.);
+    output.clear();
+
+    for frame in input.chunks(channels) {
+      for (sample, state) in frame.iter().zip(&mut self.state) {
+        *state += alpha * (sample - *state);
+
+        output.push(match self.kind {
+          FilterKind::LowPass => *state,
+          FilterKind::HighPass => sample - *state,
+        });
+      }
+    }
+  }
+}
+
+/// Multiplies the samples by a Hann window, expects one channel
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HannWindow;
+
+impl Processor for HannWindow {
+  type Input = Vec<f32>;
+  type Output = Vec<f32>;
+
+  fn process(&mut self, input: &Vec<f32>, output: &mut Vec<f32>, _: &mut BlockInfo) {
+    let len = input.len().saturating_sub(1).max(1) as f32;
+
+    output.clear();
+    output.extend(
+      input
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| sample * (0.5 - 0.5 * (2. * PI * i as f32 / len).cos())),
+    );
+  }
+}
+
+/// Runs [FFT] over the samples, expects one channel
+#[derive(Debug)]
+pub struct Spectrum {
+  fft: FFT,
+  size: usize,
+}
+
+impl Spectrum {
+  /// `size` is clamped to `1..=`[FFT::MAX_SIZE],
+  /// the buffers for it are allocated here instead of in the audio callback
+  pub fn new(size: usize) -> Self {
+    let size = size.clamp(1, <FFT>::MAX_SIZE);
+    let mut fft = FFT::default();
+
+    fft.process(&[], size);
+
+    Self { fft, size }
+  }
+
+  pub fn size(&self) -> usize {
+    self.size
+  }
+}
+
+impl Processor for Spectrum {
+  type Input = Vec<f32>;
+  type Output = Vec<f32>;
+
+  fn process(&mut self, input: &Vec<f32>, output: &mut Vec<f32>, _: &mut BlockInfo) {
+    let spectrum = self.fft.process(input, self.size);
+
+    output.clear();
+    output.extend_from_slice(spectrum);
+  }
+}
+
+/// Groups a spectrum into logarithmically spaced bands,
+/// using the lower half since the upper half mirrors it
+#[derive(Debug, Clone, Copy)]
+pub struct Bands {
+  bands: usize,
+}
+
+impl Bands {
+  pub fn new(bands: usize) -> Self {
+    Self { bands }
+  }
+}
+
+impl Processor for Bands {
+  type Input = Vec<f32>;
+  type Output = Vec<f32>;
+
+  fn process(&mut self, input: &Vec<f32>, output: &mut Vec<f32>, _: &mut BlockInfo) {
+    let bins = (input.len() / 2).max(1) as f32;
+
+    output.clear();
+    output.extend((0..self.bands).map(|band| {
+      let start = bins.powf(band as f32 / self.bands as f32) as usize;
+      let end = (bins.powf((band + 1) as f32 / self.bands as f32) as usize).max(start + 1);
+      let bins = &input[start.min(input.len())..end.min(input.len())];
+
+      bins.iter().map(|bin| bin.abs()).sum::<f32>() / bins.len().max(1) as f32
+    }));
+  }
+}
+
+/// Exponentially smooths every value with the values of the previous block,
+/// `factor` being how much of the previous value is kept
+#[derive(Debug, Clone, Copy)]
+pub struct Smooth {
+  factor: f32,
+}
+
+impl Smooth {
+  pub fn new(factor: f32) -> Self {
+    Self {
+      factor: factor.clamp(0., 1.),
+    }
+  }
+}
+
+impl Processor for Smooth {
+  type Input = Vec<f32>;
+  type Output = Vec<f32>;
+
+  fn process(&mut self, input: &Vec<f32>, output: &mut Vec<f32>, _: &mut BlockInfo) {
+    output.resize(input.len(), 0.);
+
+    for (output, input) in output.iter_mut().zip(input) {
+      *output = *output * self.factor + input * (1. - self.factor);
+    }
+  }
+}
+
+/// Scales values to `0..=1` by the highest value seen,
+/// which falls back by `decay` every block so quiet parts don't stay quiet
+#[derive(Debug, Clone, Copy)]
+pub struct Normalize {
+  decay: f32,
+  peak: f32,
+}
+
+impl Normalize {
+  pub fn new(decay: f32) -> Self {
+    Self {
+      decay: decay.clamp(0., 1.),
+      peak: 0.,
+    }
+  }
+}
+
+impl Default for Normalize {
+  fn default() -> Self {
+    Self::new(0.99)
+  }
+}
+
+impl Processor for Normalize {
+  type Input = Vec<f32>;
+  type Output = Vec<f32>;
+
+  fn process(&mut self, input: &Vec<f32>, output: &mut Vec<f32>, _: &mut BlockInfo) {
+    let max = input.iter().fold(0f32, |max, value| max.max(value.abs()));
+
+    self.peak = (self.peak * self.decay).max(max);
+
+    let scale = if self.peak > f32::EPSILON {
+      1. / self.peak
+    } else {
+      0.
+    };
+
+    output.clear();
+    output.extend(input.iter().map(|value| value * scale));
+  }
+}
@@ -0,0 +1,423 @@
+#![cfg(feature = "record")]
+
+use std::{
+  collections::VecDeque,
+  fmt::{Debug, Formatter},
+  fs::File,
+  io::BufWriter,
+  path::{Path, PathBuf},
+  sync::{mpsc, Arc, Mutex, Weak},
+  thread,
+  time::Duration,
+};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::{Block, BlockInfo, BlockReceiver, Error, Listener, Result};
+
+/// Blocks the recorder thread can fall behind by before they're dropped
+const CAPACITY: usize = 256;
+
+/// How often the recorder thread checks if every [Recorder] was dropped
+const IDLE_TIMEOUT: Duration = Duration::from_millis(100);
+
+type Writer = WavWriter<BufWriter<File>>;
+
+/// WAV file written by a [Recorder]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+  pub path: PathBuf,
+  pub sample_rate: u32,
+  pub channels: u16,
+  pub frames: u64,
+  /// Frames dropped because the recorder fell behind, written as silence so the timing stays right
+  pub dropped: u64,
+}
+
+impl Recording {
+  pub fn duration(&self) -> Duration {
+    Duration::from_secs_f64(self.frames as f64 / self.sample_rate.max(1) as f64)
+  }
+}
+
+/// File that's being recorded to, its writer is created once the format of the first block is known
+struct Active {
+  path: PathBuf,
+  file: Option<File>,
+  writer: Option<Writer>,
+  spec: WavSpec,
+  frames: u64,
+  dropped: u64,
+  /// Error from writing a block, returned by [Recorder::stop]
+  error: Option<Error>,
+}
+
+impl Active {
+  /// Writes a block that comes `gap` frames after the last one
+  fn write(&mut self, data: &[f32], info: &BlockInfo, gap: u64) {
+    if self.error.is_some() {
+      return;
+    }
+
+    let spec = spec(info);
+
+    // Stops writing if the device changed format, since a WAV file can only have one
+    if self.writer.is_some() && spec != self.spec {
+      self.error = Some(Error::FormatChanged(spec.sample_rate, spec.channels));
+      return;
+    }
+
+    if let Some(file) = self.file.take() {
+      self.spec = spec;
+
+      match WavWriter::new(BufWriter::new(file), spec) {
+        Ok(writer) => self.writer = Some(writer),
+        Err(err) => self.error = Some(err.into()),
+      }
+    }
+
+    let Some(writer) = &mut self.writer else {
+      return;
+    };
+
+    // Blocks dropped before the first written block weren't part of the recording
+    let gap = if self.frames > 0 { gap } else { 0 };
+    let silence = std::iter::repeat_n(0., gap as usize * spec.channels.max(1) as usize);
+
+    if let Err(err) = silence
+      .chain(data.iter().copied())
+      .try_for_each(|sample| writer.write_sample(sample))
+    {
+      self.error = Some(err.into());
+      return;
+    }
+
+    self.frames += gap + (data.len() / spec.channels.max(1) as usize) as u64;
+    self.dropped += gap;
+  }
+
+  fn finish(mut self) -> Result<Recording> {
+    if let Some(err) = self.error.take() {
+      return Err(err);
+    }
+
+    match (self.writer, self.file) {
+      (Some(writer), _) => writer.finalize()?,
+      // Nothing was captured, still leaves a valid empty file
+      (None, Some(file)) => WavWriter::new(BufWriter::new(file), self.spec)?.finalize()?,
+      (None, None) => {}
+    }
+
+    Ok(Recording {
+      path: self.path,
+      sample_rate: self.spec.sample_rate,
+      channels: self.spec.channels,
+      frames: self.frames,
+      dropped: self.dropped,
+    })
+  }
+}
+
+/// Last captured samples, up to the duration a [Recorder] was created with
+struct History {
+  duration: Duration,
+  samples: VecDeque<f32>,
+  spec: WavSpec,
+}
+
+impl History {
+  /// Adds a block that comes `gap` frames after the last one, filling the gap with silence
+  fn push(&mut self, data: &[f32], info: &BlockInfo, gap: u64) {
+    let spec = spec(info);
+
+    if spec != self.spec {
+      self.spec = spec;
+      self.samples.clear();
+    }
+
+    let channels = spec.channels.max(1) as usize;
+    let len = (self.duration.as_secs_f64() * spec.sample_rate as f64) as usize * channels;
+    let silence = (gap as usize * channels).min(len);
+    let data = &data[data.len().saturating_sub(len)..];
+    let overflow = (self.samples.len() + silence + data.len()).saturating_sub(len);
+
+    self.samples.drain(..overflow.min(self.samples.len()));
+    self.samples.extend(std::iter::repeat_n(0., silence));
+    self.samples.extend(data);
+
+    // Only happens if the silence and the block are both longer than the history
+    let overflow = self.samples.len().saturating_sub(len);
+
+    self.samples.drain(..overflow);
+  }
+}
+
+/// Start or end of a recording, applied once every block queued before it was written
+/// so blocks still waiting in the queue end up in the right file
+struct Cut {
+  /// Blocks queued before it, see [BlockReceiver::pushed]
+  mark: u64,
+  /// Recording that starts at it, `None` to only end the current one
+  next: Option<Active>,
+  /// Gets the recording that ended at it
+  done: mpsc::Sender<Result<Option<Recording>>>,
+}
+
+struct State {
+  active: Option<Active>,
+  history: History,
+  /// Format of the last block, used for files that didn't get any samples
+  spec: WavSpec,
+  /// Position right after the last block
+  end: Option<u64>,
+  /// Blocks taken from the receiver so far
+  received: u64,
+  /// Cuts waiting for the blocks before them, in the order they were made
+  cuts: VecDeque<Cut>,
+  /// Set once the recorder thread stopped, cuts don't wait for blocks anymore then
+  stopped: bool,
+}
+
+impl State {
+  /// Applies every cut whose blocks were all written
+  fn cut(&mut self) {
+    while let Some(mut cut) = self.cuts.pop_front() {
+      if !self.stopped && cut.mark > self.received {
+        self.cuts.push_front(cut);
+        break;
+      }
+
+      if let Some(next) = &mut cut.next {
+        next.spec = self.spec;
+      }
+
+      let previous = std::mem::replace(&mut self.active, cut.next);
+      let _ = cut.done.send(previous.map(Active::finish).transpose());
+    }
+  }
+}
+
+struct RecorderShared {
+  state: Mutex<State>,
+  receiver: BlockReceiver,
+}
+
+/// Records captured blocks to WAV files at the rate and channel count of the device,
+/// blocks are written by a separate thread so the audio callback never touches the disk
+///
+/// Also keeps the last few seconds that were captured, so they can be saved after the fact
+/// with [Self::save_history]
+#[derive(Clone)]
+pub struct Recorder {
+  shared: Arc<RecorderShared>,
+}
+
+impl Recorder {
+  pub(crate) fn new(listener: &Listener, history: Option<Duration>) -> Result<Self> {
+    let spec = spec(&BlockInfo::default());
+    let shared = Arc::new(RecorderShared {
+      state: Mutex::new(State {
+        active: None,
+        history: History {
+          duration: history.unwrap_or_default(),
+          samples: VecDeque::new(),
+          spec,
+        },
+        spec,
+        end: None,
+        received: 0,
+        cuts: VecDeque::new(),
+        stopped: false,
+      }),
+      receiver: listener.subscribe(CAPACITY),
+    });
+
+    let weak = Arc::downgrade(&shared);
+
+    thread::Builder::new()
+      .name(String::from("safav-record"))
+      .spawn(move || run(weak))?;
+
+    Ok(Self { shared })
+  }
+
+  fn state<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+    f(&mut self.shared.state.lock().unwrap())
+  }
+
+  /// Ends the current recording after the blocks captured so far and starts `next` with the blocks after them,
+  /// waits until the recorder thread wrote the blocks before it
+  fn cut(&self, next: Option<Active>) -> Result<Option<Recording>> {
+    let (done, recording) = mpsc::channel();
+
+    self.state(|state| {
+      state.cuts.push_back(Cut {
+        mark: self.shared.receiver.pushed(),
+        next,
+        done,
+      });
+      // Applied right away if the recorder thread already wrote every block
+      state.cut();
+    });
+
+    // Every cut gets applied, by the recorder thread at the latest once it stops
+    recording.recv().unwrap()
+  }
+
+  /// Starts recording every block captured from now on to `path`,
+  /// ending the current recording first
+  ///
+  /// Blocks captured before it that the recorder thread didn't get to yet still go to the previous recording
+  pub fn start(&self, path: impl AsRef<Path>) -> Result<Option<Recording>> {
+    let path = path.as_ref().to_path_buf();
+    let file = File::create(&path)?;
+
+    self.cut(Some(Active {
+      path,
+      file: Some(file),
+      writer: None,
+      spec: spec(&BlockInfo::default()),
+      frames: 0,
+      dropped: 0,
+      error: None,
+    }))
+  }
+
+  /// Stops recording and finishes the file once every block captured before it is written,
+  /// `None` if it wasn't recording
+  ///
+  /// Fails if the device changed its rate or channels while recording,
+  /// the file keeps everything before the change
+  pub fn stop(&self) -> Result<Option<Recording>> {
+    self.cut(None)
+  }
+
+  pub fn is_recording(&self) -> bool {
+    self.state(|state| state.active.is_some())
+  }
+
+  /// Gets how long the current recording is
+  pub fn recorded(&self) -> Duration {
+    self.state(|state| match &state.active {
+      Some(active) => {
+        Duration::from_secs_f64(active.frames as f64 / active.spec.sample_rate.max(1) as f64)
+      }
+      None => Duration::ZERO,
+    })
+  }
+
+  /// Gets how much of the history is filled
+  pub fn history(&self) -> Duration {
+    self.state(|state| {
+      let history = &state.history;
+      let frames = history.samples.len() / history.spec.channels.max(1) as usize;
+
+      Duration::from_secs_f64(frames as f64 / history.spec.sample_rate.max(1) as f64)
+    })
+  }
+
+  /// Writes the last captured samples to `path`, see [Host::create_recorder](crate::Host::create_recorder)
+  pub fn save_history(&self, path: impl AsRef<Path>) -> Result<Recording> {
+    let path = path.as_ref().to_path_buf();
+    // Copies the samples so the recorder thread isn't blocked while writing them
+    let (samples, spec) = self.state(|state| {
+      let history = &state.history;
+
+      (
+        history.samples.iter().copied().collect::<Vec<_>>(),
+        history.spec,
+      )
+    });
+
+    let mut writer = WavWriter::create(&path, spec)?;
+
+    for sample in &samples {
+      writer.write_sample(*sample)?;
+    }
+
+    writer.finalize()?;
+
+    Ok(Recording {
+      path,
+      sample_rate: spec.sample_rate,
+      channels: spec.channels,
+      frames: (samples.len() / spec.channels.max(1) as usize) as u64,
+      dropped: 0,
+    })
+  }
+}
+
+impl Debug for Recorder {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Recorder")
+      .field("recording", &self.is_recording())
+      .field("recorded", &self.recorded())
+      .field("history", &self.history())
+      .finish()
+  }
+}
+
+fn spec(info: &BlockInfo) -> WavSpec {
+  WavSpec {
+    channels: info.channels,
+    sample_rate: info.sample_rate,
+    bits_per_sample: 32,
+    sample_format: SampleFormat::Float,
+  }
+}
+
+/// Writes blocks until every [Recorder] was dropped or the receiver was removed
+fn run(shared: Weak<RecorderShared>) {
+  let mut block = Block::default();
+
+  while let Some(shared) = shared.upgrade() {
+    let receiver = &shared.receiver;
+
+    if receiver.is_closed() && receiver.is_empty() {
+      let mut state = shared.state.lock().unwrap();
+
+      state.stopped = true;
+      state.cut();
+      break;
+    }
+
+    if !receiver.recv_timeout_into(&mut block, IDLE_TIMEOUT) {
+      continue;
+    }
+
+    let mut state = shared.state.lock().unwrap();
+    let gap = gap(&block, state.end);
+
+    state.spec = spec(&block.info);
+    state.end = Some(block.info.position + block.frames() as u64);
+    state.history.push(&block.samples, &block.info, gap);
+
+    if let Some(active) = &mut state.active {
+      active.write(&block.samples, &block.info, gap);
+    }
+
+    state.received += 1;
+    state.cut();
+  }
+}
+
+/// Gets how many frames were dropped right before `block`,
+/// gaps without overruns are the stream restarting or being paused and aren't filled
+fn gap(block: &Block, end: Option<u64>) -> u64 {
+  if block.overruns == 0 {
+    return 0;
+  }
+
+  match end.and_then(|end| block.info.position.checked_sub(end)) {
+    Some(gap) if gap > 0 => gap,
+    // The position started over, so the dropped blocks are assumed to be as long as this one
+    _ => block.overruns * block.frames() as u64,
+  }
+}
+
+impl Listener {
+  /// Creates a [Recorder] that keeps the last `history` of captured samples
+  pub fn create_recorder(&self, history: Option<Duration>) -> Result<Recorder> {
+    Recorder::new(self, history)
+  }
+}
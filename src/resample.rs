@@ -0,0 +1,207 @@
+use std::f64::consts::PI;
+
+use crate::{BlockInfo, Processor};
+
+/// Phases of the sinc kernel that are computed ahead of time,
+/// phases between them are interpolated
+const SINC_PHASES: usize = 256;
+
+/// How [Resample] computes samples between the samples of the device
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Interpolation {
+  /// Cheapest, but loses some of the higher frequencies and doesn't filter out aliasing
+  Linear,
+  /// Windowed sinc using `depth` samples on each side,
+  /// more accurate the higher it is but also slower
+  Sinc { depth: usize },
+}
+
+impl Default for Interpolation {
+  fn default() -> Self {
+    Self::Sinc { depth: 16 }
+  }
+}
+
+/// Weights given to the input frames around an output frame,
+/// for every phase between two input frames
+#[derive(Debug, Default)]
+struct Kernel {
+  taps: usize,
+  phases: usize,
+  /// `phases + 1` rows of `taps` weights
+  table: Vec<f32>,
+}
+
+impl Kernel {
+  /// `cutoff` is relative to half of the device rate
+  fn new(interpolation: Interpolation, cutoff: f64) -> Self {
+    let (depth, phases) = match interpolation {
+      Interpolation::Linear => (1, 1),
+      Interpolation::Sinc { depth } => (depth.max(1), SINC_PHASES),
+    };
+
+    let taps = depth * 2;
+    let mut table = Vec::with_capacity((phases + 1) * taps);
+
+    for phase in 0..=phases {
+      let x = phase as f64 / phases as f64;
+
+      // Distance of every tap to the output frame, which sits `x` after tap `depth - 1`
+      let distances = (0..taps).map(|tap| tap as f64 - (depth - 1) as f64 - x);
+
+      table.extend(distances.map(|t| match interpolation {
+        Interpolation::Linear => (1. - t.abs()).max(0.) as f32,
+        Interpolation::Sinc { .. } => {
+          let x = PI * cutoff * t;
+          let sinc = if x == 0. { 1. } else { x.sin() / x };
+
+          // Blackman window over the whole kernel
+          let w = (t / depth as f64).clamp(-1., 1.);
+          let window = 0.42 + 0.5 * (PI * w).cos() + 0.08 * (2. * PI * w).cos();
+
+          (cutoff * sinc * window) as f32
+        }
+      }));
+    }
+
+    Self {
+      taps,
+      phases,
+      table,
+    }
+  }
+
+  fn row(&self, phase: usize) -> &[f32] {
+    &self.table[phase * self.taps..(phase + 1) * self.taps]
+  }
+}
+
+/// Converts interleaved samples from the rate of the device to a fixed rate,
+/// so thresholds and band layouts don't shift with the device
+///
+/// Keeps its state between blocks, so blocks need to be given in order,
+/// gaps in [BlockInfo::position] or a change in rate or channels starts it over.
+/// [Interpolation::Sinc] also filters out frequencies above half of the lower rate
+#[derive(custom_debug::Debug)]
+pub struct Resample {
+  rate: u32,
+  interpolation: Interpolation,
+  #[debug(skip)]
+  kernel: Kernel,
+  /// Last `kernel.taps` input frames, oldest first
+  #[debug(skip)]
+  history: Vec<f32>,
+  channels: usize,
+  /// Rate of the device the kernel is set up for
+  source_rate: u32,
+  /// Where the next output frame is between the middle two frames of `history`
+  phase: f64,
+  /// Position right after the last input block
+  end: u64,
+  /// Output frames given so far
+  position: u64,
+}
+
+impl Resample {
+  /// Resamples to `rate` using [Interpolation::default]
+  pub fn new(rate: u32) -> Self {
+    Self::with_interpolation(rate, Interpolation::default())
+  }
+
+  pub fn with_interpolation(rate: u32, interpolation: Interpolation) -> Self {
+    Self {
+      rate: rate.max(1),
+      interpolation,
+      kernel: Kernel::default(),
+      history: Vec::new(),
+      channels: 0,
+      source_rate: 0,
+      phase: 0.,
+      end: 0,
+      position: 0,
+    }
+  }
+
+  /// Gets the rate samples are converted to
+  pub fn rate(&self) -> u32 {
+    self.rate
+  }
+
+  /// Gets how many frames at the device rate the output lags behind the input
+  pub fn latency(&self) -> usize {
+    match self.interpolation {
+      Interpolation::Linear => 1,
+      Interpolation::Sinc { depth } => depth.max(1),
+    }
+  }
+
+  /// Same as [Processor::process] but takes any slice
+  pub fn resample(&mut self, input: &[f32], output: &mut Vec<f32>, info: &mut BlockInfo) {
+    let channels = info.channels.max(1) as usize;
+
+    // Starts over if the stream changed or restarted
+    if channels != self.channels
+      || info.sample_rate != self.source_rate
+      || info.position != self.end
+    {
+      let cutoff = (self.rate as f64 / info.sample_rate.max(1) as f64).min(1.);
+
+      self.kernel = Kernel::new(self.interpolation, cutoff);
+      self.history.clear();
+      self.history.resize(self.kernel.taps * channels, 0.);
+      self.channels = channels;
+      self.source_rate = info.sample_rate;
+      self.phase = 0.;
+      self.position = info.position * self.rate as u64 / self.source_rate.max(1) as u64;
+    }
+
+    let ratio = self.source_rate.max(1) as f64 / self.rate as f64;
+
+    output.clear();
+
+    for frame in input.chunks_exact(channels) {
+      self.history.copy_within(channels.., 0);
+
+      let newest = self.history.len() - channels;
+      self.history[newest..].copy_from_slice(frame);
+
+      while self.phase < 1. {
+        let position = self.phase * self.kernel.phases as f64;
+        let index = (position as usize).min(self.kernel.phases - 1);
+        let fraction = (position - index as f64) as f32;
+        let (a, b) = (self.kernel.row(index), self.kernel.row(index + 1));
+
+        for channel in 0..channels {
+          let frames = self.history[channel..].iter().step_by(channels);
+
+          output.push(
+            frames
+              .zip(a.iter().zip(b))
+              .map(|(sample, (a, b))| sample * (a + (b - a) * fraction))
+              .sum(),
+          );
+        }
+
+        self.phase += ratio;
+      }
+
+      self.phase -= 1.;
+    }
+
+    self.end = info.position + (input.len() / channels) as u64;
+
+    info.sample_rate = self.rate;
+    info.position = self.position;
+
+    self.position += (output.len() / channels) as u64;
+  }
+}
+
+impl Processor for Resample {
+  type Input = Vec<f32>;
+  type Output = Vec<f32>;
+
+  fn process(&mut self, input: &Vec<f32>, output: &mut Vec<f32>, info: &mut BlockInfo) {
+    self.resample(input, output, info);
+  }
+}
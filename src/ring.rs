@@ -0,0 +1,93 @@
+use std::{
+  cell::UnsafeCell,
+  sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// Bounded lock-free single producer, single consumer queue,
+/// slots are reused so pushing into them doesn't have to allocate
+pub(crate) struct Ring<T> {
+  slots: Box<[UnsafeCell<T>]>,
+  head: AtomicUsize,
+  tail: AtomicUsize,
+  pushing: AtomicBool,
+  popping: AtomicBool,
+}
+
+// SAFETY: slots between `head` and `tail` are only touched by the consumer,
+// the rest only by the producer, each side is guarded by its own flag
+unsafe impl<T: Send> Send for Ring<T> {}
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Ring<T> {
+  pub fn new(capacity: usize, fill: impl FnMut() -> T) -> Self {
+    let capacity = capacity.max(1);
+
+    Self {
+      slots: std::iter::repeat_with(fill)
+        .take(capacity)
+        .map(UnsafeCell::new)
+        .collect(),
+      head: AtomicUsize::new(0),
+      tail: AtomicUsize::new(0),
+      pushing: AtomicBool::new(false),
+      popping: AtomicBool::new(false),
+    }
+  }
+
+  pub fn capacity(&self) -> usize {
+    self.slots.len()
+  }
+
+  pub fn len(&self) -> usize {
+    let tail = self.tail.load(Ordering::Acquire);
+    let head = self.head.load(Ordering::Acquire);
+
+    tail.wrapping_sub(head)
+  }
+
+  /// Fills the next free slot, returns `false` if it's full
+  /// or another push is in progress, never waits
+  pub fn push_with(&self, f: impl FnOnce(&mut T)) -> bool {
+    if self.pushing.swap(true, Ordering::Acquire) {
+      return false;
+    }
+
+    let tail = self.tail.load(Ordering::Relaxed);
+    let full = tail.wrapping_sub(self.head.load(Ordering::Acquire)) == self.capacity();
+
+    if !full {
+      // SAFETY: the consumer never touches slots past `tail`
+      f(unsafe { &mut *self.slots[tail % self.capacity()].get() });
+
+      self.tail.store(tail.wrapping_add(1), Ordering::Release);
+    }
+
+    self.pushing.store(false, Ordering::Release);
+
+    !full
+  }
+
+  /// Takes something out of the oldest slot, `None` only if it's empty,
+  /// waits for another pop in progress since this is never called by the audio callback
+  pub fn pop_with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+    while self.popping.swap(true, Ordering::Acquire) {
+      std::thread::yield_now();
+    }
+
+    let head = self.head.load(Ordering::Relaxed);
+    let empty = head == self.tail.load(Ordering::Acquire);
+
+    let value = (!empty).then(|| {
+      // SAFETY: the producer never touches slots between `head` and `tail`
+      let value = f(unsafe { &mut *self.slots[head % self.capacity()].get() });
+
+      self.head.store(head.wrapping_add(1), Ordering::Release);
+
+      value
+    });
+
+    self.popping.store(false, Ordering::Release);
+
+    value
+  }
+}
@@ -0,0 +1,203 @@
+#![cfg(feature = "stream")]
+
+use std::{
+  fmt::{Debug, Formatter},
+  pin::Pin,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  task::{Context, Poll},
+};
+
+use futures::{task::AtomicWaker, Stream};
+use thiserror::Error;
+
+use crate::{listener::AudioListenerTrait, ring::Ring, AudioData, BlockInfo, Listener, ListenerId};
+
+/// Returned by [AudioStream] when it couldn't keep up and frames had to be dropped
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Error)]
+#[error("Stream lagged behind and dropped {0} frames")]
+pub struct Lagged(pub u64);
+
+struct Frame<T> {
+  /// Frames dropped right before this one
+  lagged: u64,
+  value: T,
+}
+
+struct Producer<T> {
+  state: T,
+  lagged: u64,
+}
+
+struct StreamShared<T> {
+  id: ListenerId,
+  /// Only touched by the audio callback
+  producer: Mutex<Producer<T>>,
+  queue: Ring<Frame<T>>,
+  waker: AtomicWaker,
+  closed: AtomicBool,
+}
+
+impl<T> Debug for StreamShared<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("StreamShared")
+      .field("id", &self.id)
+      .field("queued", &self.queue.len())
+      .field("closed", &self.closed)
+      .finish()
+  }
+}
+
+impl<T: AudioData> AudioListenerTrait for StreamShared<T> {
+  fn update(&self, data: &[f32], _: &BlockInfo) {
+    let Ok(mut producer) = self.producer.try_lock() else {
+      return;
+    };
+
+    let Producer { state, lagged } = &mut *producer;
+
+    state.update(data);
+
+    let pushed = self.queue.push_with(|frame| {
+      frame.lagged = *lagged;
+      frame.value.clone_from(state);
+    });
+
+    if pushed {
+      *lagged = 0;
+      self.waker.wake();
+    } else {
+      *lagged += 1;
+    }
+  }
+
+  fn is_active(&self) -> bool {
+    !self.is_detached()
+  }
+
+  fn is_detached(&self) -> bool {
+    self.closed.load(Ordering::Acquire)
+  }
+
+  fn detach(&self) {
+    self.closed.store(true, Ordering::Release);
+    self.waker.wake();
+  }
+}
+
+/// [Stream] of frames from the audio callback, keeps up to `capacity` frames
+/// and yields [Lagged] where frames were dropped because it was polled too slowly
+///
+/// Ends once it's removed with [Listener::remove]
+pub struct AudioStream<T = Vec<f32>> {
+  shared: Arc<StreamShared<T>>,
+  /// Frame that comes after a [Lagged]
+  next: Option<T>,
+}
+
+impl<T: AudioData> AudioStream<T> {
+  pub(crate) fn new(listener: &Listener, value: T, capacity: usize) -> Self {
+    let id = listener.next_id();
+    let shared = Arc::new(StreamShared {
+      id,
+      producer: Mutex::new(Producer {
+        state: value.clone(),
+        lagged: 0,
+      }),
+      queue: Ring::new(capacity, || Frame {
+        lagged: 0,
+        value: value.clone(),
+      }),
+      waker: AtomicWaker::new(),
+      closed: AtomicBool::new(false),
+    });
+
+    listener.register(id, shared.clone());
+
+    Self { shared, next: None }
+  }
+
+  /// Gets the id of this stream, can be used with [Listener::remove]
+  pub fn id(&self) -> ListenerId {
+    self.shared.id
+  }
+
+  /// Gets how many frames are waiting to be polled
+  pub fn len(&self) -> usize {
+    self.shared.queue.len() + self.next.is_some() as usize
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  fn next_frame(&mut self) -> Option<Result<T, Lagged>> {
+    if let Some(value) = self.next.take() {
+      return Some(Ok(value));
+    }
+
+    let (lagged, value) = self
+      .shared
+      .queue
+      .pop_with(|frame| (frame.lagged, frame.value.clone()))?;
+
+    if lagged > 0 {
+      self.next = Some(value);
+
+      return Some(Err(Lagged(lagged)));
+    }
+
+    Some(Ok(value))
+  }
+}
+
+// Never projects a pin to `next`
+impl<T> Unpin for AudioStream<T> {}
+
+impl<T: AudioData> Stream for AudioStream<T> {
+  type Item = Result<T, Lagged>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    if let Some(frame) = self.next_frame() {
+      return Poll::Ready(Some(frame));
+    }
+
+    self.shared.waker.register(cx.waker());
+
+    // Checks again in case a frame was pushed before the waker was registered
+    match self.next_frame() {
+      Some(frame) => Poll::Ready(Some(frame)),
+      None if self.shared.is_detached() => Poll::Ready(None),
+      None => Poll::Pending,
+    }
+  }
+}
+
+impl<T> Drop for AudioStream<T> {
+  fn drop(&mut self) {
+    self.shared.closed.store(true, Ordering::Release);
+  }
+}
+
+impl<T: AudioData> Debug for AudioStream<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("AudioStream")
+      .field("id", &self.id())
+      .field("len", &self.len())
+      .finish()
+  }
+}
+
+impl Listener {
+  /// Creates a new [AudioStream] starting from [Default::default]
+  pub fn create_stream<T: AudioData>(&self, capacity: usize) -> AudioStream<T> {
+    self.create_stream_with(T::default(), capacity)
+  }
+
+  /// Creates a new [AudioStream] starting from `value`
+  pub fn create_stream_with<T: AudioData>(&self, value: T, capacity: usize) -> AudioStream<T> {
+    AudioStream::new(self, value, capacity)
+  }
+}
@@ -0,0 +1,175 @@
+use std::{
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Weak,
+  },
+  thread,
+  time::{Duration, Instant},
+};
+
+use crate::{
+  listener::{dispatch, skip, Handles},
+  notify::Notify,
+  platform::Reporter,
+  ring::Ring,
+  Block, BlockInfo, Result, Status,
+};
+
+/// How often the worker checks if it should stop while there's nothing to process
+const IDLE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Snapshot of how a processing worker is keeping up, see [Listener::worker_stats](crate::Listener::worker_stats)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WorkerStats {
+  /// Blocks waiting to be processed
+  pub queued: usize,
+  /// Max amount of blocks that can wait before being dropped
+  pub capacity: usize,
+  /// Blocks processed so far
+  pub processed: u64,
+  /// Blocks dropped because the worker fell behind
+  pub dropped: u64,
+  /// Time it took to process the last block
+  pub last: Duration,
+  /// Longest time it took to process a block
+  pub max: Duration,
+  /// Average time it took to process a block
+  pub average: Duration,
+}
+
+pub(crate) struct Worker {
+  queue: Ring<Block>,
+  notify: Notify,
+  running: AtomicBool,
+  processed: AtomicU64,
+  dropped: AtomicU64,
+  /// Blocks dropped since the last queued block, only touched by the audio callback
+  skipped: AtomicU64,
+  last: AtomicU64,
+  max: AtomicU64,
+  total: AtomicU64,
+}
+
+impl Worker {
+  /// Spawns a thread that updates every handle with the blocks given to [Self::push],
+  /// blocks it drops are reported from that thread instead of the audio callback
+  /// and counted by every [BlockReceiver](crate::BlockReceiver) as overruns
+  pub fn spawn(
+    handles: &Arc<Handles>,
+    reporter: Arc<Reporter>,
+    capacity: usize,
+  ) -> Result<Arc<Self>> {
+    let worker = Arc::new(Self {
+      queue: Ring::new(capacity, Block::default),
+      notify: Default::default(),
+      running: AtomicBool::new(true),
+      processed: Default::default(),
+      dropped: Default::default(),
+      skipped: Default::default(),
+      last: Default::default(),
+      max: Default::default(),
+      total: Default::default(),
+    });
+
+    let handles = Arc::downgrade(handles);
+    let thread_worker = worker.clone();
+
+    thread::Builder::new()
+      .name(String::from("safav-worker"))
+      .spawn(move || thread_worker.run(handles, reporter))?;
+
+    Ok(worker)
+  }
+
+  fn run(&self, handles: Weak<Handles>, reporter: Arc<Reporter>) {
+    let mut block = Block::default();
+    let mut reported = 0;
+
+    while self.running.load(Ordering::Acquire) {
+      let ready = self.notify.wait_until(Some(IDLE_TIMEOUT), || {
+        self.queue.len() > 0 || !self.running.load(Ordering::Acquire)
+      });
+
+      // Stops once every listener is gone
+      let Some(handles) = handles.upgrade() else {
+        break;
+      };
+
+      if !ready {
+        continue;
+      }
+
+      while self
+        .queue
+        .pop_with(|next| std::mem::swap(next, &mut block))
+        .is_some()
+      {
+        let timer = Instant::now();
+
+        if block.overruns > 0 {
+          skip(&handles, block.overruns);
+        }
+
+        dispatch(&handles, &block.samples, &block.info);
+
+        let elapsed = timer.elapsed().as_nanos() as u64;
+
+        self.processed.fetch_add(1, Ordering::Relaxed);
+        self.last.store(elapsed, Ordering::Relaxed);
+        self.max.fetch_max(elapsed, Ordering::Relaxed);
+        self.total.fetch_add(elapsed, Ordering::Relaxed);
+
+        // Checked for every block since the queue doesn't empty while it's behind
+        self.report_dropped(&reporter, &mut reported);
+      }
+    }
+  }
+
+  /// Reports blocks dropped since the last report, on this thread instead of the audio callback
+  fn report_dropped(&self, reporter: &Reporter, reported: &mut u64) {
+    let dropped = self.dropped.load(Ordering::Relaxed);
+
+    if dropped > *reported {
+      reporter.report(Status::Dropped {
+        blocks: dropped - *reported,
+      });
+      *reported = dropped;
+    }
+  }
+
+  /// Queues a copy of the block for the worker, called from the audio callback
+  pub fn push(&self, data: &[f32], info: &BlockInfo) {
+    let pushed = self.queue.push_with(|block| {
+      block.samples.clear();
+      block.samples.extend_from_slice(data);
+      block.info = *info;
+      block.overruns = self.skipped.swap(0, Ordering::Relaxed);
+    });
+
+    if pushed {
+      self.notify.notify();
+    } else {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+      self.skipped.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  pub fn stop(&self) {
+    self.running.store(false, Ordering::Release);
+    self.notify.notify();
+  }
+
+  pub fn stats(&self) -> WorkerStats {
+    let processed = self.processed.load(Ordering::Relaxed);
+
+    WorkerStats {
+      queued: self.queue.len(),
+      capacity: self.queue.capacity(),
+      processed,
+      dropped: self.dropped.load(Ordering::Relaxed),
+      last: Duration::from_nanos(self.last.load(Ordering::Relaxed)),
+      max: Duration::from_nanos(self.max.load(Ordering::Relaxed)),
+      average: Duration::from_nanos(self.total.load(Ordering::Relaxed) / processed.max(1)),
+    }
+  }
+}
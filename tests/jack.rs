@@ -0,0 +1,141 @@
+//! Needs a JACK server, the dummy driver gives it system ports without any audio hardware:
+//!
+//! ```sh
+//! jackd -d dummy -r 48000 &
+//! cargo test --test jack --features jack -- --ignored --test-threads 1
+//! ```
+
+#![cfg(target_os = "linux")]
+
+use std::{
+  f32::consts::TAU,
+  time::{Duration, Instant},
+};
+
+use jack::{
+  AsyncClient, AudioOut, Client, ClientOptions, ClosureProcessHandler, Control, ProcessScope,
+};
+use safav::{AudioListener, Device, DeviceKind, Host};
+
+type Process = Box<dyn FnMut(&Client, &ProcessScope) -> Control + Send>;
+type Sine = AsyncClient<(), ClosureProcessHandler<Process>>;
+
+/// Client playing a sine at half volume on one output port, connected to `playback` if it's given
+fn sine(name: &str, playback: Option<&str>) -> Sine {
+  let (client, _) =
+    Client::new(name, ClientOptions::NO_START_SERVER).expect("JACK has to be running");
+  let mut port = client.register_port("out", AudioOut).unwrap();
+  let rate = client.sample_rate() as f32;
+  let mut frame = 0u64;
+
+  let process: Process = Box::new(move |_, scope| {
+    for sample in port.as_mut_slice(scope) {
+      *sample = (TAU * 440. * frame as f32 / rate).sin() * 0.5;
+      frame += 1;
+    }
+
+    Control::Continue
+  });
+
+  let client = client
+    .activate_async((), ClosureProcessHandler::new(process))
+    .unwrap();
+
+  if let Some(playback) = playback {
+    client
+      .as_client()
+      .connect_ports_by_name(&format!("{name}:out"), playback)
+      .unwrap();
+  }
+
+  client
+}
+
+/// Highest peak seen within a few seconds, stops early once it's close to the sine
+fn peak(listener: &AudioListener<Vec<f32>>) -> f32 {
+  let start = Instant::now();
+  let mut peak = 0f32;
+
+  while start.elapsed() < Duration::from_secs(5) && peak < 0.4 {
+    if let Some(data) = listener.wait_timeout(Duration::from_secs(1)) {
+      peak = data
+        .iter()
+        .fold(peak, |peak, sample| peak.max(sample.abs()));
+    }
+  }
+
+  peak
+}
+
+fn find(host: &Host, kind: DeviceKind) -> Device {
+  host
+    .devices()
+    .iter()
+    .find(|device| device.kind() == kind)
+    .cloned()
+    .unwrap_or_else(|| panic!("no {kind:?} device"))
+}
+
+#[test]
+#[ignore]
+fn lists_the_system_ports() {
+  let host = Host::jack().unwrap();
+
+  assert_eq!(
+    host.default_device().unwrap().kind(),
+    DeviceKind::OutputMonitor
+  );
+  assert_eq!(find(&host, DeviceKind::Input).id(), "system");
+}
+
+#[test]
+#[ignore]
+fn captures_a_client() {
+  let _sine = sine("safav-test-client", None);
+  let mut host = Host::jack().unwrap();
+  let listener = host.create_listener::<Vec<f32>>();
+  let device = find(&host, DeviceKind::Application);
+
+  assert_eq!(device.id(), "safav-test-client");
+
+  host.listen().unwrap();
+  host.change_device(&device).unwrap();
+
+  let peak = peak(&listener);
+
+  assert!((0.4..0.6).contains(&peak), "peak of {peak}");
+}
+
+#[test]
+#[ignore]
+fn monitors_the_system_playback() {
+  let _sine = sine("safav-test-playback", Some("system:playback_1"));
+  let mut host = Host::jack().unwrap();
+  let listener = host.create_listener::<Vec<f32>>();
+
+  host.listen().unwrap();
+  host
+    .change_device(&find(&host, DeviceKind::OutputMonitor))
+    .unwrap();
+
+  let peak = peak(&listener);
+
+  assert!((0.4..0.6).contains(&peak), "peak of {peak}");
+}
+
+#[test]
+#[ignore]
+fn captures_specific_ports() {
+  let _sine = sine("safav-test-ports", None);
+  let mut host = Host::jack().unwrap();
+  let listener = host.create_listener::<Vec<f32>>();
+
+  host.listen().unwrap();
+  host
+    .change_device(&Device::jack_ports(&["safav-test-ports:out"]))
+    .unwrap();
+
+  let peak = peak(&listener);
+
+  assert!((0.4..0.6).contains(&peak), "peak of {peak}");
+}
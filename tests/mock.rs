@@ -0,0 +1,376 @@
+use std::{
+  thread,
+  time::{Duration, Instant},
+};
+
+use safav::{
+  AnalysisSettings, BlockSize, Error, Host, MixChannels, MockClock, MockDevice, MockHost, Signal,
+  Spectrum, State,
+};
+
+const SINE: Signal = Signal::Sine {
+  frequency: 440.,
+  amplitude: 0.5,
+};
+
+/// Host listening to a mock device that only produces a block on [MockClock::step]
+fn host(device: MockDevice) -> (Host, MockClock) {
+  let mock = MockHost::new()
+    .with_device(device)
+    .with_device(MockDevice::new("Microphone").with_channels(1));
+  let clock = mock.clock();
+
+  (Host::mock(mock), clock)
+}
+
+fn listen(host: &mut Host) {
+  host.listen().unwrap();
+}
+
+#[test]
+fn listener_gets_the_latest_block() {
+  let (mut host, clock) = host(MockDevice::new("Speakers").with_signal(SINE));
+  let listener = host.create_listener::<Vec<f32>>();
+
+  listen(&mut host);
+
+  assert!(listener.poll().is_empty());
+
+  clock.step();
+  clock.step();
+
+  let data = listener.poll();
+
+  assert_eq!(data.sequence(), 2);
+  assert_eq!(data.len(), 512 * 2);
+  assert!(data.iter().any(|sample| sample.abs() > 0.1));
+  assert!(data.iter().all(|sample| sample.abs() <= 0.5));
+}
+
+#[test]
+fn frames_can_be_held_while_polling_again() {
+  let (mut host, clock) = host(MockDevice::new("Speakers").with_signal(SINE));
+  let listener = host.create_listener::<Vec<f32>>();
+  let clone = listener.clone();
+
+  listen(&mut host);
+  clock.step();
+
+  let first = listener.poll();
+
+  clock.step();
+
+  let second = clone.poll();
+
+  assert_eq!((first.sequence(), second.sequence()), (1, 2));
+  assert!(format!("{listener:?}").contains("AudioListener"));
+
+  clock.step();
+  assert_eq!(listener.sequence(), 3);
+
+  // Every slot but the latest one is held, so this frame is skipped instead of waiting for them
+  clock.step();
+  assert_eq!(listener.sequence(), 3);
+
+  drop((first, second));
+  clock.step();
+
+  assert_eq!(listener.poll().sequence(), 4);
+}
+
+#[test]
+fn waiting_stops_once_the_host_pauses() {
+  let (mut host, _clock) = host(MockDevice::new("Speakers"));
+  let listener = host.create_listener::<Vec<f32>>();
+
+  listen(&mut host);
+
+  thread::scope(|scope| {
+    let waiter = scope.spawn(|| {
+      let start = Instant::now();
+
+      (
+        listener.wait_timeout(Duration::from_secs(10)).is_none(),
+        start.elapsed(),
+      )
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    host.pause().unwrap();
+
+    let (none, waited) = waiter.join().unwrap();
+
+    assert!(none);
+    assert!(waited < Duration::from_secs(5), "waited {waited:?}");
+  });
+}
+
+#[test]
+fn goes_through_every_state() {
+  let (mut host, clock) = host(MockDevice::new("Speakers").with_signal(SINE));
+  let listener = host.create_listener::<Vec<f32>>();
+
+  assert_eq!(host.state(), State::Idle);
+  assert!(matches!(host.pause(), Err(Error::NotListening)));
+  assert!(matches!(host.resume(), Err(Error::NotListening)));
+
+  listen(&mut host);
+  clock.step();
+
+  assert_eq!(host.state(), State::Listening);
+  assert!(!listener.is_paused());
+  assert_eq!(listener.sequence(), 1);
+
+  host.pause().unwrap();
+  clock.step();
+
+  assert_eq!(host.state(), State::Paused);
+  assert!(listener.is_paused());
+  assert_eq!(listener.sequence(), 1);
+
+  host.resume().unwrap();
+  clock.step();
+
+  assert_eq!(host.state(), State::Listening);
+  assert!(!listener.is_paused());
+  assert_eq!(listener.sequence(), 2);
+
+  listener.pause();
+
+  assert!(listener.is_paused());
+
+  listener.resume();
+  clock.fail("injected error");
+
+  assert_eq!(host.state(), State::Error(String::from("injected error")));
+
+  listen(&mut host);
+
+  assert_eq!(host.state(), State::Listening);
+
+  host.stop().unwrap();
+
+  assert_eq!(host.state(), State::Idle);
+  assert!(!listener.is_paused());
+
+  clock.set_unavailable("Speakers", true);
+
+  assert!(host.listen().is_err());
+  assert_eq!(
+    host.state(),
+    State::Error(Error::NoDeviceFound(String::from("Speakers")).to_string())
+  );
+}
+
+#[test]
+fn changes_device_by_id() {
+  let (mut host, clock) = host(MockDevice::new("Speakers"));
+  let listener = host.create_listener::<Vec<f32>>();
+
+  listen(&mut host);
+
+  let microphone = host.devices()[1].clone();
+
+  host.change_device(&microphone).unwrap();
+  clock.step();
+
+  assert_eq!(
+    host.current_device().map(|device| device.id()),
+    Some(microphone.id())
+  );
+  assert_eq!(listener.poll().len(), 512);
+}
+
+#[test]
+fn chunked_listener_gets_blocks_of_the_requested_size() {
+  let (mut host, clock) = host(MockDevice::new("Speakers").with_block_size(300));
+  let listener = host.create_chunked_listener::<Vec<f32>>(BlockSize::new(1024));
+
+  listen(&mut host);
+
+  for _ in 0..3 {
+    clock.step();
+  }
+
+  assert!(listener.poll().is_empty());
+
+  clock.step();
+
+  let data = listener.poll();
+
+  assert_eq!(data.sequence(), 1);
+  assert_eq!(data.len(), 1024 * 2);
+}
+
+#[test]
+fn resampled_listener_gets_samples_at_the_requested_rate() {
+  let (mut host, clock) = host(MockDevice::new("Speakers").with_block_size(480));
+  let listener = host.create_resampled_listener::<Vec<f32>>(24000);
+
+  listen(&mut host);
+  clock.step();
+
+  assert_eq!(listener.poll().len(), 240 * 2);
+}
+
+#[test]
+fn spectrum_has_the_requested_size() {
+  let (mut host, clock) = host(MockDevice::new("Speakers").with_signal(SINE));
+  let spectrum = host
+    .pipeline()
+    .then(MixChannels)
+    .then(Spectrum::new(256))
+    .build();
+
+  listen(&mut host);
+  clock.step();
+
+  assert_eq!(spectrum.poll().len(), 256);
+}
+
+#[test]
+fn spectrum_follows_the_signal() {
+  let (mut host, clock) = host(MockDevice::new("Speakers"));
+  let mut pipeline = host.pipeline().then(MixChannels);
+  let wave = pipeline.tap();
+  let spectrum = pipeline.then(Spectrum::new(512)).build();
+
+  listen(&mut host);
+  clock.step();
+
+  assert_eq!(wave.poll().len(), 512);
+  assert!(spectrum.poll().iter().all(|bin| *bin == 0.));
+
+  clock.set_signal(SINE);
+  clock.step();
+
+  assert!(spectrum.poll().iter().any(|bin| bin.abs() > 0.1));
+
+  clock.set_signal(Signal::Silence);
+  clock.step();
+
+  assert!(spectrum.poll().iter().all(|bin| *bin == 0.));
+}
+
+#[test]
+fn pipeline_listeners_can_be_found_and_modified() {
+  let (mut host, clock) = host(MockDevice::new("Speakers").with_signal(SINE));
+  let mut pipeline = host.pipeline();
+  let samples = pipeline.tap();
+  let mono = pipeline.then(MixChannels).build();
+
+  listen(&mut host);
+
+  let found = host.get_listener::<Vec<f32>>(samples.id()).unwrap();
+
+  assert!(host.get_listener::<Vec<f32>>(mono.id()).is_some());
+
+  found.modify(|data| data.clear());
+  mono.modify(|data| data.truncate(10));
+  clock.step();
+
+  assert!(samples.poll().is_empty());
+  assert_eq!(mono.poll().len(), 10);
+
+  clock.step();
+
+  assert_eq!(samples.poll().len(), 512 * 2);
+  assert_eq!(mono.poll().len(), 512);
+
+  drop((samples, found));
+
+  assert!(host.get_listener::<Vec<f32>>(mono.id()).is_some());
+}
+
+#[test]
+fn worker_gives_the_same_spectrum() {
+  let spectrum = |worker: bool| {
+    let (mut host, clock) = host(MockDevice::new("Speakers").with_signal(SINE));
+    let spectrum = host
+      .pipeline()
+      .then(MixChannels)
+      .then(Spectrum::new(512))
+      .build();
+
+    if worker {
+      host.enable_worker(8).unwrap();
+    }
+
+    listen(&mut host);
+    clock.step();
+
+    // The worker updates listeners on its own thread
+    let data = spectrum.wait().unwrap().clone();
+
+    data
+  };
+
+  assert_eq!(spectrum(false), spectrum(true));
+}
+
+#[test]
+fn analysis_measures_the_signal() {
+  let settings = AnalysisSettings::default();
+  let (mut host, clock) = host(MockDevice::new("Speakers").with_sample_rate(44100));
+  let features = host.create_analysis_listener(settings).unwrap();
+
+  listen(&mut host);
+  clock.advance_frames(settings.window as u64 * 2);
+
+  let silent = features.poll().clone();
+
+  assert_eq!(silent.frames, settings.window);
+  assert_eq!(silent.bands.len(), settings.bands);
+  assert_eq!(silent.rms, 0.);
+  assert!(!silent.onset);
+
+  clock.set_signal(Signal::Noise { amplitude: 0.5 });
+  clock.advance_frames(settings.hop as u64);
+
+  let noise = features.poll();
+
+  assert!(noise.position > silent.position);
+  assert!(noise.rms > 0.05);
+  assert!(noise.flux > 0.);
+  assert!(noise.onset);
+}
+
+#[test]
+fn analysis_estimates_the_tempo() {
+  let settings = AnalysisSettings::default();
+  // Short clicks every half a second
+  let beat = (0..22050)
+    .map(|frame| match frame < 441 {
+      true => (frame as f32 * 0.7).sin() * 0.8,
+      false => 0.,
+    })
+    .collect::<Vec<_>>();
+  let (mut host, clock) = host(
+    MockDevice::new("Speakers")
+      .with_channels(1)
+      .with_sample_rate(44100)
+      .with_signal(Signal::Samples(beat.into())),
+  );
+  let features = host.create_analysis_listener(settings).unwrap();
+
+  listen(&mut host);
+  clock.advance_frames(44100 * 12);
+
+  let bpm = features.poll().bpm.unwrap();
+
+  assert!((bpm - 120.).abs() < 2., "estimated {bpm} bpm");
+}
+
+#[test]
+fn analysis_rejects_windows_the_fft_cant_take() {
+  let (host, _) = host(MockDevice::new("Speakers"));
+
+  for window in [0, 16385] {
+    let settings = AnalysisSettings {
+      window,
+      ..Default::default()
+    };
+
+    assert!(host.create_analysis_listener(settings).is_err());
+  }
+}
@@ -0,0 +1,115 @@
+use std::{f32::consts::TAU, sync::Arc};
+
+use safav::{
+  AnalysisSettings, Features, Host, MockDevice, MockHost, Offline, OutputFormat, Signal,
+};
+
+const RATE: u32 = 44100;
+const BLOCK: usize = 512;
+/// Just under 3 seconds, short enough that no frame starts exactly where a window ends
+const BLOCKS: usize = 258;
+const FRAME_RATE: f64 = 30.;
+
+/// Stereo sine with a click every half second, so onsets and the flux change over time
+fn samples() -> Vec<f32> {
+  (0..BLOCK * BLOCKS)
+    .flat_map(|frame| {
+      let click = frame % (RATE as usize / 2) < 64;
+      let sine = (TAU * 220. * frame as f32 / RATE as f32).sin() * 0.3;
+      let sample = if click { 0.9 } else { sine };
+
+      [sample, sample * 0.5]
+    })
+    .collect()
+}
+
+/// Runs [Offline] over `samples` pushed `chunk` frames at a time
+fn offline(samples: &[f32], chunk: usize, format: OutputFormat) -> String {
+  let mut output = Vec::new();
+  let mut offline = Offline::new(&mut output, format, FRAME_RATE).unwrap();
+
+  for chunk in samples.chunks(chunk * 2) {
+    offline.push(chunk, 2, RATE).unwrap();
+  }
+
+  offline.finish().unwrap();
+
+  String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn gives_the_same_output_for_the_same_samples() {
+  let samples = samples();
+
+  for format in [OutputFormat::Csv, OutputFormat::JsonLines] {
+    let first = offline(&samples, 1000, format);
+    let second = offline(&samples, 333, format);
+
+    assert!(!first.is_empty());
+    assert_eq!(first.as_bytes(), second.as_bytes());
+  }
+}
+
+#[test]
+fn matches_the_analysis_listener() {
+  let samples = samples();
+  let settings = AnalysisSettings::default();
+  let mock = MockHost::new().with_device(
+    MockDevice::new("Speakers")
+      .with_sample_rate(RATE)
+      .with_block_size(BLOCK)
+      .with_signal(Signal::Samples(Arc::from(samples.as_slice()))),
+  );
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+  let listener = host.create_analysis_listener(settings).unwrap();
+  let mut windows: Vec<Features> = Vec::new();
+
+  host.listen().unwrap();
+
+  for _ in 0..BLOCKS {
+    clock.step();
+
+    if listener.has_new() {
+      windows.push(listener.poll().clone());
+    }
+  }
+
+  assert!(windows.iter().any(|window| window.onset));
+
+  let csv = offline(&samples, 1000, OutputFormat::Csv);
+  let rows = csv.lines().skip(1).collect::<Vec<_>>();
+
+  assert_eq!(rows.len(), 90);
+
+  for (frame, row) in rows.iter().enumerate() {
+    let time = frame as f64 / FRAME_RATE;
+    let expected = windows
+      .iter()
+      .rev()
+      .find(|window| (window.position + window.frames as u64) as f64 / RATE as f64 <= time)
+      .cloned()
+      .unwrap_or_default();
+    let columns = row.split(',').collect::<Vec<_>>();
+    let bands = (0..settings.bands)
+      .map(|band| {
+        expected
+          .bands
+          .get(band)
+          .copied()
+          .unwrap_or_default()
+          .to_string()
+      })
+      .collect::<Vec<_>>();
+    let bpm = expected.bpm.map(|bpm| bpm.to_string()).unwrap_or_default();
+
+    assert_eq!(columns[2], expected.rms.to_string(), "rms of frame {frame}");
+    assert_eq!(
+      columns[3],
+      expected.flux.to_string(),
+      "flux of frame {frame}"
+    );
+    assert_eq!(columns[5], bpm, "bpm of frame {frame}");
+    assert_eq!(columns[6..], bands, "bands of frame {frame}");
+  }
+}
@@ -0,0 +1,141 @@
+//! Needs a PipeWire daemon with a session manager, `pw-cli` and `pw-metadata`,
+//! every test creates its own null sinks so it can run without touching the desktop session:
+//!
+//! ```sh
+//! export XDG_RUNTIME_DIR=$(mktemp -d)
+//! pipewire & wireplumber &
+//! cargo test --test pipewire --features pipewire -- --ignored --test-threads 1
+//! ```
+
+#![cfg(target_os = "linux")]
+
+use std::{
+  io::Write,
+  process::{Child, Command, Stdio},
+  thread,
+  time::{Duration, Instant},
+};
+
+use safav::{Device, DeviceKind, Host};
+
+/// Null sink that exists until it's dropped, owned by a `pw-cli` process
+struct NullSink {
+  name: String,
+  cli: Child,
+}
+
+impl NullSink {
+  fn create(name: &str) -> Self {
+    let mut cli = Command::new("pw-cli")
+      .stdin(Stdio::piped())
+      .stdout(Stdio::null())
+      .spawn()
+      .expect("pw-cli has to be installed");
+
+    writeln!(
+      cli.stdin.as_mut().unwrap(),
+      "create-node adapter {{ factory.name=support.null-audio-sink node.name={name} \
+       media.class=Audio/Sink audio.position=[FL FR] }}"
+    )
+    .unwrap();
+
+    Self {
+      name: name.to_string(),
+      cli,
+    }
+  }
+
+  /// Makes it the default sink through the session manager
+  fn set_default(&self) {
+    let status = Command::new("pw-metadata")
+      .args(["0", "default.configured.audio.sink"])
+      .arg(format!(r#"{{ "name": "{}" }}"#, self.name))
+      .arg("Spa:String:JSON")
+      .stdout(Stdio::null())
+      .status()
+      .expect("pw-metadata has to be installed");
+
+    assert!(status.success());
+  }
+}
+
+impl Drop for NullSink {
+  fn drop(&mut self) {
+    let _ = self.cli.kill();
+    let _ = self.cli.wait();
+  }
+}
+
+/// Refreshes the devices until `f` finds one, since the registry sees new nodes on its own thread
+fn find(host: &mut Host, f: impl Fn(&Device) -> bool) -> Device {
+  let start = Instant::now();
+
+  loop {
+    host.refresh().unwrap();
+
+    if let Some(device) = host.devices().iter().find(|device| f(device)) {
+      return device.clone();
+    }
+
+    assert!(
+      start.elapsed() < Duration::from_secs(5),
+      "device didn't show up"
+    );
+    thread::sleep(Duration::from_millis(50));
+  }
+}
+
+#[test]
+#[ignore]
+fn captures_a_sink_monitor_only_while_listening() {
+  let sink = NullSink::create("safav-test-capture");
+  let mut host = Host::pipewire().unwrap();
+  let listener = host.create_listener::<Vec<f32>>();
+  let device = find(&mut host, |device| device.id() == sink.name);
+
+  assert_eq!(device.kind(), DeviceKind::OutputMonitor);
+
+  host.change_device(&device).unwrap();
+  assert!(listener.wait_timeout(Duration::from_millis(500)).is_none());
+
+  host.listen().unwrap();
+  assert!(listener.wait_timeout(Duration::from_secs(5)).is_some());
+}
+
+#[test]
+#[ignore]
+fn marks_the_default_sink() {
+  let sink = NullSink::create("safav-test-default");
+  let mut host = Host::pipewire().unwrap();
+
+  find(&mut host, |device| device.id() == sink.name);
+  sink.set_default();
+
+  let device = find(&mut host, |device| device.is_default());
+
+  assert_eq!(device.id(), sink.name);
+  assert_eq!(host.default_device().unwrap().id(), sink.name);
+}
+
+#[test]
+#[ignore]
+fn captures_a_sink_again_once_it_comes_back() {
+  let name = "safav-test-follow";
+  let sink = NullSink::create(name);
+  let mut host = Host::pipewire().unwrap();
+  let listener = host.create_listener::<Vec<f32>>();
+  let device = find(&mut host, |device| device.id() == name);
+
+  host.change_device(&device).unwrap();
+  host.listen().unwrap();
+  assert!(listener.wait_timeout(Duration::from_secs(5)).is_some());
+
+  drop(sink);
+
+  // Waits until the stream stopped giving frames
+  while listener.wait_timeout(Duration::from_millis(500)).is_some() {}
+
+  let _sink = NullSink::create(name);
+
+  assert!(listener.wait_timeout(Duration::from_secs(5)).is_some());
+}
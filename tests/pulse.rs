@@ -0,0 +1,145 @@
+//! Needs a PulseAudio daemon and `pactl` / `pacat`,
+//! every test loads its own null sinks so it can run against a daemon started just for it:
+//!
+//! ```sh
+//! pulseaudio --daemonize --exit-idle-time=-1
+//! cargo test --test pulse --features pulse -- --ignored --test-threads 1
+//! ```
+
+#![cfg(target_os = "linux")]
+
+use std::{
+  f32::consts::TAU,
+  io::Write,
+  process::{Child, Command, Stdio},
+  time::{Duration, Instant},
+};
+
+use safav::{Device, DeviceEvent, DeviceKind, Host};
+
+/// Null sink module that's unloaded once it's dropped
+struct NullSink {
+  name: String,
+  module: String,
+}
+
+impl NullSink {
+  fn load(name: &str) -> Self {
+    let output = Command::new("pactl")
+      .args(["load-module", "module-null-sink"])
+      .arg(format!("sink_name={name}"))
+      .output()
+      .expect("pactl has to be installed");
+
+    assert!(output.status.success(), "couldn't load a null sink");
+
+    Self {
+      name: name.to_string(),
+      module: String::from_utf8(output.stdout).unwrap().trim().to_string(),
+    }
+  }
+
+  fn monitor(&self) -> String {
+    format!("{}.monitor", self.name)
+  }
+
+  /// Plays a sine into the sink until the returned process is killed
+  fn play_sine(&self) -> Child {
+    let mut pacat = Command::new("pacat")
+      .arg(format!("--device={}", self.name))
+      .args(["--format=float32le", "--rate=48000", "--channels=1"])
+      .stdin(Stdio::piped())
+      .spawn()
+      .expect("pacat has to be installed");
+
+    // Ten seconds of a 440 hz sine at half volume, more than any test waits
+    let bytes = (0..48000 * 10)
+      .map(|frame| (TAU * 440. * frame as f32 / 48000.).sin() * 0.5)
+      .flat_map(f32::to_le_bytes)
+      .collect::<Vec<_>>();
+
+    let mut stdin = pacat.stdin.take().unwrap();
+
+    std::thread::spawn(move || stdin.write_all(&bytes));
+
+    pacat
+  }
+}
+
+impl Drop for NullSink {
+  fn drop(&mut self) {
+    let _ = Command::new("pactl")
+      .args(["unload-module", &self.module])
+      .status();
+  }
+}
+
+fn find(host: &Host, id: &str) -> Device {
+  host
+    .devices()
+    .iter()
+    .find(|device| device.id() == id)
+    .cloned()
+    .expect("null sink monitor should be listed")
+}
+
+#[test]
+#[ignore]
+fn lists_a_null_sink_monitor() {
+  let sink = NullSink::load("safav-test-list");
+  let host = Host::pulse().unwrap();
+  let device = find(&host, &sink.monitor());
+
+  assert_eq!(device.kind(), DeviceKind::OutputMonitor);
+  assert_eq!(device.backend(), "PulseAudio");
+}
+
+#[test]
+#[ignore]
+fn captures_what_plays_on_a_sink() {
+  let sink = NullSink::load("safav-test-capture");
+  let mut host = Host::pulse().unwrap();
+  let listener = host.create_listener::<Vec<f32>>();
+  let device = find(&host, &sink.monitor());
+
+  host.change_device(&device).unwrap();
+  host.listen().unwrap();
+
+  let mut pacat = sink.play_sine();
+  let start = Instant::now();
+  let mut peak = 0f32;
+
+  while start.elapsed() < Duration::from_secs(5) && peak < 0.4 {
+    if let Some(data) = listener.wait_timeout(Duration::from_secs(1)) {
+      peak = data
+        .iter()
+        .fold(peak, |peak, sample| peak.max(sample.abs()));
+    }
+  }
+
+  let _ = pacat.kill();
+  let _ = pacat.wait();
+
+  assert!((0.4..0.6).contains(&peak), "peak of {peak}");
+}
+
+#[test]
+#[ignore]
+fn reports_added_and_removed_sinks() {
+  let host = Host::pulse().unwrap();
+  let events = host.device_events().unwrap();
+  let sink = NullSink::load("safav-test-events");
+  let monitor = sink.monitor();
+
+  let added = std::iter::from_fn(|| events.recv_timeout(Duration::from_secs(5)))
+    .find(|event| matches!(event, DeviceEvent::Added(device) if device.id() == monitor));
+
+  assert!(added.is_some());
+
+  drop(sink);
+
+  let removed = std::iter::from_fn(|| events.recv_timeout(Duration::from_secs(5)))
+    .find(|event| matches!(event, DeviceEvent::Removed(device) if device.id() == monitor));
+
+  assert!(removed.is_some());
+}
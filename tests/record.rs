@@ -0,0 +1,204 @@
+use std::{
+  path::{Path, PathBuf},
+  thread,
+  time::{Duration, Instant},
+};
+
+use safav::{Error, Host, MockClock, MockDevice, MockHost, Recorder, Signal};
+
+/// Host listening to a stereo and a mono mock device that only produce a block on [MockClock::step]
+fn host() -> (Host, MockClock, Recorder) {
+  let mock = MockHost::new()
+    .with_device(MockDevice::new("Speakers").with_signal(Signal::Noise { amplitude: 0.5 }))
+    .with_device(MockDevice::new("Microphone").with_channels(1));
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+  let recorder = host.create_recorder(Some(Duration::from_secs(1))).unwrap();
+
+  host.listen().unwrap();
+
+  (host, clock, recorder)
+}
+
+fn path(name: &str) -> PathBuf {
+  std::env::temp_dir().join(format!("safav-{}-{name}.wav", std::process::id()))
+}
+
+/// Waits up to a few seconds until `done` returns `true`
+fn poll(what: &str, mut done: impl FnMut() -> bool) {
+  let start = Instant::now();
+
+  while !done() {
+    assert!(start.elapsed() < Duration::from_secs(5), "{what}");
+    thread::sleep(Duration::from_millis(1));
+  }
+}
+
+/// Waits for the recorder thread to write everything up to `duration`
+fn wait_for(recorder: &Recorder, duration: Duration) {
+  poll("recorder didn't catch up", || {
+    recorder.recorded() >= duration
+  });
+}
+
+fn samples(path: &Path) -> Vec<f32> {
+  hound::WavReader::open(path)
+    .unwrap()
+    .into_samples::<f32>()
+    .map(Result::unwrap)
+    .collect()
+}
+
+#[test]
+fn records_every_block() {
+  let (_host, clock, recorder) = host();
+  let path = path("every-block");
+
+  recorder.start(&path).unwrap();
+
+  for _ in 0..10 {
+    clock.step();
+  }
+
+  wait_for(&recorder, Duration::from_secs_f64(5120. / 48000.));
+
+  let recording = recorder.stop().unwrap().unwrap();
+  let reader = hound::WavReader::open(&path).unwrap();
+
+  assert_eq!((recording.sample_rate, recording.channels), (48000, 2));
+  assert_eq!((recording.frames, recording.dropped), (5120, 0));
+  assert_eq!(reader.duration(), 5120);
+  assert!(reader
+    .into_samples::<f32>()
+    .any(|sample| sample.unwrap() != 0.));
+
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn cuts_at_the_blocks_captured_when_starting_and_stopping() {
+  let (_host, clock, recorder) = host();
+  let (first, second) = (path("cut-first"), path("cut-second"));
+
+  clock.step();
+  recorder.start(&first).unwrap();
+
+  for _ in 0..3 {
+    clock.step();
+  }
+
+  // Doesn't wait for the recorder thread, the blocks still in the queue go to the right file
+  let ended = recorder.start(&second).unwrap().unwrap();
+
+  clock.step();
+
+  let recording = recorder.stop().unwrap().unwrap();
+
+  assert_eq!(ended.frames, 512 * 3);
+  assert_eq!(recording.frames, 512);
+  assert_eq!(hound::WavReader::open(&first).unwrap().duration(), 512 * 3);
+  assert_eq!(hound::WavReader::open(&second).unwrap().duration(), 512);
+  assert!(!recorder.is_recording());
+
+  std::fs::remove_file(first).unwrap();
+  std::fs::remove_file(second).unwrap();
+}
+
+#[test]
+fn fails_once_the_format_changes() {
+  let (host, clock, recorder) = host();
+  let path = path("format-change");
+
+  recorder.start(&path).unwrap();
+  clock.step();
+  wait_for(&recorder, Duration::from_secs_f64(512. / 48000.));
+
+  host.change_device_by_index(1).unwrap();
+  clock.step();
+
+  // Waits for the recorder thread to get to the block of the new device
+  let result = recorder.stop();
+
+  assert!(matches!(result, Err(Error::FormatChanged(48000, 1))));
+  assert_eq!(hound::WavReader::open(&path).unwrap().duration(), 512);
+
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn fills_dropped_blocks_with_silence() {
+  let (host, clock, recorder) = host();
+  let path = path("dropped");
+
+  host.enable_worker(1).unwrap();
+  recorder.start(&path).unwrap();
+  clock.step();
+  wait_for(&recorder, Duration::from_secs_f64(512. / 48000.));
+
+  // Far faster than the worker keeps up with, so most of these get dropped
+  clock.advance_frames(512 * 200);
+  poll("worker didn't catch up", || {
+    host.worker_stats().unwrap().queued == 0
+  });
+  // Only dropped blocks before a block get filled
+  clock.step();
+  wait_for(&recorder, Duration::from_secs_f64(512. * 202. / 48000.));
+
+  let recording = recorder.stop().unwrap().unwrap();
+  let samples = samples(&path);
+  let silent = samples.iter().filter(|sample| **sample == 0.).count() as u64;
+
+  assert!(host.worker_stats().unwrap().dropped > 0);
+  assert!(recording.dropped >= host.worker_stats().unwrap().dropped * 512);
+  assert_eq!(recording.frames, 512 * 202);
+  assert_eq!(samples.len() as u64, recording.frames * 2);
+  assert!(silent >= recording.dropped * 2);
+
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn saves_what_the_history_has_so_far() {
+  let (_host, clock, recorder) = host();
+  let path = path("history-partial");
+
+  for _ in 0..10 {
+    clock.step();
+  }
+
+  // Waits for the recorder thread to get every block, nothing is being recorded
+  assert!(recorder.stop().unwrap().is_none());
+  assert_eq!(recorder.history(), Duration::from_secs_f64(5120. / 48000.));
+
+  let recording = recorder.save_history(&path).unwrap();
+
+  assert_eq!((recording.sample_rate, recording.channels), (48000, 2));
+  assert_eq!(recording.frames, 5120);
+  assert_eq!(samples(&path).len(), 5120 * 2);
+
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn history_keeps_the_last_samples() {
+  let (host, clock, recorder) = host();
+  let listener = host.create_listener::<Vec<f32>>();
+  let path = path("history-full");
+
+  // A bit more than the second of history
+  for _ in 0..100 {
+    clock.step();
+  }
+
+  assert!(recorder.stop().unwrap().is_none());
+  assert_eq!(recorder.history(), Duration::from_secs(1));
+
+  let recording = recorder.save_history(&path).unwrap();
+  let samples = samples(&path);
+
+  assert_eq!(recording.frames, 48000);
+  assert_eq!(samples.len(), 48000 * 2);
+  assert_eq!(&samples[samples.len() - 512 * 2..], &listener.poll()[..]);
+
+  std::fs::remove_file(path).unwrap();
+}
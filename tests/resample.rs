@@ -0,0 +1,149 @@
+use std::f64::consts::TAU;
+
+use safav::{BlockInfo, Interpolation, Resample};
+
+/// Linear sweep from `start` to `end` hz over `duration` seconds, sampled at `seconds`
+fn sweep(start: f64, end: f64, duration: f64, seconds: f64) -> f32 {
+  let phase = start * seconds + (end - start) * seconds * seconds / (2. * duration);
+
+  (TAU * phase).sin() as f32
+}
+
+/// Sweep sampled at `rate` for `duration` seconds
+fn source(rate: u32, start: f64, end: f64, duration: f64) -> Vec<f32> {
+  (0..(rate as f64 * duration) as usize)
+    .map(|frame| sweep(start, end, duration, frame as f64 / rate as f64))
+    .collect()
+}
+
+/// Resamples mono `input` in blocks of `block` frames
+fn resample(resample: &mut Resample, input: &[f32], from: u32, block: usize) -> Vec<f32> {
+  let mut output = Vec::new();
+  let mut samples = Vec::new();
+
+  for (index, chunk) in input.chunks(block).enumerate() {
+    let mut info = BlockInfo {
+      channels: 1,
+      sample_rate: from,
+      position: (index * block) as u64,
+      ..Default::default()
+    };
+
+    resample.resample(chunk, &mut samples, &mut info);
+    output.extend_from_slice(&samples);
+  }
+
+  output
+}
+
+/// Largest difference between the output and the sweep at the output rate,
+/// skipping the start where the kernel is still filling up
+fn max_error(
+  output: &[f32],
+  from: u32,
+  to: u32,
+  latency: usize,
+  (start, end, duration): (f64, f64, f64),
+) -> f32 {
+  let ratio = from as f64 / to as f64;
+
+  output
+    .iter()
+    .enumerate()
+    .skip(latency * 4)
+    .map(|(frame, sample)| {
+      // Output frames lag `latency` frames at the source rate behind the input
+      let seconds = (frame as f64 * ratio - latency as f64) / from as f64;
+
+      (sample - sweep(start, end, duration, seconds)).abs()
+    })
+    .fold(0., f32::max)
+}
+
+#[test]
+fn sinc_follows_a_sweep_when_downsampling() {
+  let sweep = (100., 8000., 1.);
+  let input = source(48000, sweep.0, sweep.1, sweep.2);
+  let mut resampler = Resample::new(44100);
+  let output = resample(&mut resampler, &input, 48000, 512);
+
+  assert!(output.len().abs_diff(44100) <= 1);
+  let error = max_error(&output, 48000, 44100, resampler.latency(), sweep);
+
+  assert!(error < 0.01, "off by {error}");
+}
+
+#[test]
+fn sinc_follows_a_sweep_when_upsampling() {
+  let sweep = (100., 8000., 1.);
+  let input = source(44100, sweep.0, sweep.1, sweep.2);
+  let mut resampler = Resample::new(48000);
+  let output = resample(&mut resampler, &input, 44100, 441);
+
+  // The last frame lands right on the end of the input, rounding decides if it's included
+  assert!(output.len().abs_diff(48000) <= 1);
+  let error = max_error(&output, 44100, 48000, resampler.latency(), sweep);
+
+  assert!(error < 0.01, "off by {error}");
+}
+
+#[test]
+fn linear_follows_a_slow_sweep() {
+  let sweep = (20., 200., 1.);
+  let input = source(48000, sweep.0, sweep.1, sweep.2);
+  let mut resampler = Resample::with_interpolation(44100, Interpolation::Linear);
+  let output = resample(&mut resampler, &input, 48000, 512);
+
+  let error = max_error(&output, 48000, 44100, resampler.latency(), sweep);
+
+  assert!(error < 0.01, "off by {error}");
+}
+
+#[test]
+fn sinc_filters_out_frequencies_above_the_lower_nyquist() {
+  let input = source(48000, 14000., 20000., 1.);
+  let mut resampler = Resample::new(16000);
+  let output = resample(&mut resampler, &input, 48000, 512);
+  let peak = output
+    .iter()
+    .skip(resampler.latency() * 4)
+    .fold(0f32, |peak, sample| peak.max(sample.abs()));
+
+  assert!(peak < 0.1, "aliasing peaks at {peak}");
+}
+
+#[test]
+fn block_size_doesnt_change_the_output() {
+  let input = source(48000, 100., 8000., 0.25);
+  let whole = resample(&mut Resample::new(44100), &input, 48000, input.len());
+  let blocks = resample(&mut Resample::new(44100), &input, 48000, 137);
+
+  assert_eq!(whole.len(), blocks.len());
+
+  for (whole, blocks) in whole.iter().zip(&blocks) {
+    assert!((whole - blocks).abs() < 1e-6);
+  }
+}
+
+#[test]
+fn gives_positions_at_the_output_rate() {
+  let mut resampler = Resample::new(24000);
+  let mut output = Vec::new();
+  let mut info = BlockInfo {
+    channels: 2,
+    sample_rate: 48000,
+    position: 0,
+    ..Default::default()
+  };
+
+  resampler.resample(&[0.; 2048], &mut output, &mut info);
+
+  assert_eq!((info.sample_rate, info.position), (24000, 0));
+  assert_eq!(output.len(), 1024);
+
+  info.sample_rate = 48000;
+  info.position = 1024;
+  resampler.resample(&[0.; 2048], &mut output, &mut info);
+
+  assert_eq!((info.sample_rate, info.position), (24000, 512));
+}
@@ -0,0 +1,118 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures::{
+  executor::{block_on, LocalPool},
+  task::LocalSpawnExt,
+  StreamExt,
+};
+use safav::{AudioData, Host, Lagged, MockClock, MockDevice, MockHost};
+
+/// Counts the blocks it was updated with, so every frame tells which block it came from
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Count(u64);
+
+impl AudioData for Count {
+  fn update(&mut self, _: &[f32]) {
+    self.0 += 1;
+  }
+}
+
+/// Listens to a mock device that only produces a block on [MockClock::step]
+fn host() -> (Host, MockClock) {
+  let mock = MockHost::new().with_device(MockDevice::new("Speakers"));
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+
+  host.listen().unwrap();
+
+  (host, clock)
+}
+
+#[test]
+fn yields_every_frame_in_order() {
+  let (host, clock) = host();
+  let mut stream = host.create_stream::<Count>(8);
+
+  for _ in 0..3 {
+    clock.step();
+  }
+
+  block_on(async {
+    for count in 1..=3 {
+      assert_eq!(stream.next().await, Some(Ok(Count(count))));
+    }
+  });
+
+  assert!(stream.is_empty());
+}
+
+#[test]
+fn reports_frames_dropped_while_full() {
+  let (host, clock) = host();
+  let mut stream = host.create_stream::<Count>(2);
+
+  for _ in 0..5 {
+    clock.step();
+  }
+
+  block_on(async {
+    assert_eq!(stream.next().await, Some(Ok(Count(1))));
+    assert_eq!(stream.next().await, Some(Ok(Count(2))));
+
+    clock.step();
+
+    assert_eq!(stream.next().await, Some(Err(Lagged(3))));
+    assert_eq!(stream.next().await, Some(Ok(Count(6))));
+  });
+}
+
+#[test]
+fn wakes_a_pending_task() {
+  let (host, clock) = host();
+  let mut stream = host.create_stream::<Count>(8);
+  let received = Rc::new(RefCell::new(Vec::new()));
+  let mut pool = LocalPool::new();
+
+  pool
+    .spawner()
+    .spawn_local({
+      let received = received.clone();
+
+      async move {
+        while let Some(frame) = stream.next().await {
+          received.borrow_mut().push(frame);
+        }
+      }
+    })
+    .unwrap();
+
+  pool.run_until_stalled();
+  assert!(received.borrow().is_empty());
+
+  clock.step();
+  pool.run_until_stalled();
+  assert_eq!(*received.borrow(), [Ok(Count(1))]);
+
+  clock.step();
+  clock.step();
+  pool.run_until_stalled();
+  assert_eq!(
+    *received.borrow(),
+    [Ok(Count(1)), Ok(Count(2)), Ok(Count(3))]
+  );
+}
+
+#[test]
+fn ends_once_removed() {
+  let (host, clock) = host();
+  let mut stream = host.create_stream::<Count>(8);
+
+  clock.step();
+  assert!(host.remove_listener(stream.id()));
+  clock.step();
+
+  block_on(async {
+    assert_eq!(stream.next().await, Some(Ok(Count(1))));
+    assert_eq!(stream.next().await, None);
+  });
+}
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use safav::{
+  Clock, Device, Error, Host, MockClock, MockDevice, MockHost, Signal, StreamState, StreamStates,
+  SupervisorSettings,
+};
+
+const SETTINGS: SupervisorSettings = SupervisorSettings {
+  stall_timeout: Duration::from_millis(200),
+  backoff: Duration::from_millis(10),
+  max_backoff: Duration::from_millis(20),
+  attempts_per_device: 2,
+  max_attempts: Some(4),
+};
+
+/// Host supervising a real time mock device called "Headphones", with "Speakers" as the default
+fn host() -> (Host, MockClock, StreamStates) {
+  let mock = MockHost::new()
+    .with_clock(Clock::RealTime)
+    .with_device(MockDevice::new("Speakers"))
+    .with_device(MockDevice::new("Headphones"));
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+  let states = host.stream_states();
+
+  host.listen().unwrap();
+  host.change_device_by_index(1).unwrap();
+  host.supervise(SETTINGS).unwrap();
+
+  (host, clock, states)
+}
+
+fn device(host: &Host, name: &str) -> Device {
+  host
+    .devices()
+    .iter()
+    .find(|device| device.name() == name)
+    .cloned()
+    .unwrap()
+}
+
+/// Receives as many states as `expected` has and checks they match
+fn expect(states: &StreamStates, expected: &[StreamState]) {
+  let received = expected
+    .iter()
+    .map_while(|_| states.recv_timeout(Duration::from_secs(5)))
+    .collect::<Vec<_>>();
+
+  assert_eq!(received, expected);
+}
+
+fn recovering(attempt: u32, device: &Device) -> StreamState {
+  StreamState::Recovering {
+    attempt,
+    device: device.clone(),
+  }
+}
+
+fn not_found(device: &Device) -> StreamState {
+  StreamState::Failed(Error::NoDeviceFound(device.name().to_string()).to_string())
+}
+
+#[test]
+fn needs_to_be_listening() {
+  let mut host = Host::mock(MockHost::new().with_device(MockDevice::new("Speakers")));
+
+  assert!(matches!(host.supervise(SETTINGS), Err(Error::NotListening)));
+}
+
+#[test]
+fn recovers_from_a_stream_error() {
+  let (host, clock, states) = host();
+  let listener = host.create_listener::<Vec<f32>>();
+  let headphones = device(&host, "Headphones");
+
+  expect(&states, &[StreamState::Running(headphones.clone())]);
+
+  clock.fail("injected error");
+
+  expect(
+    &states,
+    &[
+      StreamState::Failed(String::from("injected error")),
+      recovering(1, &headphones),
+      StreamState::Running(headphones),
+    ],
+  );
+  assert!(listener.wait_timeout(Duration::from_secs(1)).is_some());
+}
+
+#[test]
+fn recovers_from_a_stall() {
+  let (host, clock, states) = host();
+  let headphones = device(&host, "Headphones");
+
+  expect(&states, &[StreamState::Running(headphones.clone())]);
+
+  clock.stall();
+
+  expect(
+    &states,
+    &[
+      StreamState::Stalled,
+      recovering(1, &headphones),
+      StreamState::Running(headphones),
+    ],
+  );
+}
+
+#[test]
+fn falls_back_to_the_default_device_once_the_device_is_gone() {
+  let (host, clock, states) = host();
+  let listener = host.create_listener::<Vec<f32>>();
+  let headphones = device(&host, "Headphones");
+  let speakers = device(&host, "Speakers");
+
+  expect(&states, &[StreamState::Running(headphones.clone())]);
+
+  clock.set_unavailable("Headphones", true);
+
+  expect(
+    &states,
+    &[
+      StreamState::Failed(String::from("Headphones was removed")),
+      recovering(1, &headphones),
+      not_found(&headphones),
+      recovering(2, &headphones),
+      not_found(&headphones),
+      recovering(3, &speakers),
+      StreamState::Running(speakers.clone()),
+    ],
+  );
+  assert_eq!(host.current_device(), Some(&speakers));
+  assert!(listener.wait_timeout(Duration::from_secs(1)).is_some());
+}
+
+#[test]
+fn gives_up_after_max_attempts() {
+  let (host, clock, states) = host();
+  let headphones = device(&host, "Headphones");
+  let speakers = device(&host, "Speakers");
+
+  expect(&states, &[StreamState::Running(headphones.clone())]);
+
+  clock.set_unavailable("Speakers", true);
+  clock.set_unavailable("Headphones", true);
+
+  expect(
+    &states,
+    &[
+      StreamState::Failed(String::from("Headphones was removed")),
+      recovering(1, &headphones),
+      not_found(&headphones),
+      recovering(2, &headphones),
+      not_found(&headphones),
+      recovering(3, &speakers),
+      not_found(&speakers),
+      recovering(4, &speakers),
+      not_found(&speakers),
+      StreamState::GaveUp,
+    ],
+  );
+  assert!(states.recv_timeout(Duration::from_millis(500)).is_none());
+}
+
+#[test]
+fn waits_out_the_silence_of_a_device_that_stops_when_silent() {
+  let mock = MockHost::new()
+    .with_clock(Clock::RealTime)
+    .with_device(MockDevice::new("Loopback").with_stops_when_silent(true));
+  let clock = mock.clock();
+  let mut host = Host::mock(mock);
+  let states = host.stream_states();
+  let listener = host.create_listener::<Vec<f32>>();
+  let loopback = device(&host, "Loopback");
+
+  assert!(loopback.stops_when_silent());
+
+  host.listen().unwrap();
+  host.supervise(SETTINGS).unwrap();
+
+  expect(&states, &[StreamState::Running(loopback.clone())]);
+  assert!(listener.wait_timeout(Duration::from_millis(500)).is_none());
+  assert!(states.recv_timeout(Duration::from_millis(500)).is_none());
+
+  clock.set_signal(Signal::Sine {
+    frequency: 440.,
+    amplitude: 1.,
+  });
+
+  assert!(listener.wait_timeout(Duration::from_secs(1)).is_some());
+
+  clock.fail("injected error");
+
+  expect(
+    &states,
+    &[
+      StreamState::Failed(String::from("injected error")),
+      recovering(1, &loopback),
+      StreamState::Running(loopback),
+    ],
+  );
+}